@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Paths exempt from admission checks by default, so load balancer health probes and metrics
+/// scrapers keep working while the server sheds load.
+pub const DEFAULT_EXEMPT_PATHS: &[&str] = &["/health", "/health/live", "/metrics", "/info"];
+
+/// Returns true if `path` is one of `exempt_paths`. A trailing `/` on either is ignored.
+pub fn is_exempt(path: &str, exempt_paths: &[String]) -> bool {
+    let path = path.strip_suffix('/').filter(|p| !p.is_empty()).unwrap_or(path);
+    exempt_paths
+        .iter()
+        .any(|exempt| exempt.strip_suffix('/').filter(|e| !e.is_empty()).unwrap_or(exempt) == path)
+}
+
+/// Whether a request only reads or may change state, for separate admission limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestClass {
+    /// `GET`, `HEAD` and `OPTIONS` requests.
+    Read,
+    /// Every other method.
+    Write,
+}
+
+impl RequestClass {
+    pub fn from_method(method: &str) -> Self {
+        match method {
+            "GET" | "HEAD" | "OPTIONS" => RequestClass::Read,
+            _ => RequestClass::Write,
+        }
+    }
+}
+
+/// Caps on the number of requests handled at the same time. `None` leaves a cap unset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AdmissionLimits {
+    /// Cap on all requests together.
+    pub total: Option<usize>,
+    /// Cap on read requests.
+    pub reads: Option<usize>,
+    /// Cap on write requests.
+    pub writes: Option<usize>,
+}
+
+/// Proof that a request was admitted. The request's slots are freed when it is dropped.
+#[derive(Debug)]
+pub struct Admission {
+    _total: Option<OwnedSemaphorePermit>,
+    _class: Option<OwnedSemaphorePermit>,
+}
+
+/// Admits requests while fewer than the configured number are in flight.
+///
+/// Requests are never queued: when a limit is reached, new requests are turned
+/// away immediately so the server sheds load instead of letting every request
+/// time out waiting on the same exhausted resources.
+#[derive(Debug, Clone)]
+pub struct AdmissionControl {
+    total: Option<Arc<Semaphore>>,
+    reads: Option<Arc<Semaphore>>,
+    writes: Option<Arc<Semaphore>>,
+}
+
+impl AdmissionControl {
+    pub fn new(limits: AdmissionLimits) -> Self {
+        let semaphore = |limit: Option<usize>| limit.map(|n| Arc::new(Semaphore::new(n)));
+        Self {
+            total: semaphore(limits.total),
+            reads: semaphore(limits.reads),
+            writes: semaphore(limits.writes),
+        }
+    }
+
+    /// True if any limit is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.total.is_some() || self.reads.is_some() || self.writes.is_some()
+    }
+
+    /// Takes a slot for a request of `class` under the overall limit and the
+    /// limit for its class. Returns `None`, taking nothing, if either is full.
+    pub fn try_admit(&self, class: RequestClass) -> Option<Admission> {
+        let acquire = |semaphore: &Option<Arc<Semaphore>>| match semaphore {
+            Some(semaphore) => semaphore.clone().try_acquire_owned().ok().map(Some),
+            None => Some(None),
+        };
+        let class_semaphore = match class {
+            RequestClass::Read => &self.reads,
+            RequestClass::Write => &self.writes,
+        };
+        let total = acquire(&self.total)?;
+        let class = acquire(class_semaphore)?;
+        Some(Admission { _total: total, _class: class })
+    }
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+/// Longest attachment name accepted, in bytes.
+pub const MAX_NAME_LENGTH: usize = 255;
+
+/// A file stored alongside a prompt, without its contents.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentInfo {
+    pub name: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+}
+
+/// Returns true if `name` can be used as an attachment name: letters, digits,
+/// `.`, `-` and `_` only, not starting with a dot. This keeps names safe to use
+/// as file names and in URLs.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
+
+/// Guesses the media type to serve an attachment with from its extension.
+/// Types a browser could run scripts from, such as SVG, are served as
+/// `application/octet-stream`.
+pub fn content_type(name: &str) -> &'static str {
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("pdf") => "application/pdf",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("csv") => "text/csv; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,131 @@
+use crate::admission;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::{Error, HttpResponse};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Environment variable read for the API key when `--api-key` isn't given.
+pub const API_KEY_ENV: &str = "MCP_PROMPTS_API_KEY";
+
+/// Paths always served without an API key, so health probes keep working.
+pub const UNAUTHENTICATED_PATHS: &[&str] = &["/health"];
+
+/// Middleware requiring an `Authorization: Bearer <token>` header on every request,
+/// except to [`UNAUTHENTICATED_PATHS`] and any added with [`ApiKeyAuth::open_paths`].
+/// Requests without an accepted token get `401 Unauthorized`. Without any configured
+/// token it lets every request through.
+#[derive(Clone, Default)]
+pub struct ApiKeyAuth {
+    tokens: Arc<[String]>,
+    open_paths: Arc<[String]>,
+}
+
+impl ApiKeyAuth {
+    /// Requires `api_key`, or nothing if it is `None` or blank.
+    pub fn new(api_key: Option<String>) -> Self {
+        Self {
+            tokens: api_key.into_iter().filter(|key| !key.trim().is_empty()).collect(),
+            open_paths: UNAUTHENTICATED_PATHS.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    /// Also serves `paths` without a token, such as metrics scrapes. Paths match
+    /// exactly, ignoring a trailing `/`.
+    pub fn open_paths(mut self, paths: impl IntoIterator<Item = String>) -> Self {
+        self.open_paths = self.open_paths.iter().cloned().chain(paths).collect();
+        self
+    }
+
+    /// Also accepts `token`, e.g. the admin token, so its holder doesn't need both.
+    /// Doesn't enable authentication on its own.
+    pub fn also_accept(mut self, token: Option<String>) -> Self {
+        if self.is_enabled() {
+            if let Some(token) = token.filter(|t| !t.trim().is_empty()) {
+                self.tokens = self.tokens.iter().cloned().chain(Some(token)).collect();
+            }
+        }
+        self
+    }
+
+    /// True if requests must carry a token.
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// True if the request may proceed.
+    fn allows(&self, req: &ServiceRequest) -> bool {
+        if !self.is_enabled() || admission::is_exempt(req.path(), &self.open_paths) {
+            return true;
+        }
+        let Some(presented) = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+        else {
+            return false;
+        };
+        // Check every token without stopping early, so timing doesn't reveal which matched
+        self.tokens
+            .iter()
+            .fold(false, |matched, token| constant_time_eq(presented.as_bytes(), token.as_bytes()) | matched)
+    }
+}
+
+/// Compares in time independent of where the inputs first differ, for checking secrets.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ApiKeyAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = ApiKeyAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiKeyAuthMiddleware {
+            service: Rc::new(service),
+            auth: self.clone(),
+        }))
+    }
+}
+
+/// Service created by [`ApiKeyAuth`] for each worker.
+pub struct ApiKeyAuthMiddleware<S> {
+    service: Rc<S>,
+    auth: ApiKeyAuth,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiKeyAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if self.auth.allows(&req) {
+            let response = self.service.call(req);
+            return Box::pin(async move { Ok(response.await?.map_into_left_body()) });
+        }
+        tracing::warn!(method = %req.method(), path = %req.path(), "Rejected request without a valid API key");
+        let response = HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .body("A valid API key is required");
+        Box::pin(async move { Ok(req.into_response(response).map_into_right_body()) })
+    }
+}
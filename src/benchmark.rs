@@ -0,0 +1,101 @@
+use crate::models::prompt::Prompt;
+use crate::storage::PromptStorage;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Latency percentiles for one kind of storage operation, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LatencyPercentiles {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl LatencyPercentiles {
+    /// Computes nearest-rank percentiles. `samples` must not be empty.
+    pub fn from_samples(samples: &mut [Duration]) -> Self {
+        samples.sort_unstable();
+        let at = |pct: usize| {
+            let rank = (samples.len() * pct).div_ceil(100).max(1);
+            samples[rank - 1].as_secs_f64() * 1000.0
+        };
+        Self {
+            p50_ms: at(50),
+            p95_ms: at(95),
+            p99_ms: at(99),
+        }
+    }
+}
+
+/// Timings from a storage benchmark run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchmarkReport {
+    pub storage: &'static str,
+    pub ops: usize,
+    pub total_ms: f64,
+    pub write: LatencyPercentiles,
+    pub read: LatencyPercentiles,
+    pub delete: LatencyPercentiles,
+    /// A full write + read + delete cycle.
+    pub cycle: LatencyPercentiles,
+}
+
+/// Runs `ops` write/read/delete cycles against scratch prompts and reports latency
+/// percentiles per operation.
+///
+/// Each scratch prompt is deleted before the next cycle starts. Backends that keep
+/// deletion tombstones will keep one per scratch id.
+pub async fn run_benchmark(storage: &dyn PromptStorage, ops: usize) -> Result<BenchmarkReport> {
+    if ops == 0 {
+        bail!("Benchmark needs at least one operation");
+    }
+
+    let mut writes = Vec::with_capacity(ops);
+    let mut reads = Vec::with_capacity(ops);
+    let mut deletes = Vec::with_capacity(ops);
+    let mut cycles = Vec::with_capacity(ops);
+    let started = Instant::now();
+
+    for i in 0..ops {
+        let scratch = Prompt::new(
+            format!("__benchmark_{}__", i),
+            "Benchmark prompt for {{ name }}".to_string(),
+            Some("benchmark".to_string()),
+            Some(vec!["name".to_string()]),
+        );
+        let cycle_start = Instant::now();
+
+        let start = Instant::now();
+        storage
+            .save_prompt(&scratch)
+            .await
+            .context("Benchmark failed to write scratch prompt")?;
+        writes.push(start.elapsed());
+
+        let start = Instant::now();
+        let read_back = storage.get_prompt(&scratch.id).await;
+        reads.push(start.elapsed());
+
+        let start = Instant::now();
+        // Delete before checking the read so a failure never leaves the scratch prompt behind
+        let deleted = storage.delete_prompt(&scratch.id).await;
+        deletes.push(start.elapsed());
+        cycles.push(cycle_start.elapsed());
+
+        if read_back.context("Benchmark failed to read scratch prompt")?.is_none() {
+            bail!("Benchmark could not find scratch prompt {} after writing it", scratch.id);
+        }
+        deleted.context("Benchmark failed to delete scratch prompt")?;
+    }
+
+    Ok(BenchmarkReport {
+        storage: storage.backend_name(),
+        ops,
+        total_ms: started.elapsed().as_secs_f64() * 1000.0,
+        write: LatencyPercentiles::from_samples(&mut writes),
+        read: LatencyPercentiles::from_samples(&mut reads),
+        delete: LatencyPercentiles::from_samples(&mut deletes),
+        cycle: LatencyPercentiles::from_samples(&mut cycles),
+    })
+}
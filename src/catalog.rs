@@ -0,0 +1,127 @@
+use crate::models::prompt::{normalize_tags, Prompt};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// Metadata key under which the catalog's own (usually non-UUID) id is kept, so
+/// importing the same catalog again updates the prompts instead of duplicating them.
+pub const CATALOG_ID_KEY: &str = "catalog_id";
+
+/// Catalog fields that are mapped onto the prompt model. Anything else is reported.
+const MAPPED_FIELDS: &[&str] = &[
+    "id", "name", "description", "content", "messages", "category", "tags", "arguments",
+    "variables", "metadata",
+];
+
+/// A prompt converted from one catalog entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogImport {
+    pub prompt: Prompt,
+    /// The entry's `id` when it isn't a UUID, also stored in metadata under [`CATALOG_ID_KEY`].
+    pub catalog_id: Option<String>,
+    /// Top-level fields of the entry that have no equivalent and were dropped.
+    pub unmapped: Vec<String>,
+}
+
+/// Extracts the entries of a catalog document: either an array of prompts or an
+/// object holding them under `prompts`.
+pub fn catalog_entries(document: Value) -> Result<Vec<Map<String, Value>>, String> {
+    let entries = match document {
+        Value::Array(entries) => entries,
+        Value::Object(mut object) => match object.remove("prompts") {
+            Some(Value::Array(entries)) => entries,
+            _ => return Err("Catalog object must contain a `prompts` array".to_string()),
+        },
+        _ => return Err("Catalog must be an array of prompts or an object with a `prompts` array".to_string()),
+    };
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| match entry {
+            Value::Object(entry) => Ok(entry),
+            _ => Err(format!("Catalog entry {} is not an object", i)),
+        })
+        .collect()
+}
+
+/// Maps one catalog entry onto a [`Prompt`].
+///
+/// `content` is taken as is; without it, the text of every entry in `messages` is
+/// joined with blank lines. Variable names come from `arguments` or `variables`
+/// (plain names or objects with a `name`). String `tags` become the prompt's tags;
+/// `description` goes into metadata along with the entry's own `metadata` object.
+pub fn map_entry(entry: &Map<String, Value>) -> Result<CatalogImport, String> {
+    let name = entry
+        .get("name")
+        .and_then(Value::as_str)
+        .filter(|name| !name.trim().is_empty())
+        .ok_or_else(|| "Catalog entry has no `name`".to_string())?;
+    let content = match entry.get("content") {
+        Some(Value::String(content)) => content.clone(),
+        _ => messages_text(entry.get("messages"))
+            .ok_or_else(|| format!("Catalog entry '{}' has neither `content` nor text `messages`", name))?,
+    };
+    let category = entry.get("category").and_then(Value::as_str).map(str::to_string);
+    let variables = variable_names(entry.get("arguments")).or_else(|| variable_names(entry.get("variables")));
+
+    let mut prompt = Prompt::new(name.to_string(), content, category, variables);
+    if let Some(Value::Object(metadata)) = entry.get("metadata") {
+        prompt.metadata = metadata.clone();
+    }
+    if let Some(value) = entry.get("description").filter(|v| !v.is_null()) {
+        prompt.metadata.insert("description".to_string(), value.clone());
+    }
+    if let Some(Value::Array(tags)) = entry.get("tags") {
+        let tags: Vec<String> = tags.iter().filter_map(Value::as_str).map(str::to_string).collect();
+        prompt.tags = normalize_tags(&tags);
+    }
+
+    let mut catalog_id = None;
+    match entry.get("id") {
+        Some(Value::String(id)) => match Uuid::parse_str(id) {
+            Ok(uuid) => prompt.id = uuid,
+            Err(_) => {
+                prompt.metadata.insert(CATALOG_ID_KEY.to_string(), Value::String(id.clone()));
+                catalog_id = Some(id.clone());
+            }
+        },
+        Some(Value::Number(id)) => {
+            prompt.metadata.insert(CATALOG_ID_KEY.to_string(), Value::String(id.to_string()));
+            catalog_id = Some(id.to_string());
+        }
+        _ => {}
+    }
+
+    let unmapped = entry
+        .keys()
+        .filter(|key| !MAPPED_FIELDS.contains(&key.as_str()))
+        .cloned()
+        .collect();
+    Ok(CatalogImport { prompt, catalog_id, unmapped })
+}
+
+/// Joins the text of MCP-style messages, whose `content` is a string or a
+/// `{"type": "text", "text": ...}` object.
+fn messages_text(messages: Option<&Value>) -> Option<String> {
+    let texts: Vec<&str> = messages?
+        .as_array()?
+        .iter()
+        .filter_map(|message| match message.get("content")? {
+            Value::String(text) => Some(text.as_str()),
+            content => content.get("text")?.as_str(),
+        })
+        .collect();
+    (!texts.is_empty()).then(|| texts.join("\n\n"))
+}
+
+/// Names from a list of variable names or of argument objects with a `name`.
+fn variable_names(list: Option<&Value>) -> Option<Vec<String>> {
+    let names: Vec<String> = list?
+        .as_array()?
+        .iter()
+        .filter_map(|item| match item {
+            Value::String(name) => Some(name.clone()),
+            item => item.get("name")?.as_str().map(str::to_string),
+        })
+        .collect();
+    (!names.is_empty()).then_some(names)
+}
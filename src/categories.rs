@@ -0,0 +1,90 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Separator between levels of a hierarchical category, e.g. `writing/marketing/email`.
+pub const CATEGORY_SEPARATOR: char = '/';
+
+/// A category in the hierarchy, with counts and its subcategories.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CategoryNode {
+    /// Last segment of the path, e.g. `email`.
+    pub name: String,
+    /// Full path from the root, e.g. `writing/marketing/email`.
+    pub path: String,
+    /// Prompts whose category is exactly this path.
+    pub prompt_count: usize,
+    /// Prompts in this category or any subcategory.
+    pub total_count: usize,
+    pub children: Vec<CategoryNode>,
+}
+
+/// Splits a category into its path segments, trimming whitespace and ignoring
+/// empty segments, so `" writing//marketing/ "` and `writing/marketing` are the same.
+pub fn category_segments(category: &str) -> Vec<&str> {
+    category
+        .split(CATEGORY_SEPARATOR)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    prompt_count: usize,
+    children: BTreeMap<String, TreeBuilder>,
+}
+
+impl TreeBuilder {
+    fn into_nodes(self, parent_path: &str) -> Vec<CategoryNode> {
+        self.children
+            .into_iter()
+            .map(|(name, builder)| {
+                let path = if parent_path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}{}{}", parent_path, CATEGORY_SEPARATOR, name)
+                };
+                let prompt_count = builder.prompt_count;
+                let children = builder.into_nodes(&path);
+                let total_count = prompt_count + children.iter().map(|c| c.total_count).sum::<usize>();
+                CategoryNode {
+                    name,
+                    path,
+                    prompt_count,
+                    total_count,
+                    children,
+                }
+            })
+            .collect()
+    }
+}
+
+/// True if `category` is `prefix` or lies below it, matching whole segments, so
+/// `writing` (or `writing/`) covers `writing/email` but not `writingtools`.
+pub fn in_subtree(category: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches(CATEGORY_SEPARATOR);
+    if prefix.is_empty() {
+        return true;
+    }
+    category
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(CATEGORY_SEPARATOR))
+}
+
+/// Builds the hierarchy of the given prompt categories (one per prompt), sorted by
+/// name at every level.
+pub fn category_tree<'a>(categories: impl IntoIterator<Item = &'a str>) -> Vec<CategoryNode> {
+    let mut root = TreeBuilder::default();
+    for category in categories {
+        let segments = category_segments(category);
+        if segments.is_empty() {
+            continue;
+        }
+        let mut node = &mut root;
+        for segment in segments {
+            node = node.children.entry(segment.to_string()).or_default();
+        }
+        node.prompt_count += 1;
+    }
+    root.into_nodes("")
+}
@@ -0,0 +1,139 @@
+use crate::models::prompt::VariableMetadata;
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Reserved render-context key that holds the shared constants.
+pub const GLOBALS_KEY: &str = "globals";
+
+/// Shared constants loaded from a JSON file and exposed to every render as `globals`.
+#[derive(Debug, Default)]
+pub struct GlobalContext {
+    path: Option<PathBuf>,
+    globals: RwLock<Map<String, Value>>,
+}
+
+impl GlobalContext {
+    /// An empty context with no backing file.
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Loads the context from a JSON file containing an object.
+    pub async fn from_file(path: impl Into<PathBuf>) -> Result<Self> {
+        let context = Self {
+            path: Some(path.into()),
+            globals: RwLock::new(Map::new()),
+        };
+        context.reload().await?;
+        Ok(context)
+    }
+
+    /// Re-reads the backing file, keeping the previous globals if it fails.
+    /// Returns the number of top-level keys loaded.
+    pub async fn reload(&self) -> Result<usize> {
+        let Some(path) = &self.path else {
+            return Ok(0);
+        };
+        let globals = read_object_file(path, "context file").await?;
+        let count = globals.len();
+        *self.globals.write().unwrap_or_else(|e| e.into_inner()) = globals;
+        Ok(count)
+    }
+
+    /// Returns the render arguments with the globals added under [`GLOBALS_KEY`].
+    pub fn apply(&self, arguments: &Map<String, Value>) -> Map<String, Value> {
+        let globals = self.globals.read().unwrap_or_else(|e| e.into_inner());
+        with_globals(&globals, arguments)
+    }
+}
+
+/// Merges `globals` into the render arguments under [`GLOBALS_KEY`].
+///
+/// Caller-supplied arguments win on clashes: a `globals` object argument overrides
+/// individual keys, and any other `globals` argument replaces the globals entirely.
+pub fn with_globals(globals: &Map<String, Value>, arguments: &Map<String, Value>) -> Map<String, Value> {
+    let mut merged = arguments.clone();
+    let combined = match arguments.get(GLOBALS_KEY) {
+        Some(Value::Object(overrides)) => {
+            let mut combined = globals.clone();
+            combined.extend(overrides.iter().map(|(k, v)| (k.clone(), v.clone())));
+            Value::Object(combined)
+        }
+        Some(other) => other.clone(),
+        None => Value::Object(globals.clone()),
+    };
+    merged.insert(GLOBALS_KEY.to_string(), combined);
+    merged
+}
+
+/// Reads a JSON file that must contain an object, such as a context or default
+/// arguments file. `kind` names the file in error messages, e.g. `"context file"`.
+pub async fn read_object_file(path: &Path, kind: &str) -> Result<Map<String, Value>> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read {} '{}'", kind, path.display()))?;
+    match serde_json::from_str::<Value>(&contents)
+        .with_context(|| format!("Failed to parse {} '{}'", kind, path.display()))?
+    {
+        Value::Object(map) => Ok(map),
+        _ => {
+            let mut chars = kind.chars();
+            let kind: String = chars.next().into_iter().flat_map(char::to_uppercase).chain(chars).collect();
+            bail!("{} '{}' must contain a JSON object", kind, path.display())
+        }
+    }
+}
+
+/// Layers default arguments under the caller's render arguments.
+///
+/// Precedence, highest first: request arguments, the prompt's own defaults, then
+/// server-wide defaults. Only top-level keys are merged; a higher layer replaces a
+/// lower layer's value for the same key entirely.
+pub fn with_defaults(
+    server_defaults: &Map<String, Value>,
+    prompt_defaults: &Map<String, Value>,
+    arguments: &Map<String, Value>,
+) -> Map<String, Value> {
+    let mut merged = server_defaults.clone();
+    merged.extend(prompt_defaults.iter().map(|(k, v)| (k.clone(), v.clone())));
+    merged.extend(arguments.iter().map(|(k, v)| (k.clone(), v.clone())));
+    merged
+}
+
+/// Renames arguments supplied under a variable's alias to the variable's own name.
+///
+/// An argument given under the canonical name wins over its aliases; otherwise the
+/// first alias in declaration order wins. Supplying more than one is logged as a
+/// conflict. Alias keys are removed from the returned arguments.
+pub fn resolve_aliases(
+    variables: &BTreeMap<String, VariableMetadata>,
+    arguments: &Map<String, Value>,
+) -> Map<String, Value> {
+    let mut resolved = arguments.clone();
+    for (name, metadata) in variables {
+        let supplied: Vec<&str> = metadata
+            .aliases
+            .iter()
+            .map(String::as_str)
+            .filter(|alias| *alias != name && resolved.contains_key(*alias))
+            .collect();
+        if supplied.is_empty() {
+            continue;
+        }
+        if supplied.len() > 1 || resolved.contains_key(name) {
+            tracing::warn!(
+                variable = %name,
+                aliases = ?supplied,
+                "Several names supplied for the same variable; keeping the canonical name or first alias"
+            );
+        }
+        let values: Vec<Value> = supplied.iter().filter_map(|alias| resolved.remove(*alias)).collect();
+        if let Some(value) = values.into_iter().next() {
+            resolved.entry(name.clone()).or_insert(value);
+        }
+    }
+    resolved
+}
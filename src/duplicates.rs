@@ -0,0 +1,99 @@
+use crate::models::prompt::Prompt;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Prompts whose content is byte-for-byte identical.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExactDuplicateGroup {
+    /// SHA-256 of the shared content, hex encoded.
+    pub content_hash: String,
+    pub prompt_ids: Vec<Uuid>,
+}
+
+/// Prompts whose content is similar above the requested threshold.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SimilarGroup {
+    pub prompt_ids: Vec<Uuid>,
+}
+
+/// Result of scanning a prompt library for duplicates.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DuplicateReport {
+    pub exact: Vec<ExactDuplicateGroup>,
+    /// Only present when a similarity threshold was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similar: Option<Vec<SimilarGroup>>,
+}
+
+/// Hex-encoded SHA-256 of prompt content.
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Groups prompts with identical content and, if `similarity` is given, clusters
+/// distinct contents whose normalized Levenshtein similarity is at least that value.
+///
+/// Fuzzy matching compares every pair of distinct contents, so it is quadratic in
+/// library size and intended for occasional hygiene checks.
+pub fn find_duplicates(prompts: &[Prompt], similarity: Option<f64>) -> DuplicateReport {
+    let mut by_hash: BTreeMap<String, (&str, Vec<Uuid>)> = BTreeMap::new();
+    for prompt in prompts {
+        by_hash
+            .entry(content_hash(&prompt.content))
+            .or_insert_with(|| (prompt.content.as_str(), Vec::new()))
+            .1
+            .push(prompt.id);
+    }
+
+    let exact = by_hash
+        .iter()
+        .filter(|(_, (_, ids))| ids.len() > 1)
+        .map(|(hash, (_, ids))| ExactDuplicateGroup {
+            content_hash: hash.clone(),
+            prompt_ids: ids.clone(),
+        })
+        .collect();
+
+    let similar = similarity.map(|threshold| {
+        let groups: Vec<&(&str, Vec<Uuid>)> = by_hash.values().collect();
+        let mut parent: Vec<usize> = (0..groups.len()).collect();
+
+        for i in 0..groups.len() {
+            for j in (i + 1)..groups.len() {
+                if strsim::normalized_levenshtein(groups[i].0, groups[j].0) >= threshold {
+                    let (root_i, root_j) = (find_root(&mut parent, i), find_root(&mut parent, j));
+                    if root_i != root_j {
+                        parent[root_j] = root_i;
+                    }
+                }
+            }
+        }
+
+        let mut clusters: BTreeMap<usize, Vec<Uuid>> = BTreeMap::new();
+        let mut sizes: BTreeMap<usize, usize> = BTreeMap::new();
+        for (i, (_, ids)) in groups.iter().enumerate() {
+            let root = find_root(&mut parent, i);
+            clusters.entry(root).or_default().extend(ids.iter().copied());
+            *sizes.entry(root).or_default() += 1;
+        }
+
+        // Clusters made of a single exact group are already reported above
+        clusters
+            .into_iter()
+            .filter(|(root, _)| sizes[root] > 1)
+            .map(|(_, prompt_ids)| SimilarGroup { prompt_ids })
+            .collect()
+    });
+
+    DuplicateReport { exact, similar }
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
@@ -0,0 +1,59 @@
+/// Renders a minimal standalone HTML page for an HTTP error, for browsers
+/// hitting the API directly. `message` is escaped before insertion.
+pub fn render_error_page(status: u16, reason: &str, message: &str) -> String {
+    let reason = escape_html(reason);
+    let message = message.trim();
+    let detail = if message.is_empty() {
+        String::new()
+    } else {
+        format!("\n    <p>{}</p>", escape_html(message))
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>{status} {reason}</title>
+  <style>
+    body {{ font-family: system-ui, sans-serif; background: #f6f7f9; color: #1f2328; margin: 0; }}
+    main {{ max-width: 40rem; margin: 15vh auto; padding: 2rem; background: #fff; border-radius: 8px; box-shadow: 0 1px 4px rgba(0, 0, 0, .1); }}
+    h1 {{ margin-top: 0; font-size: 1.5rem; }}
+    .status {{ color: #cf222e; }}
+    a {{ color: #0969da; }}
+  </style>
+</head>
+<body>
+  <main>
+    <h1><span class="status">{status}</span> {reason}</h1>{detail}
+    <p><a href="/info">Server info</a> &middot; <a href="/prompts">Prompts</a></p>
+  </main>
+</body>
+</html>
+"#
+    )
+}
+
+/// Extracts a human-readable message from an error response body: the `error`
+/// field when the body is a JSON object, otherwise the body text itself.
+pub fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or_else(|| body.to_string())
+}
+
+/// Escapes the characters that are significant in HTML text and attributes.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,40 @@
+use crate::models::prompt::Prompt;
+use std::fmt::Write;
+
+/// Renders prompts as a single Markdown document, one section per prompt.
+///
+/// Each section has the prompt name as a heading, its metadata as a list and
+/// its content in a fenced code block.
+pub fn prompts_to_markdown(prompts: &[Prompt]) -> String {
+    let mut doc = String::from("# Prompts\n");
+
+    for prompt in prompts {
+        let _ = write!(doc, "\n## {}\n\n", prompt.name.trim());
+        let _ = writeln!(doc, "- **ID:** `{}`", prompt.id);
+        if let Some(category) = &prompt.category {
+            let _ = writeln!(doc, "- **Category:** {}", category);
+        }
+        if !prompt.tags.is_empty() {
+            let _ = writeln!(doc, "- **Tags:** {}", prompt.tags.join(", "));
+        }
+        if let Some(variables) = prompt.variables.as_ref().filter(|v| !v.is_empty()) {
+            let names: Vec<String> = variables.iter().map(|v| format!("`{}`", v)).collect();
+            let _ = writeln!(doc, "- **Variables:** {}", names.join(", "));
+        }
+
+        let fence = code_fence_for(&prompt.content);
+        let _ = write!(doc, "\n{}\n{}\n{}\n", fence, prompt.content.trim_end(), fence);
+    }
+    doc
+}
+
+/// Returns a backtick fence longer than any backtick run in `content`,
+/// so content containing its own code blocks can't close the fence early.
+fn code_fence_for(content: &str) -> String {
+    let longest_run = content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest_run.max(2) + 1)
+}
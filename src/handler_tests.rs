@@ -0,0 +1,571 @@
+//! Tests for the server binary: the REST handlers, driven through an in-process
+//! actix app, and the startup helpers.
+
+use super::*;
+use actix_web::test;
+use mcp_prompts_rs::storage::filesystem::FileSystemStorage;
+use mcp_prompts_rs::storage::memory::InMemoryStorage;
+
+fn memory_storage() -> Arc<dyn PromptStorage> {
+    Arc::new(InMemoryStorage::new())
+}
+
+fn cli(flags: &[&str]) -> Cli {
+    Cli::parse_from(std::iter::once("mcp-prompts-rs").chain(flags.iter().copied()))
+}
+
+/// Registers the routes with the app state the server builds from `flags`.
+fn app_state(flags: &[&str], storage: Arc<dyn PromptStorage>) -> impl FnOnce(&mut web::ServiceConfig) {
+    let args = cli(flags);
+    let config = AppConfig::from_cli(&args);
+    let metrics = Metrics::new().unwrap();
+    let storage: Arc<dyn PromptStorage> = Arc::new(InstrumentedStorage::new(storage, metrics.clone()));
+    move |cfg| {
+        cfg.app_data(web::Data::new(storage))
+            .app_data(web::Data::new(metrics))
+            .app_data(web::Data::new(config))
+            .app_data(web::Data::new(RenderQuota::new()))
+            .app_data(web::Data::new(GlobalContext::empty()))
+            .app_data(web::Data::new(StartedAt(Instant::now())))
+            .app_data(web::Data::new(WebhookDispatcher::disabled()));
+        configure_routes(cfg);
+    }
+}
+
+#[actix_web::test]
+async fn browsers_get_an_html_error_page() {
+    let app = test::init_service(
+        App::new()
+            .wrap(html_error_handlers())
+            .configure(app_state(&["--html-errors"], memory_storage())),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/no/such/route")
+        .insert_header((header::ACCEPT, "text/html,application/xhtml+xml"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(res.headers().get(header::CONTENT_TYPE).unwrap(), "text/html; charset=utf-8");
+    let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+    assert!(body.starts_with("<!DOCTYPE html>"));
+    assert!(body.contains("No route for GET /no/such/route"));
+}
+
+#[actix_web::test]
+async fn api_clients_keep_json_errors_with_html_errors_enabled() {
+    let app = test::init_service(
+        App::new()
+            .wrap(html_error_handlers())
+            .configure(app_state(&["--html-errors"], memory_storage())),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/no/such/route")
+        .insert_header((header::ACCEPT, "application/json"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["error"], "No route for GET /no/such/route");
+}
+
+#[actix_web::test]
+async fn only_requests_accepting_html_count_as_browsers() {
+    let browser = test::TestRequest::default()
+        .insert_header((header::ACCEPT, "text/html"))
+        .to_http_request();
+    assert!(accepts_html(&browser));
+    assert!(!accepts_html(&test::TestRequest::default().to_http_request()));
+}
+
+async fn save(storage: &Arc<dyn PromptStorage>, name: &str, content: &str) -> Prompt {
+    let prompt = Prompt::new(name.to_string(), content.to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+    prompt
+}
+
+#[actix_web::test]
+async fn delete_with_a_stale_if_match_version_is_rejected() {
+    let storage = memory_storage();
+    let mut prompt = save(&storage, "guarded", "first").await;
+    prompt.version += 1;
+    storage.save_prompt(&prompt).await.unwrap();
+    let stale_etag = prompt.etag();
+    // Changed in place without a version bump, as an owner transfer does
+    prompt.owner = Some("alice".to_string());
+    storage.save_prompt(&prompt).await.unwrap();
+    let app = test::init_service(App::new().configure(app_state(&[], storage.clone()))).await;
+
+    for stale in ["\"1\"".to_string(), stale_etag] {
+        let req = test::TestRequest::delete()
+            .uri(&format!("/prompts/{}", prompt.id))
+            .insert_header((header::IF_MATCH, stale.as_str()))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED, "{}", stale);
+        assert_eq!(res.headers().get(header::ETAG).unwrap().to_str().unwrap(), prompt.etag());
+        assert!(storage.get_prompt(&prompt.id).await.unwrap().is_some());
+    }
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/prompts/{}", prompt.id))
+        .insert_header((header::IF_MATCH, format!("\"1\", {}", prompt.etag())))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(storage.get_prompt(&prompt.id).await.unwrap(), None);
+}
+
+#[actix_web::test]
+async fn update_with_the_current_etag_succeeds() {
+    let storage = memory_storage();
+    let prompt = save(&storage, "guarded", "first").await;
+    let app = test::init_service(App::new().configure(app_state(&[], storage.clone()))).await;
+
+    let req = test::TestRequest::get().uri(&format!("/prompts/{}", prompt.id)).to_request();
+    let res = test::call_service(&app, req).await;
+    let etag = res.headers().get(header::ETAG).unwrap().to_str().unwrap().to_string();
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/prompts/{}", prompt.id))
+        .insert_header((header::IF_MATCH, etag.as_str()))
+        .set_json(serde_json::json!({ "name": "guarded", "content": "second" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let new_etag = res.headers().get(header::ETAG).unwrap().to_str().unwrap().to_string();
+    assert_ne!(new_etag, etag);
+    let stored = storage.get_prompt(&prompt.id).await.unwrap().unwrap();
+    assert_eq!(stored.content, "second");
+    assert_eq!(stored.etag(), new_etag);
+}
+
+#[actix_web::test]
+async fn update_with_a_stale_etag_is_rejected() {
+    let storage = memory_storage();
+    let mut prompt = save(&storage, "guarded", "first").await;
+    let stale_etag = prompt.etag();
+    prompt.content = "edited elsewhere".to_string();
+    prompt.version += 1;
+    storage.save_prompt(&prompt).await.unwrap();
+    let app = test::init_service(App::new().configure(app_state(&[], storage.clone()))).await;
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/prompts/{}", prompt.id))
+        .insert_header((header::IF_MATCH, stale_etag.as_str()))
+        .set_json(serde_json::json!({ "name": "guarded", "content": "clobbered" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(res.headers().get(header::ETAG).unwrap().to_str().unwrap(), prompt.etag());
+    assert_eq!(storage.get_prompt(&prompt.id).await.unwrap().unwrap().content, "edited elsewhere");
+}
+
+#[actix_web::test]
+async fn category_prefix_filter_does_not_match_partial_segments() {
+    let storage = memory_storage();
+    for category in ["writing/email", "writingtools"] {
+        let prompt = Prompt::new(category.to_string(), "content".to_string(), Some(category.to_string()), None);
+        storage.save_prompt(&prompt).await.unwrap();
+    }
+    let app = test::init_service(App::new().configure(app_state(&[], storage))).await;
+
+    for uri in ["/prompts?category_prefix=writing", "/prompts?category_prefix=writing&summary=true"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let names: Vec<&str> = body.as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["writing/email"], "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn filtered_listings_without_a_limit_respect_max_unpaginated() {
+    let storage = memory_storage();
+    for name in ["one", "two", "three"] {
+        let prompt = Prompt::new(name.to_string(), "content".to_string(), Some("writing".to_string()), None);
+        storage.save_prompt(&prompt).await.unwrap();
+    }
+    let app = test::init_service(App::new().configure(app_state(&["--max-unpaginated", "2"], storage))).await;
+
+    for uri in ["/prompts?category=writing", "/prompts?sort=name", "/prompts?summary=true"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", uri);
+    }
+    for uri in ["/prompts?category=writing&limit=2", "/prompts?category=other"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn sandbox_uses_the_configured_template_engine() {
+    let flags = ["--enable-sandbox", "--template-engine", "handlebars"];
+    let app = test::init_service(App::new().configure(app_state(&flags, memory_storage()))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/prompts/sandbox")
+        .set_json(serde_json::json!({ "content": "Hi {{name}}{{#if loud}}!{{/if}}", "arguments": { "name": "Ada", "loud": true } }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["rendered"], "Hi Ada!");
+}
+
+#[actix_web::test]
+async fn attachments_are_served_as_downloads() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage: Arc<dyn PromptStorage> = Arc::new(FileSystemStorage::new(dir.path()));
+    let prompt = save(&storage, "with-svg", "content").await;
+    storage.save_attachment(&prompt.id, "logo.svg", b"<svg onload=\"alert(1)\"/>").await.unwrap();
+    let app = test::init_service(App::new().configure(app_state(&[], storage))).await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/prompts/{}/attachments/logo.svg", prompt.id))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let headers = res.headers();
+    assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), "application/octet-stream");
+    assert_eq!(headers.get(header::CONTENT_DISPOSITION).unwrap(), "attachment; filename=\"logo.svg\"");
+    assert_eq!(headers.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+    assert_eq!(headers.get(header::CONTENT_SECURITY_POLICY).unwrap(), "sandbox");
+}
+
+#[actix_web::test]
+async fn rename_variable_can_be_scoped_to_a_tag() {
+    let storage = memory_storage();
+    let mut tagged = Prompt::new("tagged".to_string(), "Hi {{ user }}".to_string(), None, None);
+    tagged.tags = vec!["email".to_string()];
+    let untagged = Prompt::new("untagged".to_string(), "Bye {{ user }}".to_string(), None, None);
+    for prompt in [&tagged, &untagged] {
+        storage.save_prompt(prompt).await.unwrap();
+    }
+    let app = test::init_service(App::new().configure(app_state(&[], storage.clone()))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/prompts/rename-variable")
+        .set_json(serde_json::json!({ "old": "user", "new": "member", "scope": { "tag": "Email" } }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["changed"], 1);
+    assert_eq!(storage.get_prompt(&tagged.id).await.unwrap().unwrap().content, "Hi {{ member }}");
+    assert_eq!(storage.get_prompt(&untagged.id).await.unwrap().unwrap().content, "Bye {{ user }}");
+}
+
+#[actix_web::test]
+async fn capabilities_describe_the_configured_engine() {
+    let app = test::init_service(App::new().configure(app_state(&[], memory_storage()))).await;
+    let req = test::TestRequest::get().uri("/capabilities").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["template_engine"], "tera");
+    assert_eq!(body["templates"]["engine"], "tera");
+    assert!(body["templates"]["builtin_filters"].as_array().unwrap().contains(&"upper".into()));
+    assert_eq!(body["templates"]["custom_filters"], serde_json::json!(["prompt"]));
+
+    let app = test::init_service(App::new().configure(app_state(&["--template-engine", "handlebars"], memory_storage()))).await;
+    let req = test::TestRequest::get().uri("/capabilities").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["templates"]["engine"], "handlebars");
+    assert_eq!(body["templates"]["builtin_filters"], serde_json::json!([]));
+}
+
+#[actix_web::test]
+async fn benchmark_requires_the_admin_token() {
+    let app = test::init_service(App::new().configure(app_state(&["--admin-token", "s3cret"], memory_storage()))).await;
+
+    for (authorization, status) in [
+        (None, StatusCode::UNAUTHORIZED),
+        (Some("Bearer wrong"), StatusCode::UNAUTHORIZED),
+        (Some("Bearer s3cre"), StatusCode::UNAUTHORIZED),
+        (Some("Bearer s3cret"), StatusCode::OK),
+    ] {
+        let mut req = test::TestRequest::post().uri("/admin/benchmark?ops=2");
+        if let Some(value) = authorization {
+            req = req.insert_header((header::AUTHORIZATION, value));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), status, "{:?}", authorization);
+    }
+}
+
+#[actix_web::test]
+async fn merging_a_draft_updates_the_original() {
+    let storage = memory_storage();
+    let original = save(&storage, "greeting", "Hello").await;
+    let app = test::init_service(App::new().configure(app_state(&[], storage.clone()))).await;
+
+    let req = test::TestRequest::post().uri(&format!("/prompts/{}/fork", original.id)).to_request();
+    let mut draft: Prompt = test::call_and_read_body_json(&app, req).await;
+    assert_eq!((draft.forked_from, draft.forked_from_version), (Some(original.id), Some(1)));
+    draft.content = "Hello there".to_string();
+    storage.save_prompt(&draft).await.unwrap();
+
+    let req = test::TestRequest::post().uri(&format!("/prompts/{}/merge", draft.id)).to_request();
+    let merged: Prompt = test::call_and_read_body_json(&app, req).await;
+    assert_eq!((merged.id, merged.version, merged.content.as_str()), (original.id, 2, "Hello there"));
+
+    // The draft follows the merge, so it can be merged again
+    let draft = storage.get_prompt(&draft.id).await.unwrap().unwrap();
+    assert_eq!(draft.forked_from_version, Some(2));
+    let req = test::TestRequest::post().uri(&format!("/prompts/{}/merge", draft.id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn merging_a_stale_draft_conflicts() {
+    let storage = memory_storage();
+    let mut original = save(&storage, "greeting", "Hello").await;
+    let app = test::init_service(App::new().configure(app_state(&[], storage.clone()))).await;
+
+    let req = test::TestRequest::post().uri(&format!("/prompts/{}/fork", original.id)).to_request();
+    let draft: Prompt = test::call_and_read_body_json(&app, req).await;
+    original.content = "Hi".to_string();
+    original.version += 1;
+    storage.save_prompt(&original).await.unwrap();
+
+    let req = test::TestRequest::post().uri(&format!("/prompts/{}/merge", draft.id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+    assert_eq!(storage.get_prompt(&original.id).await.unwrap().unwrap().content, "Hi");
+}
+
+#[actix_web::test]
+async fn merging_an_invalid_draft_is_rejected() {
+    let storage = memory_storage();
+    let original = save(&storage, "greeting", "Hello").await;
+    let mut draft = original.fork();
+    draft.content = "Hello {{ name".to_string();
+    storage.save_prompt(&draft).await.unwrap();
+    let app = test::init_service(App::new().configure(app_state(&[], storage.clone()))).await;
+
+    let req = test::TestRequest::post().uri(&format!("/prompts/{}/merge", draft.id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(storage.get_prompt(&original.id).await.unwrap().unwrap().version, 1);
+}
+
+/// Fails the first `failures` connection attempts, like a database that is still starting.
+struct FlakyFactory {
+    failures: usize,
+    attempts: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl storage::StorageFactory for FlakyFactory {
+    async fn create(&self, _options: &StorageOptions) -> anyhow::Result<Arc<dyn PromptStorage>> {
+        let attempt = self.attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        if attempt < self.failures {
+            anyhow::bail!("connection refused");
+        }
+        Ok(memory_storage())
+    }
+}
+
+#[actix_web::test]
+async fn connect_storage_retries_until_the_database_is_ready() {
+    let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    storage::register_storage("connect-test-flaky", FlakyFactory { failures: 1, attempts: attempts.clone() });
+
+    let args = cli(&["--storage", "connect-test-flaky", "--db-url", "flaky://db", "--db-wait-timeout", "10"]);
+    assert!(connect_storage(&args).await.is_ok());
+    assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
+
+#[actix_web::test]
+async fn connect_storage_gives_up_at_the_wait_timeout() {
+    let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    storage::register_storage("connect-test-down", FlakyFactory { failures: usize::MAX, attempts: attempts.clone() });
+
+    let args = cli(&["--storage", "connect-test-down", "--db-url", "down://db", "--db-wait-timeout", "0"]);
+    let err = connect_storage(&args).await.err().unwrap();
+    assert_eq!(err.to_string(), "Failed to initialize connect-test-down storage");
+    assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[actix_web::test]
+async fn connect_storage_does_not_retry_configuration_mistakes() {
+    let started = Instant::now();
+    let args = cli(&["--storage", "connect-test-unknown", "--db-url", "x://db", "--db-wait-timeout", "30"]);
+    let err = connect_storage(&args).await.err().unwrap();
+    assert!(matches!(
+        err.downcast_ref::<storage::StorageSetupError>(),
+        Some(storage::StorageSetupError::Unsupported { .. })
+    ));
+    assert!(started.elapsed() < INITIAL_DB_RETRY_DELAY);
+}
+
+#[actix_web::test]
+async fn new_prompts_are_owned_by_the_caller() {
+    let storage = memory_storage();
+    let app = test::init_service(App::new().configure(app_state(&["--owner-header", "X-User"], storage.clone()))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/prompts")
+        .insert_header(("X-User", "ada"))
+        .set_json(serde_json::json!({ "name": "mine", "content": "Hello" }))
+        .to_request();
+    let created: Prompt = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(created.owner.as_deref(), Some("ada"));
+
+    // Without the header nobody owns it
+    let req = test::TestRequest::put()
+        .uri(&format!("/prompts/{}", Uuid::new_v4()))
+        .set_json(serde_json::json!({ "name": "anonymous", "content": "Hello" }))
+        .to_request();
+    let created: Prompt = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(created.owner, None);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/prompts/{}/fork", created.id))
+        .insert_header(("X-User", "grace"))
+        .to_request();
+    let draft: Prompt = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(draft.owner.as_deref(), Some("grace"));
+}
+
+#[actix_web::test]
+async fn new_prompts_cannot_claim_an_owner_or_version() {
+    let storage = memory_storage();
+    let app = test::init_service(App::new().configure(app_state(&["--owner-header", "X-User"], storage.clone()))).await;
+
+    let bodies = [
+        serde_json::json!({ "name": "spoofed", "content": "Hello", "owner": "admin" }),
+        serde_json::json!({ "name": "spoofed", "content": "Hello", "version": 7 }),
+    ];
+    for body in bodies {
+        let req = test::TestRequest::post()
+            .uri("/prompts")
+            .insert_header(("X-User", "mallory"))
+            .set_json(&body)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST, "{}", body);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/prompts/{}", Uuid::new_v4()))
+            .set_json(&body)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST, "{}", body);
+    }
+    assert!(storage.list_prompts().await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn updates_keep_the_owner() {
+    let storage = memory_storage();
+    let mut prompt = Prompt::new("owned".to_string(), "Hello".to_string(), None, None);
+    prompt.owner = Some("ada".to_string());
+    storage.save_prompt(&prompt).await.unwrap();
+    let app = test::init_service(App::new().configure(app_state(&["--owner-header", "X-User"], storage.clone()))).await;
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/prompts/{}", prompt.id))
+        .insert_header(("X-User", "mallory"))
+        .set_json(serde_json::json!({ "name": "owned", "content": "Hi", "owner": "mallory" }))
+        .to_request();
+    let updated: Prompt = test::call_and_read_body_json(&app, req).await;
+    assert_eq!((updated.owner.as_deref(), updated.version), (Some("ada"), 2));
+}
+
+#[actix_web::test]
+async fn locked_prompts_cannot_be_transferred() {
+    let storage = memory_storage();
+    let mut prompt = Prompt::new("owned".to_string(), "Hello".to_string(), None, None);
+    prompt.owner = Some("ada".to_string());
+    prompt.locked = true;
+    storage.save_prompt(&prompt).await.unwrap();
+    let app = test::init_service(App::new().configure(app_state(&["--owner-header", "X-User"], storage.clone()))).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/prompts/{}/transfer", prompt.id))
+        .insert_header(("X-User", "ada"))
+        .set_json(serde_json::json!({ "new_owner": "grace" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::LOCKED);
+    assert_eq!(storage.get_prompt(&prompt.id).await.unwrap().unwrap().owner.as_deref(), Some("ada"));
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/prompts/{}/transfer", prompt.id))
+        .insert_header(("X-User", "mallory"))
+        .set_json(serde_json::json!({ "new_owner": "mallory" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn only_owners_and_admins_can_unlock_prompts() {
+    let storage = memory_storage();
+    let mut prompt = Prompt::new("owned".to_string(), "Hello".to_string(), None, None);
+    prompt.owner = Some("ada".to_string());
+    prompt.locked = true;
+    storage.save_prompt(&prompt).await.unwrap();
+    let flags = ["--admin-token", "s3cret", "--owner-header", "X-User"];
+    let app = test::init_service(App::new().configure(app_state(&flags, storage.clone()))).await;
+    let unlock = || test::TestRequest::post().uri(&format!("/prompts/{}/unlock", prompt.id));
+
+    let res = test::call_service(&app, unlock().to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = test::call_service(&app, unlock().insert_header(("X-User", "mallory")).to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert!(storage.get_prompt(&prompt.id).await.unwrap().unwrap().locked);
+
+    let res = test::call_service(&app, unlock().insert_header(("X-User", "ada")).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(!storage.get_prompt(&prompt.id).await.unwrap().unwrap().locked);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/prompts/{}/lock", prompt.id))
+        .insert_header((header::AUTHORIZATION, "Bearer s3cret"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert!(storage.get_prompt(&prompt.id).await.unwrap().unwrap().locked);
+}
+
+#[actix_web::test]
+async fn maintenance_is_disabled_without_an_admin_token() {
+    let app = test::init_service(App::new().configure(app_state(&[], memory_storage()))).await;
+    let req = test::TestRequest::post().uri("/admin/maintenance").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let app = test::init_service(App::new().configure(app_state(&["--admin-token", "s3cret"], memory_storage()))).await;
+    let req = test::TestRequest::post().uri("/admin/maintenance").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+    let req = test::TestRequest::post()
+        .uri("/admin/maintenance")
+        .insert_header((header::AUTHORIZATION, "Bearer s3cret"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_runs_take_one_render_quota_slot_per_rendered_case() {
+    let storage = memory_storage();
+    let mut prompt = Prompt::new("quota".to_string(), "Hi {{ name }}".to_string(), None, None);
+    prompt.render_quota_per_hour = Some(3);
+    prompt.tests = serde_json::from_value(serde_json::json!([
+        { "arguments": { "name": "Ada" }, "expected_output": "Hi Ada" },
+        { "expected_output": "fails to render without a name" },
+    ]))
+    .unwrap();
+    storage.save_prompt(&prompt).await.unwrap();
+    let app = test::init_service(App::new().configure(app_state(&[], storage))).await;
+    let run_tests = || test::TestRequest::post().uri(&format!("/prompts/{}/run-tests", prompt.id)).to_request();
+    let render = || {
+        test::TestRequest::post()
+            .uri(&format!("/prompts/{}/render", prompt.id))
+            .set_json(serde_json::json!({ "name": "Ada" }))
+            .to_request()
+    };
+
+    // Each run renders two cases, but the one that fails to render is refunded
+    assert_eq!(test::call_service(&app, run_tests()).await.status(), StatusCode::OK);
+    assert_eq!(test::call_service(&app, run_tests()).await.status(), StatusCode::OK);
+
+    // Two renders are needed but only one is left: the run is refused and takes nothing
+    let res = test::call_service(&app, run_tests()).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(res.headers().contains_key(header::RETRY_AFTER));
+    assert_eq!(test::call_service(&app, render()).await.status(), StatusCode::OK);
+    assert_eq!(test::call_service(&app, render()).await.status(), StatusCode::TOO_MANY_REQUESTS);
+}
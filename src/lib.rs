@@ -1,5 +1,35 @@
 pub mod storage;
 pub mod models;
+pub mod admission;
+pub mod attachments;
+pub mod auth;
+pub mod benchmark;
+pub mod catalog;
+pub mod categories;
+pub mod context;
+pub mod duplicates;
+pub mod error_page;
+pub mod export;
+pub mod lint;
+pub mod listen;
+pub mod messages;
+pub mod metrics;
+pub mod projection;
+pub mod quota;
+pub mod references;
+pub mod request_id;
+pub mod sandbox;
+pub mod search;
+pub mod seed;
+pub mod selftest;
+pub mod snippets;
+pub mod sorting;
+pub mod telemetry;
+pub mod template;
+pub mod testing;
+pub mod variables;
+pub mod watcher;
+pub mod webhooks;
 
 // Comment out rmcp server/model imports until we figure out the correct structure
 // use rmcp::model::{ServerCapabilities, Prompt as McpPrompt, Resource};
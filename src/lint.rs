@@ -0,0 +1,241 @@
+use crate::context::GLOBALS_KEY;
+use crate::models::prompt::Prompt;
+use crate::references::{PROMPTS_KEY, REFS_KEY};
+use crate::template::{self, EngineMode, TemplateCapabilities, TemplateEngine};
+use crate::variables;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Kinds of problems reported by [`lint_prompt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintKind {
+    /// A `{{`, `{%` or `{#` without its closing delimiter, or a closing delimiter without an opening one.
+    UnbalancedDelimiter,
+    /// A filter that isn't registered with the template engine.
+    UnknownFilter,
+    /// A variable used in a tag but missing from the prompt's declared variables.
+    UndeclaredVariable,
+}
+
+/// A likely authoring mistake found in a prompt template.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintWarning {
+    pub kind: LintKind,
+    /// Where the problem is: `content` or `section '<name>'`.
+    pub location: String,
+    /// 1-based line of the problem within that template.
+    pub line: usize,
+    pub message: String,
+}
+
+/// Checks the Tera templates of `prompt` for mistakes Tera would otherwise only
+/// report when rendering: unbalanced delimiters, unknown filters and, when the
+/// prompt declares its variables, variables missing from that list.
+///
+/// Content rendered with Handlebars (see [`EngineMode::engine_for`]) and encoded
+/// content are not linted.
+/// Prompts with custom delimiters are linted after translation to Tera syntax, so
+/// delimiter balance is left to [`template::validate_prompt`].
+pub fn lint_prompt(prompt: &Prompt, mode: EngineMode) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    let declared = declared_variables(prompt);
+
+    let mut templates = Vec::new();
+    if mode.engine_for(prompt) == TemplateEngine::Tera && !prompt.is_encoded() {
+        templates.push(("content".to_string(), &prompt.content));
+    }
+    for (name, section) in prompt.sections.iter().flatten() {
+        templates.push((format!("section '{}'", name), section));
+    }
+
+    for (location, text) in templates {
+        let source = match &prompt.delimiters {
+            Some(delimiters) => match template::to_tera_syntax(text, delimiters) {
+                Ok(source) => source,
+                Err(_) => continue,
+            },
+            None => {
+                check_delimiters(text, &location, &mut warnings);
+                text.clone()
+            }
+        };
+        check_filters(&source, &location, &mut warnings);
+        if let Some(declared) = &declared {
+            check_variables(&source, declared, &location, &mut warnings);
+        }
+    }
+    warnings
+}
+
+/// Names a template may use without declaring them, or `None` when the prompt
+/// doesn't declare its variables.
+fn declared_variables(prompt: &Prompt) -> Option<HashSet<String>> {
+    let mut declared: HashSet<String> = prompt.variables.clone()?.into_iter().collect();
+    declared.extend(prompt.default_arguments.keys().cloned());
+    declared.insert(GLOBALS_KEY.to_string());
+    declared.insert(REFS_KEY.to_string());
+    declared.insert(PROMPTS_KEY.to_string());
+    Some(declared)
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+fn raw_end_regex() -> &'static Regex {
+    static RAW_END: OnceLock<Regex> = OnceLock::new();
+    RAW_END.get_or_init(|| Regex::new(r"\{%-?\s*endraw\s*-?%\}").expect("valid endraw regex"))
+}
+
+const DELIMITERS: &[(&str, &str)] = &[("{{", "}}"), ("{%", "%}"), ("{#", "#}")];
+
+/// The next opening or closing delimiter at or after `from`, with its offset.
+fn next_delimiter(source: &str, from: usize) -> Option<(usize, &'static str)> {
+    (from..source.len()).filter(|&i| source.is_char_boundary(i)).find_map(|i| {
+        DELIMITERS
+            .iter()
+            .flat_map(|(open, close)| [*open, *close])
+            .find(|token| source[i..].starts_with(token))
+            .map(|token| (i, token))
+    })
+}
+
+fn closing_for(opener: &str) -> Option<&'static str> {
+    DELIMITERS.iter().find(|(open, _)| *open == opener).map(|(_, close)| *close)
+}
+
+fn check_delimiters(source: &str, location: &str, warnings: &mut Vec<LintWarning>) {
+    let mut warn = |offset: usize, message: String| {
+        warnings.push(LintWarning {
+            kind: LintKind::UnbalancedDelimiter,
+            location: location.to_string(),
+            line: line_of(source, offset),
+            message,
+        });
+    };
+
+    let mut open: Option<(&str, usize)> = None;
+    let mut pos = 0;
+    while let Some((offset, token)) = next_delimiter(source, pos) {
+        pos = offset + token.len();
+        match (open, closing_for(token)) {
+            (None, Some(_)) => open = Some((token, offset)),
+            (None, None) => warn(offset, format!("'{}' has no matching opening delimiter", token)),
+            (Some((opener, start)), Some(_)) => {
+                warn(start, format!("'{}' is not closed before the next '{}'", opener, token));
+                open = Some((token, offset));
+            }
+            (Some((opener, start)), None) => {
+                open = None;
+                if closing_for(opener) != Some(token) {
+                    warn(start, format!("'{}' is closed by '{}'", opener, token));
+                } else if opener == "{%" && source[start + 2..offset].trim_matches(|c: char| c == '-' || c.is_whitespace()) == "raw" {
+                    // Everything up to `endraw` is literal text
+                    match raw_end_regex().find_at(source, pos) {
+                        Some(end) => pos = end.end(),
+                        None => {
+                            warn(start, "'{% raw %}' has no matching '{% endraw %}'".to_string());
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+        // Comments may contain anything, including other delimiters
+        if let Some(("{#", start)) = open {
+            match source[pos..].find("#}") {
+                Some(end) => {
+                    pos += end + 2;
+                    open = None;
+                }
+                None => {
+                    warn(start, "'{#' is not closed".to_string());
+                    return;
+                }
+            }
+        }
+    }
+    if let Some((opener, start)) = open {
+        warn(start, format!("'{}' is not closed", opener));
+    }
+}
+
+fn filter_regex() -> &'static Regex {
+    static FILTER: OnceLock<Regex> = OnceLock::new();
+    FILTER.get_or_init(|| {
+        Regex::new(r"\|\s*([A-Za-z_][A-Za-z0-9_]*)|^\{%-?\s*filter\s+([A-Za-z_][A-Za-z0-9_]*)")
+            .expect("valid filter regex")
+    })
+}
+
+/// Replaces the contents of string literals with spaces, keeping offsets intact.
+fn blank_strings(tag: &str) -> String {
+    let mut output = String::with_capacity(tag.len());
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in tag.chars() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                    output.push(c);
+                    continue;
+                }
+                output.extend(std::iter::repeat_n(' ', c.len_utf8()));
+            }
+            None => {
+                if matches!(c, '"' | '\'' | '`') {
+                    quote = Some(c);
+                }
+                output.push(c);
+            }
+        }
+    }
+    output
+}
+
+fn check_filters(source: &str, location: &str, warnings: &mut Vec<LintWarning>) {
+    let capabilities = TemplateCapabilities::for_engine(TemplateEngine::Tera);
+    for tag in variables::template_tags(source) {
+        let text = blank_strings(&source[tag.clone()]);
+        for captures in filter_regex().captures_iter(&text) {
+            let Some(name) = captures.get(1).or_else(|| captures.get(2)) else {
+                continue;
+            };
+            let name = name.as_str();
+            if !capabilities.builtin_filters.contains(&name) && !capabilities.custom_filters.contains(&name) {
+                warnings.push(LintWarning {
+                    kind: LintKind::UnknownFilter,
+                    location: location.to_string(),
+                    line: line_of(source, tag.start),
+                    message: format!("Unknown filter '{}'", name),
+                });
+            }
+        }
+    }
+}
+
+fn check_variables(source: &str, declared: &HashSet<String>, location: &str, warnings: &mut Vec<LintWarning>) {
+    let bound = variables::template_bindings(source);
+    let mut reported = HashSet::new();
+    for tag in variables::template_tags(source) {
+        for name in variables::tag_variables(&source[tag.clone()]) {
+            if declared.contains(name) || bound.contains(name) || !reported.insert(name) {
+                continue;
+            }
+            warnings.push(LintWarning {
+                kind: LintKind::UndeclaredVariable,
+                location: location.to_string(),
+                line: line_of(source, tag.start),
+                message: format!("Variable '{}' is not in the prompt's declared variables", name),
+            });
+        }
+    }
+}
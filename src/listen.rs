@@ -0,0 +1,29 @@
+use anyhow::{Context, Result};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+/// Default `--host`: only reachable from the local machine.
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+
+/// Resolves `--host` and `--port` to the addresses the server listens on.
+///
+/// `host` is an IPv4 or IPv6 address (brackets around IPv6 are optional, so `::1`
+/// and `[::1]` are the same), or a host name, which is resolved and may yield
+/// several addresses. `0.0.0.0` or `::` listens on every interface.
+pub fn listen_addrs(host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+    let host = host.trim();
+    let unbracketed = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(host);
+    if let Ok(ip) = unbracketed.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+    if host.is_empty() || host.contains(|c: char| c.is_whitespace() || c == '/' || c == '[' || c == ']') {
+        anyhow::bail!("Invalid --host '{}': expected an IP address or host name", host);
+    }
+    let addrs: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .with_context(|| format!("Invalid --host '{}': not an IP address or resolvable host name", host))?
+        .collect();
+    if addrs.is_empty() {
+        anyhow::bail!("Invalid --host '{}': the host name resolved to no addresses", host);
+    }
+    Ok(addrs)
+}
@@ -1,237 +1,3414 @@
-use clap::Parser;
-use mcp_prompts_rs::storage::postgres::PostgresStorage;
-use mcp_prompts_rs::storage::{filesystem::FileSystemStorage, PromptStorage};
+// Handlers bail out early with `Result<_, HttpResponse>`, whose error is a full response
+#![allow(clippy::result_large_err)]
+
+use clap::{Parser, Subcommand};
+use mcp_prompts_rs::storage::{self, DeleteOutcome, PromptStorage, StorageConfig, StorageOptions};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, EnvFilter};
-use actix_web::{web, App, HttpServer, Responder, HttpResponse, get, post, put, delete};
-use mcp_prompts_rs::models::prompt::Prompt;
+use actix_web::{web, App, HttpRequest, HttpServer, Responder, HttpResponse, get, post, put, delete};
+use actix_multipart::Multipart;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::{header, StatusCode};
+use actix_web::middleware::{Condition, ErrorHandlerResponse, ErrorHandlers};
+use futures_util::TryStreamExt;
+use mcp_prompts_rs::admission::{self, AdmissionControl, AdmissionLimits, RequestClass, DEFAULT_EXEMPT_PATHS};
+use mcp_prompts_rs::attachments::{self, AttachmentInfo};
+use mcp_prompts_rs::error_page;
+use mcp_prompts_rs::models::category::Category;
+use mcp_prompts_rs::models::collection::Collection;
+use mcp_prompts_rs::models::prompt::{self as prompt_model, parse_prompt_json, Prompt, PromptParseError, PromptSummary};
+use mcp_prompts_rs::benchmark;
+use mcp_prompts_rs::catalog::{self, CatalogImport};
+use mcp_prompts_rs::categories;
+use mcp_prompts_rs::context::{self, GlobalContext};
+use mcp_prompts_rs::duplicates;
+use mcp_prompts_rs::export;
+use mcp_prompts_rs::auth::{constant_time_eq, ApiKeyAuth, API_KEY_ENV};
+use mcp_prompts_rs::lint;
+use mcp_prompts_rs::request_id::RequestIds;
+use mcp_prompts_rs::metrics::{self, InstrumentedStorage, Metrics, PromptOperation};
+use mcp_prompts_rs::listen;
+use mcp_prompts_rs::messages::{self, RenderFormat};
+use mcp_prompts_rs::projection;
+use mcp_prompts_rs::quota::RenderQuota;
+use mcp_prompts_rs::references::{self, DependentsPolicy, MissingReferencePolicy};
+use mcp_prompts_rs::sandbox;
+use mcp_prompts_rs::search::SearchOptions;
+use mcp_prompts_rs::seed::{self, SeedOutcome};
+use mcp_prompts_rs::sorting::{self, SortOrder};
+use mcp_prompts_rs::selftest;
+use mcp_prompts_rs::snippets::{self, SnippetLibrary};
+use mcp_prompts_rs::telemetry;
+use mcp_prompts_rs::testing::{TestCaseResult, TestReport};
+use mcp_prompts_rs::template::{self, EngineMode, RenderError, RenderLimits, TemplateEngine, DEFAULT_MAX_TEMPLATE_DEPTH};
+use mcp_prompts_rs::variables;
+use mcp_prompts_rs::watcher::PromptDirWatcher;
+use mcp_prompts_rs::webhooks::{WebhookConfig, WebhookDispatcher, WebhookEventKind};
+use serde::Deserialize;
 use uuid::Uuid;
 
+#[cfg(test)]
+mod handler_tests;
+
 // If available, import the rmcp crate for MCP server functionality
 // use rmcp::server::{McpServer, McpServerConfig};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Address or host name to listen on, e.g. 0.0.0.0 to accept connections from other hosts
+    #[arg(long, default_value = listen::DEFAULT_HOST)]
+    host: String,
+
     /// Port to run the server on
     #[arg(long, default_value_t = 8080)]
     port: u16,
 
-    /// Storage backend type (filesystem, postgres)
-    #[arg(long, default_value = "filesystem")]
-    storage: String,
+    /// Storage backend type (filesystem, memory, postgres, redis, sqlite, or a backend registered by an embedding application)
+    #[arg(long, default_value = "filesystem")]
+    storage: String,
+
+    /// Database connection URL, e.g. postgres://... or sqlite://prompts.db
+    #[arg(long)]
+    db_url: Option<String>,
+
+    /// Maximum number of pooled database connections
+    #[arg(long, default_value_t = 5)]
+    db_max_connections: u32,
+
+    /// Seconds to wait for a pooled database connection before failing
+    #[arg(long, default_value_t = 30)]
+    db_acquire_timeout: u64,
+
+    /// Seconds after which idle database connections are closed (0 keeps them open)
+    #[arg(long, default_value_t = 600)]
+    db_idle_timeout: u64,
+
+    /// Seconds to keep retrying the initial database connection before giving up (0 tries once)
+    #[arg(long, default_value_t = 30)]
+    db_wait_timeout: u64,
+
+    /// Maximum number of requests handled at once; further requests get 503 (unlimited if unset)
+    #[arg(long)]
+    max_inflight: Option<usize>,
+
+    /// Maximum number of read (GET, HEAD, OPTIONS) requests handled at once
+    #[arg(long)]
+    max_inflight_reads: Option<usize>,
+
+    /// Maximum number of write requests handled at once
+    #[arg(long)]
+    max_inflight_writes: Option<usize>,
+
+    /// Seconds clients are told to wait in `Retry-After` when a request is shed
+    #[arg(long, default_value_t = 1)]
+    overload_retry_after: u64,
+
+    /// Comma-separated paths exempt from the API key and request admission checks, such as
+    /// health probes and metrics scrapes
+    #[arg(long, value_delimiter = ',', default_values_t = DEFAULT_EXEMPT_PATHS.iter().map(|p| p.to_string()))]
+    auth_exempt_paths: Vec<String>,
+
+    /// Directory for prompt storage (when using filesystem storage)
+    #[arg(long, default_value = "./prompts")]
+    prompt_dir: String,
+
+    /// OTLP/gRPC collector endpoint to export traces to, e.g. http://localhost:4317
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
+
+    /// Sync each written file to disk before acknowledging the write (filesystem storage only)
+    #[arg(long)]
+    fsync: bool,
+
+    /// Scope all stored data to this namespace, e.g. dev, staging or prod, to share one store
+    #[arg(long)]
+    namespace: Option<String>,
+
+    /// Watch the prompt directory for changes made on disk (filesystem storage only)
+    #[arg(long)]
+    watch: bool,
+
+    /// Normalize prompt content on save (CRLF to LF, trim trailing whitespace per line)
+    #[arg(long)]
+    normalize_content: bool,
+
+    /// Set each saved prompt's variables to the ones its content and sections use
+    #[arg(long)]
+    auto_declare_variables: bool,
+
+    /// Allow updating and deleting locked prompts
+    #[arg(long)]
+    allow_locked_edits: bool,
+
+    /// JSON or YAML file with a list of prompts to load on startup when the store is empty
+    #[arg(long)]
+    seed_file: Option<String>,
+
+    /// Reject create/update bodies containing fields that aren't part of the prompt model
+    #[arg(long)]
+    strict_schema: bool,
+
+    /// JSON file of shared constants available to every render as `globals`
+    #[arg(long)]
+    context_file: Option<String>,
+
+    /// Directory of template snippets prompts can include as "snippet:<name>"
+    #[arg(long)]
+    snippets_dir: Option<String>,
+
+    /// Compile every stored prompt's templates at startup so first renders are fast
+    #[arg(long)]
+    warmup: bool,
+
+    /// Run a write/read/delete round trip against the configured storage and exit
+    #[arg(long)]
+    self_test: bool,
+
+    /// Reject GET /prompts without a limit once the library holds more than this many prompts
+    #[arg(long)]
+    max_unpaginated: Option<usize>,
+
+    /// Wrap list and search responses as {"data": [...], "meta": {...}} unless ?envelope=false
+    #[arg(long)]
+    envelope: bool,
+
+    /// URL notified of prompt changes (repeat for multiple webhooks)
+    #[arg(long = "webhook-url")]
+    webhook_urls: Vec<String>,
+
+    /// Retries for a failed webhook delivery before it is dead-lettered
+    #[arg(long, default_value_t = 3)]
+    webhook_retries: u32,
+
+    /// Delay in milliseconds before the first webhook retry (doubles on each retry)
+    #[arg(long, default_value_t = 500)]
+    webhook_backoff_ms: u64,
+
+    /// Timeout in seconds for a single webhook delivery attempt
+    #[arg(long, default_value_t = 10)]
+    webhook_timeout: u64,
+
+    /// File that undeliverable webhook events are appended to as JSON lines
+    #[arg(long)]
+    webhook_dead_letter: Option<String>,
+
+    /// Time limit in milliseconds for rendering a stored prompt
+    #[arg(long, default_value_t = 5000)]
+    render_timeout_ms: u64,
+
+    /// Maximum size in bytes of a rendered prompt in a response
+    #[arg(long, default_value_t = 1_048_576)]
+    render_max_output: usize,
+
+    /// Maximum size in bytes of a single prompt attachment
+    #[arg(long, default_value_t = 10 * 1024 * 1024)]
+    max_attachment_bytes: usize,
+
+    /// Enable POST /prompts/sandbox for rendering unsaved templates
+    #[arg(long)]
+    enable_sandbox: bool,
+
+    /// Time limit in milliseconds for a sandbox render
+    #[arg(long, default_value_t = 1000)]
+    sandbox_timeout_ms: u64,
+
+    /// Maximum size in bytes of sandbox render output
+    #[arg(long, default_value_t = 65536)]
+    sandbox_max_output: usize,
+
+    /// Request header carrying the authenticated user's identity, set by a trusted
+    /// reverse proxy (e.g. X-Forwarded-User). New prompts are owned by this user
+    #[arg(long)]
+    owner_header: Option<String>,
+
+    /// What to render for a referenced prompt that doesn't exist: error, placeholder or skip
+    #[arg(long, default_value_t = MissingReferencePolicy::Error)]
+    on_missing_reference: MissingReferencePolicy,
+
+    /// How deeply templates may nest through references, prompt() includes and extends
+    #[arg(long, default_value_t = DEFAULT_MAX_TEMPLATE_DEPTH)]
+    max_template_depth: usize,
+
+    /// What deleting a prompt that other prompts reference, extend or include does: allow, warn or block
+    #[arg(long, default_value_t = DependentsPolicy::Warn)]
+    on_delete_with_dependents: DependentsPolicy,
+
+    /// JSON file of default render arguments, overridden by prompt defaults and request arguments
+    #[arg(long)]
+    default_args: Option<String>,
+
+    /// Bearer token required by the /admin endpoints. /admin/benchmark and /admin/maintenance are disabled without one
+    #[arg(long)]
+    admin_token: Option<Secret>,
+
+    /// Bearer token required on every request except /health and --auth-exempt-paths. Falls back
+    /// to MCP_PROMPTS_API_KEY; without either the API is open
+    #[arg(long)]
+    api_key: Option<Secret>,
+
+    /// Template engine for rendering prompts: tera, handlebars, or auto to detect it per prompt
+    #[arg(long, default_value_t = EngineMode::Tera)]
+    template_engine: EngineMode,
+
+    /// Save prompts whose content or sections fail to compile as templates
+    #[arg(long)]
+    allow_invalid_templates: bool,
+
+    /// Reject saving prompts whose templates have lint warnings (see POST /prompts/validate)
+    #[arg(long)]
+    lint_on_save: bool,
+
+    /// Serialize unset optional prompt fields as `null` instead of omitting them
+    #[arg(long)]
+    explicit_nulls: bool,
+
+    /// Serve styled HTML pages for 404/500 errors to clients that accept text/html
+    #[arg(long)]
+    html_errors: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the stored test cases of prompts against the configured storage, print a
+    /// report and exit with status 1 if any case fails. The server is not started
+    Test {
+        /// Ids of the prompts to test; all prompts with test cases when omitted
+        ids: Vec<String>,
+    },
+}
+
+/// A secret CLI value, redacted from `Debug` output so it never reaches the logs.
+#[derive(Clone)]
+struct Secret(String);
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+impl std::str::FromStr for Secret {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Secret(s.to_string()))
+    }
+}
+
+/// Runtime options shared with the REST handlers.
+#[derive(Debug, Clone)]
+struct AppConfig {
+    normalize_content: bool,
+    /// Derive `variables` from the templates on save.
+    auto_declare_variables: bool,
+    allow_locked_edits: bool,
+    strict_schema: bool,
+    /// Engine used to render and validate prompt content.
+    template_engine: EngineMode,
+    allow_invalid_templates: bool,
+    lint_on_save: bool,
+    max_unpaginated: Option<usize>,
+    /// Wrap list responses with pagination metadata unless the request says otherwise.
+    envelope: bool,
+    /// Limits for rendering stored prompts.
+    render_limits: RenderLimits,
+    max_attachment_bytes: usize,
+    /// Limits for sandbox renders; `None` when the sandbox is disabled.
+    sandbox_limits: Option<RenderLimits>,
+    /// Bearer token for the /admin endpoints; `None` leaves them open (except benchmark).
+    admin_token: Option<Secret>,
+    /// What to render in place of a referenced prompt that doesn't exist.
+    on_missing_reference: MissingReferencePolicy,
+    /// How deeply templates may nest through references, includes and extends.
+    max_template_depth: usize,
+    /// What deleting a prompt with dependents does.
+    on_delete_with_dependents: DependentsPolicy,
+    /// Header naming the calling user, from `--owner-header`.
+    owner_header: Option<String>,
+    /// Server-wide default render arguments, loaded from `--default-args`.
+    default_arguments: serde_json::Map<String, serde_json::Value>,
+}
+
+impl AppConfig {
+    fn from_cli(args: &Cli) -> Self {
+        Self {
+            normalize_content: args.normalize_content,
+            auto_declare_variables: args.auto_declare_variables,
+            allow_locked_edits: args.allow_locked_edits,
+            strict_schema: args.strict_schema,
+            template_engine: args.template_engine,
+            allow_invalid_templates: args.allow_invalid_templates,
+            lint_on_save: args.lint_on_save,
+            max_unpaginated: args.max_unpaginated,
+            envelope: args.envelope,
+            render_limits: RenderLimits {
+                timeout: Duration::from_millis(args.render_timeout_ms),
+                max_output_bytes: args.render_max_output,
+            },
+            max_attachment_bytes: args.max_attachment_bytes,
+            sandbox_limits: args.enable_sandbox.then(|| RenderLimits {
+                timeout: Duration::from_millis(args.sandbox_timeout_ms),
+                max_output_bytes: args.sandbox_max_output,
+            }),
+            admin_token: args.admin_token.clone(),
+            owner_header: args.owner_header.clone(),
+            on_missing_reference: args.on_missing_reference,
+            max_template_depth: args.max_template_depth,
+            on_delete_with_dependents: args.on_delete_with_dependents,
+            default_arguments: serde_json::Map::new(),
+        }
+    }
+
+    /// Parses a create/update request body, or returns the 400/422 response to send.
+    fn parse_prompt_body(&self, body: &[u8]) -> Result<Prompt, HttpResponse> {
+        parse_prompt_json(body, self.strict_schema).map_err(|e| {
+            tracing::warn!(error = %e, "Rejected prompt request body");
+            match e {
+                PromptParseError::Invalid(_) => HttpResponse::BadRequest().body(e.to_string()),
+                PromptParseError::UnknownFields(_) => HttpResponse::UnprocessableEntity().body(e.to_string()),
+            }
+        })
+    }
+
+    /// The calling user's identity from the `--owner-header` header, if configured and present.
+    fn caller(&self, req: &HttpRequest) -> Option<String> {
+        let header_name = self.owner_header.as_deref()?;
+        req.headers()
+            .get(header_name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    }
+
+    /// True if an admin token is configured and the request carries it.
+    fn is_admin(&self, req: &HttpRequest) -> bool {
+        self.admin_token.as_ref().is_some_and(|token| {
+            req.headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .is_some_and(|presented| constant_time_eq(presented.as_bytes(), token.0.as_bytes()))
+        })
+    }
+
+    /// Returns the 422 response to send if the prompt's templates don't compile,
+    /// unless `--allow-invalid-templates` is set, or have lint warnings when
+    /// `--lint-on-save` is set.
+    fn check_templates(&self, prompt: &Prompt) -> Result<(), HttpResponse> {
+        if !self.allow_invalid_templates {
+            template::validate_prompt(prompt, self.template_engine).map_err(|msg| {
+                tracing::warn!(prompt_id = %prompt.id, error = %msg, "Rejected prompt with an invalid template");
+                HttpResponse::UnprocessableEntity().body(msg)
+            })?;
+        }
+        if self.lint_on_save {
+            let warnings = lint::lint_prompt(prompt, self.template_engine);
+            if !warnings.is_empty() {
+                tracing::warn!(prompt_id = %prompt.id, warnings = warnings.len(), "Rejected prompt with lint warnings");
+                return Err(HttpResponse::UnprocessableEntity().json(serde_json::json!({ "warnings": warnings })));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the 400 response to send if the prompt's Tera templates use variables
+    /// it doesn't declare (see [`Prompt::validate_variables`]), and logs declared
+    /// variables that are never used.
+    fn check_variables(&self, prompt: &Prompt) -> Result<(), HttpResponse> {
+        if self.template_engine.engine_for(prompt) != TemplateEngine::Tera {
+            return Ok(());
+        }
+        match prompt.validate_variables() {
+            Ok(unused) if !unused.is_empty() => {
+                tracing::warn!(prompt_id = %prompt.id, unused = ?unused, "Prompt declares variables its templates don't use");
+                Ok(())
+            }
+            Ok(_) => Ok(()),
+            Err(msg) => {
+                tracing::warn!(prompt_id = %prompt.id, error = %msg, "Rejected prompt with undeclared variables");
+                Err(HttpResponse::BadRequest().body(msg))
+            }
+        }
+    }
+
+    /// Applies configured transformations to a prompt before it is saved.
+    fn prepare_for_save(&self, prompt: &mut Prompt) {
+        // Also done by the storage backends; done here so responses show the stored tags
+        prompt.tags = prompt_model::normalize_tags(&prompt.tags);
+        if self.normalize_content {
+            prompt.normalize_content();
+        }
+        if self.auto_declare_variables {
+            // Invalid delimiters are left for check_templates to report
+            if let Err(e) = variables::declare_used_variables(prompt) {
+                tracing::debug!(prompt_id = %prompt.id, error = %e, "Not declaring variables of prompt");
+            }
+        }
+    }
+}
+
+/// Builds the options passed to the selected storage factory from the CLI flags.
+fn storage_options(args: &Cli) -> StorageOptions {
+    StorageOptions {
+        prompt_dir: PathBuf::from(&args.prompt_dir),
+        connection: args.db_url.clone().map(|url| StorageConfig {
+            url,
+            max_connections: args.db_max_connections,
+            acquire_timeout: Duration::from_secs(args.db_acquire_timeout),
+            idle_timeout: (args.db_idle_timeout > 0).then(|| Duration::from_secs(args.db_idle_timeout)),
+        }),
+        fsync: args.fsync,
+        namespace: args.namespace.clone(),
+    }
+}
+
+// --- REST Handlers Implementation ---
+
+/// Parses a prompt id from a path segment, or returns the 400 response to send.
+fn parse_prompt_id(id_str: &str) -> Result<Uuid, HttpResponse> {
+    Uuid::parse_str(id_str).map_err(|_| {
+        tracing::warn!(prompt_id = %id_str, "Invalid UUID format provided");
+        HttpResponse::BadRequest().body("Invalid prompt ID format. Please use UUID.")
+    })
+}
+
+/// Loads a prompt by id, or returns the 404/500 response to send.
+async fn load_prompt(storage: &dyn PromptStorage, id: &Uuid) -> Result<Prompt, HttpResponse> {
+    match storage.get_prompt(id).await {
+        Ok(Some(prompt)) => Ok(prompt),
+        Ok(None) => Err(missing_prompt_response(storage, id).await),
+        Err(e) => {
+            tracing::error!(prompt_id = %id, error = %e, "Failed to get prompt");
+            Err(HttpResponse::InternalServerError().body("Failed to retrieve prompt"))
+        }
+    }
+}
+
+/// `201 Created` with the stored prompt as the body, and its URL and id in headers
+/// so clients needn't parse the body.
+fn created_prompt_response(prompt: &Prompt) -> HttpResponse {
+    HttpResponse::Created()
+        .insert_header((header::LOCATION, format!("/prompts/{}", prompt.id)))
+        .insert_header(("X-Resource-Id", prompt.id.to_string()))
+        .json(prompt)
+}
+
+/// Response for an id with no stored prompt: 410 if it was deleted, 404 if it never existed.
+async fn missing_prompt_response(storage: &dyn PromptStorage, id: &Uuid) -> HttpResponse {
+    match storage.is_deleted(id).await {
+        Ok(true) => {
+            tracing::warn!(prompt_id = %id, "Prompt has been deleted");
+            HttpResponse::Gone().body(format!("Prompt with id {} has been deleted", id))
+        }
+        Ok(false) => {
+            tracing::warn!(prompt_id = %id, "Prompt not found");
+            HttpResponse::NotFound().body(format!("Prompt with id {} not found", id))
+        }
+        Err(e) => {
+            tracing::error!(prompt_id = %id, error = %e, "Failed to check whether prompt was deleted");
+            HttpResponse::InternalServerError().body("Failed to retrieve prompt")
+        }
+    }
+}
+
+/// Returns the 423 response to send if `existing` is locked and locked edits aren't allowed.
+fn check_not_locked(config: &AppConfig, existing: &Prompt) -> Result<(), HttpResponse> {
+    if existing.locked && !config.allow_locked_edits {
+        tracing::warn!(prompt_id = %existing.id, "Rejected modification of locked prompt");
+        return Err(HttpResponse::Locked().body(format!(
+            "Prompt with id {} is locked. Unlock it before making changes.",
+            existing.id
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the 400 response to send if a body for a new prompt sets a field only the
+/// server assigns: `owner`, which comes from `--owner-header`, or a `version` other
+/// than the initial 1.
+fn reject_server_fields(body: &[u8]) -> Result<(), HttpResponse> {
+    let Ok(serde_json::Value::Object(object)) = serde_json::from_slice(body) else {
+        return Ok(());
+    };
+    if object.get("owner").is_some_and(|owner| !owner.is_null()) {
+        return Err(HttpResponse::BadRequest()
+            .body("owner is set from the caller's identity; transfer a prompt to change it"));
+    }
+    if object.get("version").is_some_and(|version| !version.is_null() && version.as_u64() != Some(1)) {
+        return Err(HttpResponse::BadRequest().body("New prompts start at version 1; omit version"));
+    }
+    Ok(())
+}
+
+/// Returns the 401 response to send unless the request carries the configured admin token.
+/// Passes every request when no token is configured.
+fn check_admin(config: &AppConfig, req: &HttpRequest) -> Result<(), HttpResponse> {
+    if config.admin_token.is_some() && !config.is_admin(req) {
+        tracing::warn!(path = %req.path(), "Rejected admin request without a valid token");
+        return Err(HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .body("A valid admin token is required"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct ListQuery {
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+    /// Only prompts in exactly this category
+    category: Option<String>,
+    /// Only prompts in this category or its subcategories, e.g. `writing`
+    category_prefix: Option<String>,
+    /// Only prompts with this tag, ignoring case
+    tag: Option<String>,
+    /// Return only id, name, category and version, without loading content
+    #[serde(default)]
+    summary: bool,
+    /// Comma-separated fields to include in each returned prompt
+    fields: Option<String>,
+    /// Sort field (`name`, `category`, `version`, `id`), prefixed with `-` to reverse
+    sort: Option<String>,
+    /// Only prompts whose content is at least this many characters long
+    min_length: Option<usize>,
+    /// Only prompts whose content is at most this many characters long
+    max_length: Option<usize>,
+    /// Wrap the list with pagination metadata, overriding `--envelope`
+    envelope: Option<bool>,
+}
+
+/// Parses an optional `?fields=` list, or returns the 400 response to send.
+fn requested_fields(fields: Option<&str>) -> Result<Option<Vec<String>>, HttpResponse> {
+    fields
+        .map(projection::parse_fields)
+        .transpose()
+        .map_err(|msg| HttpResponse::BadRequest().body(msg))
+}
+
+/// Responds with `value` as JSON, keeping only `fields` of each prompt when given.
+fn json_with_fields<T: serde::Serialize>(value: &T, fields: Option<&[String]>) -> HttpResponse {
+    let Some(fields) = fields else {
+        return HttpResponse::Ok().json(value);
+    };
+    match projection::project(value, fields) {
+        Ok(projected) => HttpResponse::Ok().json(projected),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to serialize projected response");
+            HttpResponse::InternalServerError().body("Failed to serialize response")
+        }
+    }
+}
+
+/// Pagination metadata of an enveloped list response.
+#[derive(Debug, serde::Serialize)]
+struct PageMeta {
+    /// Number of matching items before `offset` and `limit` are applied
+    total: usize,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+/// Responds with a list as JSON like [`json_with_fields`], wrapped as
+/// `{"data": [...], "meta": {...}}` when `meta` is given.
+fn list_json<T: serde::Serialize>(items: &[T], fields: Option<&[String]>, meta: Option<PageMeta>) -> HttpResponse {
+    let Some(meta) = meta else {
+        return json_with_fields(&items, fields);
+    };
+    let data = match fields {
+        Some(fields) => projection::project(&items, fields),
+        None => serde_json::to_value(items),
+    };
+    match data {
+        Ok(data) => HttpResponse::Ok().json(serde_json::json!({ "data": data, "meta": meta })),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to serialize list response");
+            HttpResponse::InternalServerError().body("Failed to serialize response")
+        }
+    }
+}
+
+/// Collects `metadata.<key>=<value>` equality filters from the query string.
+fn metadata_filters(req: &HttpRequest) -> Vec<(String, String)> {
+    web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+        .map(|pairs| {
+            pairs
+                .into_inner()
+                .into_iter()
+                .filter_map(|(key, value)| key.strip_prefix("metadata.").map(|k| (k.to_string(), value)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Rejects a listing of `count` prompts requested without a `limit` when it
+/// is larger than `--max-unpaginated` allows.
+fn check_unpaginated(config: &AppConfig, limit: Option<usize>, count: usize) -> Result<(), HttpResponse> {
+    match config.max_unpaginated {
+        Some(max) if limit.is_none() && count > max => {
+            tracing::warn!(count, max, "Rejected unpaginated list of a large library");
+            Err(HttpResponse::BadRequest().body(format!(
+                "There are {} prompts, more than the {} allowed without pagination. Use the limit and offset query parameters.",
+                count, max
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[get("")]
+async fn list_prompts_handler(
+    req: HttpRequest,
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    query: web::Query<ListQuery>,
+) -> impl Responder {
+    tracing::info!(limit = ?query.limit, offset = query.offset, category = ?query.category, category_prefix = ?query.category_prefix, tag = ?query.tag, "Handling GET /prompts");
+
+    let fields = match requested_fields(query.fields.as_deref()) {
+        Ok(fields) => fields,
+        Err(resp) => return resp,
+    };
+    let fields = fields.as_deref();
+    let envelope = query.envelope.unwrap_or(config.envelope);
+    let meta = |total: usize| {
+        envelope.then(|| PageMeta {
+            total,
+            limit: query.limit,
+            offset: query.offset,
+        })
+    };
+    let order = match query.sort.as_deref().map(str::parse::<SortOrder>).transpose() {
+        Ok(order) => order.unwrap_or_default(),
+        Err(msg) => return HttpResponse::BadRequest().body(msg),
+    };
+    let length_filtered = query.min_length.is_some() || query.max_length.is_some();
+    if let (Some(min), Some(max)) = (query.min_length, query.max_length) {
+        if min > max {
+            return HttpResponse::BadRequest().body("min_length must not be greater than max_length");
+        }
+    }
+
+    let in_category = |category: Option<&str>| {
+        query.category.as_deref().is_none_or(|wanted| category == Some(wanted))
+            && query
+                .category_prefix
+                .as_deref()
+                .is_none_or(|prefix| category.is_some_and(|c| categories::in_subtree(c, prefix)))
+    };
+
+    let filters = metadata_filters(&req);
+    if query.summary {
+        if !filters.is_empty() {
+            return HttpResponse::BadRequest().body("metadata filters are not supported with summary=true");
+        }
+        if length_filtered {
+            return HttpResponse::BadRequest().body("length filters are not supported with summary=true");
+        }
+        if query.tag.is_some() {
+            return HttpResponse::BadRequest().body("tag filters are not supported with summary=true");
+        }
+        return match storage.list_prompt_summaries().await {
+            Ok(summaries) => {
+                let mut matching: Vec<PromptSummary> = summaries
+                    .into_iter()
+                    .filter(|s| in_category(s.category.as_deref()))
+                    .collect();
+                if let Err(resp) = check_unpaginated(&config, query.limit, matching.len()) {
+                    return resp;
+                }
+                sorting::sort_items(&mut matching, order);
+                let total = matching.len();
+                let page: Vec<PromptSummary> = matching
+                    .into_iter()
+                    .skip(query.offset)
+                    .take(query.limit.unwrap_or(usize::MAX))
+                    .collect();
+                list_json(&page, fields, meta(total))
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to list prompt summaries");
+                HttpResponse::InternalServerError().body("Failed to list prompts")
+            }
+        };
+    }
+    let category_filtered = query.category.is_some() || query.category_prefix.is_some();
+    let tag_filtered = query.tag.is_some();
+    if !filters.is_empty() || category_filtered || tag_filtered || length_filtered || order != SortOrder::default() {
+        let prompts = match (&query.category, &query.category_prefix, &query.tag) {
+            _ if length_filtered => storage.list_prompts_by_length(query.min_length, query.max_length).await,
+            (Some(category), _, _) => storage.list_prompts_by_category(category).await,
+            (None, Some(prefix), _) => storage.list_prompts_by_category_prefix(prefix).await,
+            (None, None, Some(tag)) => storage.list_prompts_by_tag(tag).await,
+            (None, None, None) => storage.list_prompts().await,
+        };
+        return match prompts {
+            Ok(prompts) => {
+                let mut matching: Vec<Prompt> = prompts
+                    .into_iter()
+                    .filter(|p| in_category(p.category.as_deref()))
+                    .filter(|p| query.tag.as_deref().is_none_or(|tag| p.has_tag(tag)))
+                    .filter(|p| filters.iter().all(|(key, value)| p.metadata_matches(key, value)))
+                    .collect();
+                if let Err(resp) = check_unpaginated(&config, query.limit, matching.len()) {
+                    return resp;
+                }
+                sorting::sort_items(&mut matching, order);
+                let total = matching.len();
+                let page: Vec<Prompt> = matching
+                    .into_iter()
+                    .skip(query.offset)
+                    .take(query.limit.unwrap_or(usize::MAX))
+                    .collect();
+                list_json(&page, fields, meta(total))
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to list prompts");
+                HttpResponse::InternalServerError().body("Failed to list prompts")
+            }
+        };
+    }
+
+    let Some(limit) = query.limit else {
+        if config.max_unpaginated.is_some() {
+            match storage.count_prompts().await {
+                Ok(count) => {
+                    if let Err(resp) = check_unpaginated(&config, None, count) {
+                        return resp;
+                    }
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to count prompts");
+                    return HttpResponse::InternalServerError().body("Failed to list prompts");
+                }
+            }
+        }
+        // Without a limit the whole library is returned, whatever the offset
+        return match storage.list_prompts().await {
+            Ok(prompts) => list_json(
+                &prompts,
+                fields,
+                envelope.then_some(PageMeta {
+                    total: prompts.len(),
+                    limit: None,
+                    offset: 0,
+                }),
+            ),
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to list prompts");
+                HttpResponse::InternalServerError().body("Failed to list prompts")
+            }
+        };
+    };
+
+    let total = if envelope {
+        match storage.count_prompts().await {
+            Ok(count) => count,
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to count prompts");
+                return HttpResponse::InternalServerError().body("Failed to list prompts");
+            }
+        }
+    } else {
+        0
+    };
+    match storage.list_prompts_paginated(query.offset, limit).await {
+        Ok(prompts) => list_json(&prompts, fields, meta(total)),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list prompts");
+            HttpResponse::InternalServerError().body("Failed to list prompts")
+        }
+    }
+}
+
+#[get("/categories/tree")]
+async fn category_tree_handler(storage: web::Data<Arc<dyn PromptStorage>>) -> impl Responder {
+    tracing::info!("Handling GET /prompts/categories/tree");
+    match storage.list_prompt_summaries().await {
+        Ok(summaries) => HttpResponse::Ok().json(categories::category_tree(
+            summaries.iter().filter_map(|s| s.category.as_deref()),
+        )),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list prompts for category tree");
+            HttpResponse::InternalServerError().body("Failed to build category tree")
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    q: String,
+    /// Ignore accents as well as case
+    #[serde(default)]
+    fold: bool,
+    /// Order by number of matches and add a highlighted content snippet to each result
+    #[serde(default)]
+    highlight: bool,
+    /// Wrap the results with pagination metadata, overriding `--envelope`
+    envelope: Option<bool>,
+}
+
+#[get("/search")]
+async fn search_prompts_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    query: web::Query<SearchQuery>,
+) -> impl Responder {
+    tracing::info!(q = %query.q, fold = query.fold, highlight = query.highlight, "Handling GET /prompts/search");
+    let options = SearchOptions { fold: query.fold };
+    // Search isn't paginated, so the envelope always covers every result
+    let meta = |total: usize| {
+        query.envelope.unwrap_or(config.envelope).then_some(PageMeta {
+            total,
+            limit: None,
+            offset: 0,
+        })
+    };
+    if query.highlight {
+        return match storage.search_prompts_ranked(&query.q, &options, true).await {
+            Ok(hits) => list_json(&hits, None, meta(hits.len())),
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to search prompts");
+                HttpResponse::InternalServerError().body("Failed to search prompts")
+            }
+        };
+    }
+    match storage.search_prompts(&query.q, &options).await {
+        Ok(prompts) => list_json(&prompts, None, meta(prompts.len())),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to search prompts");
+            HttpResponse::InternalServerError().body("Failed to search prompts")
+        }
+    }
+}
+
+// Registered before `/{id}` so that "export" isn't treated as a prompt id
+#[get("/export")]
+async fn export_prompts_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    query: web::Query<ExportQuery>,
+) -> impl Responder {
+    tracing::info!(format = ?query.format, "Handling GET /prompts/export");
+
+    match query.format.as_deref().unwrap_or("md") {
+        "md" | "markdown" => {}
+        other => {
+            return HttpResponse::BadRequest().body(format!(
+                "Unsupported export format '{}'. Supported formats: md",
+                other
+            ))
+        }
+    }
+
+    match storage.list_prompts().await {
+        Ok(prompts) => HttpResponse::Ok()
+            .content_type("text/markdown; charset=utf-8")
+            .body(export::prompts_to_markdown(&prompts)),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list prompts for export");
+            HttpResponse::InternalServerError().body("Failed to export prompts")
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DuplicatesQuery {
+    /// Also cluster near-duplicates at or above this similarity (0.0 to 1.0)
+    similarity: Option<f64>,
+}
+
+#[get("/duplicates")]
+async fn duplicates_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    query: web::Query<DuplicatesQuery>,
+) -> impl Responder {
+    tracing::info!(similarity = ?query.similarity, "Handling GET /prompts/duplicates");
+
+    if let Some(similarity) = query.similarity {
+        if !(0.0..=1.0).contains(&similarity) {
+            return HttpResponse::BadRequest().body("similarity must be between 0.0 and 1.0");
+        }
+    }
+
+    match storage.list_prompts().await {
+        Ok(prompts) => HttpResponse::Ok().json(duplicates::find_duplicates(&prompts, query.similarity)),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list prompts for duplicate detection");
+            HttpResponse::InternalServerError().body("Failed to list prompts")
+        }
+    }
+}
+
+/// Which prompts a bulk operation applies to.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PromptScope {
+    All,
+    Category(String),
+    /// Prompts with this tag, ignoring case
+    Tag(String),
+}
+
+impl PromptScope {
+    fn matches(&self, prompt: &Prompt) -> bool {
+        match self {
+            PromptScope::All => true,
+            PromptScope::Category(category) => prompt.category.as_deref() == Some(category.as_str()),
+            PromptScope::Tag(tag) => prompt.has_tag(tag),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RenameVariableRequest {
+    old: String,
+    new: String,
+    #[serde(default = "default_scope")]
+    scope: PromptScope,
+}
+
+fn default_scope() -> PromptScope {
+    PromptScope::All
+}
+
+#[post("/rename-variable")]
+async fn rename_variable_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
+    request: web::Json<RenameVariableRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+    tracing::info!(old = %request.old, new = %request.new, scope = ?request.scope, "Handling POST /prompts/rename-variable");
+
+    for name in [&request.old, &request.new] {
+        if !variables::is_valid_identifier(name) {
+            return HttpResponse::BadRequest().body(format!("'{}' is not a valid variable name", name));
+        }
+    }
+
+    let prompts = match storage.list_prompts().await {
+        Ok(prompts) => prompts,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list prompts for variable rename");
+            return HttpResponse::InternalServerError().body("Failed to list prompts");
+        }
+    };
+
+    let mut changed = Vec::new();
+    let mut skipped_locked = Vec::new();
+    for mut prompt in prompts.into_iter().filter(|p| request.scope.matches(p)) {
+        let original = prompt.clone();
+        if !prompt.rename_variable(&request.old, &request.new) {
+            continue;
+        }
+        prompt.version = prompt.version.saturating_add(1);
+        if check_not_locked(&config, &original).is_err() {
+            skipped_locked.push(prompt.id);
+            continue;
+        }
+        config.prepare_for_save(&mut prompt);
+        if let Err(e) = storage.save_prompt(&prompt).await {
+            tracing::error!(prompt_id = %prompt.id, error = %e, "Failed to save renamed prompt");
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to save prompt {}", prompt.id),
+                "changed": changed.len(),
+                "prompt_ids": changed,
+            }));
+        }
+        webhooks.notify(WebhookEventKind::Updated, prompt.id);
+        changed.push(prompt.id);
+    }
+
+    tracing::info!(changed = changed.len(), skipped_locked = skipped_locked.len(), "Variable rename complete");
+    HttpResponse::Ok().json(serde_json::json!({
+        "changed": changed.len(),
+        "prompt_ids": changed,
+        "skipped_locked": skipped_locked,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportQuery {
+    /// Format of the request body; only `catalog` is supported
+    format: String,
+}
+
+#[post("/import")]
+async fn import_prompts_handler(
+    req: HttpRequest,
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+) -> impl Responder {
+    tracing::info!(format = %query.format, "Handling POST /prompts/import");
+    if query.format != "catalog" {
+        return HttpResponse::BadRequest().body(format!("Unsupported import format '{}'; expected 'catalog'", query.format));
+    }
+
+    // Map every entry before saving anything, so a bad entry doesn't leave a partial import
+    let imports = match serde_json::from_slice(&body)
+        .map_err(|e| format!("Invalid catalog JSON: {}", e))
+        .and_then(catalog::catalog_entries)
+        .and_then(|entries| entries.iter().map(catalog::map_entry).collect::<Result<Vec<CatalogImport>, String>>())
+    {
+        Ok(imports) => imports,
+        Err(msg) => {
+            tracing::warn!(error = %msg, "Rejected catalog import");
+            return HttpResponse::BadRequest().body(msg);
+        }
+    };
+    for import in &imports {
+        if let Err(resp) = config.check_templates(&import.prompt) {
+            return resp;
+        }
+    }
+
+    let existing = match storage.list_prompts().await {
+        Ok(prompts) => prompts,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list prompts for catalog import");
+            return HttpResponse::InternalServerError().body("Failed to import prompts");
+        }
+    };
+
+    let mut created = 0;
+    let mut updated = 0;
+    let mut skipped_locked = Vec::new();
+    let mut imported = Vec::new();
+    for CatalogImport { mut prompt, catalog_id, unmapped } in imports {
+        let previous = existing.iter().find(|p| match &catalog_id {
+            Some(catalog_id) => p.metadata_matches(catalog::CATALOG_ID_KEY, catalog_id),
+            None => p.id == prompt.id,
+        });
+        if let Some(previous) = previous {
+            if check_not_locked(&config, previous).is_err() {
+                skipped_locked.push(previous.id);
+                continue;
+            }
+            prompt.id = previous.id;
+            prompt.locked = previous.locked;
+            prompt.owner = previous.owner.clone();
+            prompt.version = previous.version.saturating_add(1);
+        } else {
+            prompt.owner = config.caller(&req);
+        }
+        config.prepare_for_save(&mut prompt);
+
+        if let Err(e) = storage.save_prompt(&prompt).await {
+            tracing::error!(prompt_id = %prompt.id, error = %e, "Failed to save imported prompt");
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to save prompt {}", prompt.id),
+                "created": created,
+                "updated": updated,
+            }));
+        }
+        if previous.is_some() {
+            updated += 1;
+            webhooks.notify(WebhookEventKind::Updated, prompt.id);
+        } else {
+            created += 1;
+            webhooks.notify(WebhookEventKind::Created, prompt.id);
+        }
+        imported.push(serde_json::json!({
+            "id": prompt.id,
+            "name": prompt.name,
+            "catalog_id": catalog_id,
+            "unmapped_fields": unmapped,
+        }));
+    }
+
+    tracing::info!(created, updated, skipped_locked = skipped_locked.len(), "Catalog import complete");
+    HttpResponse::Ok().json(serde_json::json!({
+        "created": created,
+        "updated": updated,
+        "skipped_locked": skipped_locked,
+        "prompts": imported,
+    }))
+}
+
+/// Representations of a single prompt that `GET /prompts/{id}` can return.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PromptFormat {
+    Json,
+    Yaml,
+    Text,
+}
+
+/// Picks the response format from an `Accept` header, honouring `q` weights.
+/// Falls back to JSON when the header is absent or names nothing we support.
+fn negotiate_prompt_format(accept: Option<&str>) -> PromptFormat {
+    let Some(accept) = accept else {
+        return PromptFormat::Json;
+    };
+
+    let mut candidates: Vec<(f32, PromptFormat)> = accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next()?.to_ascii_lowercase();
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            let format = match media_type.as_str() {
+                "application/json" | "application/*" | "*/*" => PromptFormat::Json,
+                "application/yaml" | "application/x-yaml" | "text/yaml" => PromptFormat::Yaml,
+                "text/plain" | "text/*" => PromptFormat::Text,
+                _ => return None,
+            };
+            (quality > 0.0).then_some((quality, format))
+        })
+        .collect();
+
+    // Stable sort keeps header order for equal weights
+    candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+    candidates.first().map_or(PromptFormat::Json, |(_, format)| *format)
+}
+
+#[derive(Debug, Deserialize)]
+struct FieldsQuery {
+    /// Comma-separated fields to include in the returned prompt
+    fields: Option<String>,
+}
+
+#[get("/{id}")]
+async fn get_prompt_handler(
+    req: HttpRequest,
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    metrics: web::Data<Metrics>,
+    path: web::Path<String>,
+    query: web::Query<FieldsQuery>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling GET /prompts/{}", id_str);
+    let fields = match requested_fields(query.fields.as_deref()) {
+        Ok(fields) => fields,
+        Err(resp) => return resp,
+    };
+
+    let format = negotiate_prompt_format(
+        req.headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok()),
+    );
+
+    match Uuid::parse_str(&id_str) {
+        Ok(id_uuid) => match storage.get_prompt(&id_uuid).await {
+            Ok(Some(prompt)) => {
+                metrics.record(PromptOperation::Get);
+                let etag = prompt.etag();
+                let mut response = match format {
+                    PromptFormat::Json => json_with_fields(&prompt, fields.as_deref()),
+                    PromptFormat::Yaml => match match fields.as_deref() {
+                        Some(fields) => projection::project(&prompt, fields)
+                            .map_err(|e| e.to_string())
+                            .and_then(|value| serde_yaml::to_string(&value).map_err(|e| e.to_string())),
+                        None => serde_yaml::to_string(&prompt).map_err(|e| e.to_string()),
+                    } {
+                        Ok(yaml) => HttpResponse::Ok().content_type("application/yaml").body(yaml),
+                        Err(e) => {
+                            tracing::error!(prompt_id = %id_str, error = %e, "Failed to serialize prompt as YAML");
+                            HttpResponse::InternalServerError().body("Failed to serialize prompt")
+                        }
+                    },
+                    PromptFormat::Text => HttpResponse::Ok()
+                        .content_type("text/plain; charset=utf-8")
+                        .body(prompt.content),
+                };
+                if response.status().is_success() {
+                    if let Ok(value) = header::HeaderValue::from_str(&etag) {
+                        response.headers_mut().insert(header::ETAG, value);
+                    }
+                }
+                response
+            }
+            Ok(None) => missing_prompt_response(storage.get_ref().as_ref(), &id_uuid).await,
+            Err(e) => {
+                tracing::error!(prompt_id = %id_str, error = %e, "Failed to get prompt");
+                HttpResponse::InternalServerError().body("Failed to retrieve prompt")
+            }
+        },
+        Err(_) => {
+            tracing::warn!(prompt_id = %id_str, "Invalid UUID format provided");
+            HttpResponse::BadRequest().body("Invalid prompt ID format. Please use UUID.")
+        }
+    }
+}
+
+/// Serves a prompt's content as bytes, decoded per its `content_encoding`, with its
+/// declared `content_type`. The content is never rendered as a template.
+#[get("/{id}/raw")]
+async fn get_raw_prompt_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling GET /prompts/{}/raw", id_str);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let prompt = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    let bytes = match prompt.decoded_content() {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to decode prompt content");
+            return HttpResponse::UnprocessableEntity().body(e);
+        }
+    };
+    let content_type = prompt.content_type.clone().unwrap_or_else(|| {
+        if prompt.is_encoded() {
+            "application/octet-stream".to_string()
+        } else {
+            "text/plain; charset=utf-8".to_string()
+        }
+    });
+    // The content type is client-set, so keep browsers from running the content in the API's origin
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .insert_header((header::CONTENT_SECURITY_POLICY, "sandbox"))
+        .body(bytes)
+}
+
+#[post("")]
+async fn create_prompt_handler(
+    req: HttpRequest,
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
+    metrics: web::Data<Metrics>,
+    body: web::Bytes, // JSON body parsed into Prompt (strictly when --strict-schema is set)
+) -> impl Responder {
+    let mut prompt = match config.parse_prompt_body(&body) {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = reject_server_fields(&body) {
+        return resp;
+    }
+    config.prepare_for_save(&mut prompt);
+    if let Err(resp) = config.check_templates(&prompt) {
+        return resp;
+    }
+    if let Err(resp) = config.check_variables(&prompt) {
+        return resp;
+    }
+    prompt.owner = config.caller(&req);
+    let prompt_id = prompt.id; // ID is generated in the struct
+    tracing::info!(prompt_id = %prompt_id, "Handling POST /prompts");
+
+    // save_prompt upserts, so a client-supplied id must not overwrite an existing prompt
+    match storage.get_prompt(&prompt_id).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            tracing::warn!(prompt_id = %prompt_id, "Rejected create of a prompt id that already exists");
+            return HttpResponse::Conflict().body(format!(
+                "Prompt '{}' already exists. Use PUT /prompts/{} to replace it.",
+                prompt_id, prompt_id
+            ));
+        }
+        Err(e) => {
+            tracing::error!(prompt_id = %prompt_id, error = %e, "Failed to check for an existing prompt");
+            return HttpResponse::InternalServerError().body("Failed to create prompt");
+        }
+    }
+
+    match storage.save_prompt(&prompt).await {
+        Ok(_) => {
+            tracing::info!(prompt_id = %prompt_id, "Prompt created successfully");
+            webhooks.notify(WebhookEventKind::Created, prompt_id);
+            metrics.record(PromptOperation::Create);
+            created_prompt_response(&prompt)
+        }
+        Err(e) => {
+            tracing::error!(prompt_id = %prompt_id, error = %e, "Failed to create prompt");
+            HttpResponse::InternalServerError().body("Failed to create prompt")
+        }
+    }
+}
+
+#[put("/{id}")]
+async fn update_prompt_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
+    metrics: web::Data<Metrics>,
+    path: web::Path<String>,
+    req: HttpRequest,
+    body: web::Bytes, // JSON body with updated prompt
+) -> impl Responder {
+    let id_str = path.into_inner();
+    let if_match = req.headers().get(header::IF_MATCH).map(|v| v.to_str().unwrap_or_default().to_string());
+    let (base_content, body) = split_base_content(&body);
+    let mut prompt_update = match config.parse_prompt_body(&body) {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+    tracing::info!(prompt_id = %id_str, "Handling PUT /prompts/{}", id_str);
+
+    match Uuid::parse_str(&id_str) {
+        Ok(id_uuid) => {
+            // Ensure the ID in the path matches the ID in the body, or set it
+            prompt_update.id = id_uuid;
+
+            // The lock state and owner can only be changed through their own endpoints
+            let mut created = false;
+            match storage.get_prompt(&id_uuid).await {
+                Ok(Some(existing)) => {
+                    if let Err(resp) = check_not_locked(&config, &existing) {
+                        return resp;
+                    }
+                    // The client's copy is out of date: refuse rather than overwrite another edit
+                    if let Some(if_match) = if_match.as_deref().filter(|tag| !existing.matches_if_match(tag)) {
+                        tracing::warn!(prompt_id = %id_uuid, if_match, version = existing.version, "Refusing to update prompt changed since the client's ETag");
+                        return HttpResponse::PreconditionFailed()
+                            .insert_header((header::ETAG, existing.etag()))
+                            .body(format!("Prompt {} was changed since the ETag given in If-Match", id_uuid));
+                    }
+                    // Someone else changed the content since the client loaded it: keep both edits
+                    if let Some(base) = base_content.as_deref().filter(|base| *base != existing.content) {
+                        match prompt_model::merge_content(base, &existing.content, &prompt_update.content) {
+                            Ok(merged) => {
+                                tracing::info!(prompt_id = %id_uuid, version = existing.version, "Merged concurrent content edits");
+                                prompt_update.content = merged;
+                            }
+                            Err(conflicted) => {
+                                tracing::warn!(prompt_id = %id_uuid, version = existing.version, "Rejected update with conflicting content edits");
+                                return HttpResponse::Conflict().json(serde_json::json!({
+                                    "error": "The content was changed since base_content in ways that conflict with this update",
+                                    "current_version": existing.version,
+                                    "content": conflicted,
+                                }));
+                            }
+                        }
+                    }
+                    prompt_update.locked = existing.locked;
+                    prompt_update.owner = existing.owner;
+                    prompt_update.created_at = existing.created_at;
+                    prompt_update.version = existing.version.saturating_add(1);
+                }
+                Ok(None) if if_match.is_some() => {
+                    tracing::warn!(prompt_id = %id_uuid, "Refusing conditional update of a missing prompt");
+                    return HttpResponse::PreconditionFailed()
+                        .body(format!("Prompt {} does not exist, so If-Match can't match", id_uuid));
+                }
+                Ok(None) => {
+                    if let Err(resp) = reject_server_fields(&body) {
+                        return resp;
+                    }
+                    prompt_update.owner = config.caller(&req);
+                    created = true;
+                }
+                Err(e) => {
+                    tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to load prompt for update");
+                    return HttpResponse::InternalServerError().body("Failed to update prompt");
+                }
+            }
+            config.prepare_for_save(&mut prompt_update);
+            if let Err(resp) = config.check_templates(&prompt_update) {
+                return resp;
+            }
+            if let Err(resp) = config.check_variables(&prompt_update) {
+                return resp;
+            }
+
+            // Optional: Add validation for the prompt data here
+
+            match storage.save_prompt(&prompt_update).await { // Assuming save_prompt handles create/update
+                Ok(_) if created => {
+                    tracing::info!(prompt_id = %id_uuid, "Prompt created successfully");
+                    webhooks.notify(WebhookEventKind::Created, id_uuid);
+                    metrics.record(PromptOperation::Create);
+                    created_prompt_response(&prompt_update)
+                }
+                Ok(_) => {
+                    tracing::info!(prompt_id = %id_uuid, "Prompt updated successfully");
+                    webhooks.notify(WebhookEventKind::Updated, id_uuid);
+                    metrics.record(PromptOperation::Update);
+                    HttpResponse::Ok()
+                        .insert_header((header::ETAG, prompt_update.etag()))
+                        .json(prompt_update)
+                }
+                Err(e) => {
+                    tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to update prompt");
+                    // Consider specific errors, e.g., NotFound vs InternalServerError
+                    HttpResponse::InternalServerError().body("Failed to update prompt")
+                }
+            }
+        }
+        Err(_) => {
+            tracing::warn!(prompt_id = %id_str, "Invalid UUID format provided for update");
+            HttpResponse::BadRequest().body("Invalid prompt ID format. Please use UUID.")
+        }
+    }
+}
+
+/// Takes the optional `base_content` field, the content the client started its
+/// edit from, out of a PUT body. Bodies that aren't JSON objects are returned
+/// unchanged for [`AppConfig::parse_prompt_body`] to reject.
+fn split_base_content(body: &[u8]) -> (Option<String>, std::borrow::Cow<'_, [u8]>) {
+    let Ok(serde_json::Value::Object(mut object)) = serde_json::from_slice(body) else {
+        return (None, body.into());
+    };
+    match object.remove("base_content") {
+        Some(serde_json::Value::String(base)) => {
+            let rest = serde_json::to_vec(&object).unwrap_or_else(|_| body.to_vec());
+            (Some(base), rest.into())
+        }
+        _ => (None, body.into()),
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_prompt_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
+    metrics: web::Data<Metrics>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling DELETE /prompts/{}", id_str);
+
+    let id_uuid = match Uuid::parse_str(&id_str) {
+        Ok(id_uuid) => id_uuid,
+        Err(_) => {
+            tracing::warn!(prompt_id = %id_str, "Invalid UUID format provided for delete");
+            return HttpResponse::BadRequest().body("Invalid prompt ID format. Please use UUID.");
+        }
+    };
+    let if_match = req.headers().get(header::IF_MATCH).map(|v| v.to_str().unwrap_or_default().trim().to_string());
+
+    let mut dependents = Vec::new();
+    let mut expected_version = None;
+    match storage.get_prompt(&id_uuid).await {
+        Ok(Some(existing)) => {
+            if let Err(resp) = check_not_locked(&config, &existing) {
+                return resp;
+            }
+            if let Some(if_match) = if_match.as_deref().filter(|tag| *tag != "*") {
+                // The client's copy is out of date: refuse rather than delete another edit
+                if !existing.matches_if_match(if_match) {
+                    tracing::warn!(prompt_id = %id_uuid, if_match, version = existing.version, "Refusing to delete prompt changed since the client's ETag");
+                    return HttpResponse::PreconditionFailed()
+                        .insert_header((header::ETAG, existing.etag()))
+                        .body(format!("Prompt {} was changed since the ETag given in If-Match", id_uuid));
+                }
+                // Still refuse if an update lands between this check and the delete
+                expected_version = Some(existing.version);
+            }
+            if config.on_delete_with_dependents != DependentsPolicy::Allow {
+                dependents = match references::find_dependents(storage.get_ref().as_ref(), &existing).await {
+                    Ok(dependents) => dependents,
+                    Err(e) => {
+                        tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to find dependents of prompt to delete");
+                        return HttpResponse::InternalServerError().body("Failed to delete prompt");
+                    }
+                };
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to load prompt for delete");
+            return HttpResponse::InternalServerError().body("Failed to delete prompt");
+        }
+    }
+    if !dependents.is_empty() {
+        let ids: Vec<String> = dependents.iter().map(|d| d.id.to_string()).collect();
+        if config.on_delete_with_dependents == DependentsPolicy::Block {
+            tracing::warn!(prompt_id = %id_uuid, dependents = %ids.join(","), "Refusing to delete prompt with dependents");
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": format!("Prompt {} is referenced by {} other prompt(s)", id_uuid, dependents.len()),
+                "dependents": dependents,
+            }));
+        }
+        tracing::warn!(prompt_id = %id_uuid, dependents = %ids.join(","), "Deleting prompt that other prompts depend on");
+    }
+
+    let outcome = match expected_version {
+        Some(version) => storage.delete_prompt_if_version(&id_uuid, version).await,
+        None => storage
+            .delete_prompt(&id_uuid)
+            .await
+            .map(|deleted| if deleted { DeleteOutcome::Deleted } else { DeleteOutcome::NotFound }),
+    };
+
+    match outcome {
+        Ok(DeleteOutcome::Deleted) => {
+            tracing::info!(prompt_id = %id_uuid, "Prompt deleted successfully");
+            webhooks.notify(WebhookEventKind::Deleted, id_uuid);
+            metrics.record(PromptOperation::Delete);
+            let mut response = HttpResponse::NoContent(); // 204 No Content is standard for successful DELETE
+            if !dependents.is_empty() {
+                response.insert_header(("X-Prompt-Dependents", dependents.len().to_string()));
+            }
+            response.finish()
+        }
+        Ok(DeleteOutcome::NotFound) => {
+            tracing::warn!(prompt_id = %id_uuid, "Attempted to delete non-existent prompt");
+            HttpResponse::NotFound().body(format!("Prompt with id {} not found", id_str))
+        }
+        Ok(DeleteOutcome::VersionMismatch { current }) => {
+            tracing::warn!(prompt_id = %id_uuid, current, "Refusing to delete prompt edited since the expected version");
+            let mut response = HttpResponse::PreconditionFailed();
+            if let Ok(Some(existing)) = storage.get_prompt(&id_uuid).await {
+                response.insert_header((header::ETAG, existing.etag()));
+            }
+            response.body(format!("Prompt {} was changed since the ETag given in If-Match", id_uuid))
+        }
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to delete prompt");
+            HttpResponse::InternalServerError().body("Failed to delete prompt")
+        }
+    }
+}
+
+/// Lists the stored versions of a prompt, oldest first.
+#[get("/{id}/versions")]
+async fn list_versions_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling GET /prompts/{}/versions", id_str);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let prompt = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    match storage.list_prompt_versions(&id_uuid).await {
+        Ok(versions) => HttpResponse::Ok().json(serde_json::json!({
+            "id": id_uuid,
+            "current_version": prompt.version,
+            "versions": versions,
+        })),
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to list prompt versions");
+            HttpResponse::InternalServerError().body("Failed to list prompt versions")
+        }
+    }
+}
+
+/// Gets a prompt as it was at one of its versions.
+#[get("/{id}/versions/{version}")]
+async fn get_version_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<(String, u32)>,
+) -> impl Responder {
+    let (id_str, version) = path.into_inner();
+    tracing::info!(prompt_id = %id_str, version, "Handling GET /prompts/{}/versions/{}", id_str, version);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    match storage.get_prompt_version(&id_uuid, version).await {
+        Ok(Some(prompt)) => HttpResponse::Ok().json(prompt),
+        Ok(None) => match storage.get_prompt(&id_uuid).await {
+            Ok(Some(_)) => HttpResponse::NotFound().body(format!("Version {} of prompt {} is not stored", version, id_uuid)),
+            Ok(None) => missing_prompt_response(storage.get_ref().as_ref(), &id_uuid).await,
+            Err(e) => {
+                tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to get prompt");
+                HttpResponse::InternalServerError().body("Failed to retrieve prompt")
+            }
+        },
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, version, error = %e, "Failed to get prompt version");
+            HttpResponse::InternalServerError().body("Failed to retrieve prompt version")
+        }
+    }
+}
+
+/// Lists the prompts that reference, extend or include a prompt.
+#[get("/{id}/usage")]
+async fn prompt_usage_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling GET /prompts/{}/usage", id_str);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let prompt = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    match references::find_dependents(storage.get_ref().as_ref(), &prompt).await {
+        Ok(dependents) => HttpResponse::Ok().json(serde_json::json!({ "id": id_uuid, "dependents": dependents })),
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to find dependents of prompt");
+            HttpResponse::InternalServerError().body("Failed to find prompt usage")
+        }
+    }
+}
+
+/// Takes a slot from the prompt's `render_quota_per_hour`, or returns the 429
+/// response to send. Returns the slot to refund if the render fails, or `None`
+/// when the prompt has no quota.
+fn acquire_render_quota(quota: &RenderQuota, prompt: &Prompt) -> Result<Option<Instant>, HttpResponse> {
+    let Some(limit) = prompt.render_quota_per_hour else {
+        return Ok(None);
+    };
+    quota.try_acquire(&prompt.id, limit).map(Some).map_err(|retry_after| {
+        tracing::warn!(prompt_id = %prompt.id, limit, "Render quota exceeded");
+        HttpResponse::TooManyRequests()
+            .insert_header((header::RETRY_AFTER, retry_after.as_secs().max(1).to_string()))
+            .body(format!(
+                "Render quota of {} per hour exceeded for prompt {}",
+                limit, prompt.id
+            ))
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct RenderQuery {
+    /// Only substitute provided variables, leaving the rest of the template intact
+    #[serde(default)]
+    partial: bool,
+    /// Comma-separated section names to assemble in order, or `*` for all sections
+    sections: Option<String>,
+    /// Overrides `--on-missing-reference` for this render
+    on_missing_reference: Option<MissingReferencePolicy>,
+    /// `text` (default) or `openai` for an array of chat messages
+    #[serde(default)]
+    format: RenderFormat,
+}
+
+#[post("/{id}/render")]
+async fn render_prompt_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    quota: web::Data<RenderQuota>,
+    globals: web::Data<GlobalContext>,
+    path: web::Path<String>,
+    query: web::Query<RenderQuery>,
+    arguments: web::Json<serde_json::Map<String, serde_json::Value>>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling POST /prompts/{}/render", id_str);
+
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let prompt = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    let arguments = context::resolve_aliases(&prompt.variable_metadata, &arguments);
+    let arguments = context::with_defaults(&config.default_arguments, &prompt.default_arguments, &arguments);
+    let arguments = globals.apply(&arguments);
+    let policy = query.on_missing_reference.unwrap_or(config.on_missing_reference);
+    let engine = config.template_engine;
+    let max_depth = config.max_template_depth;
+    let sections: Option<Vec<String>> = query.sections.as_ref().map(|order| {
+        if order.trim() == "*" {
+            prompt.sections.iter().flat_map(|s| s.keys().cloned()).collect()
+        } else {
+            order.split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect()
+        }
+    });
+    let partial = sections.is_none() && query.partial;
+    let format = query.format;
+    if format == RenderFormat::Openai && (sections.is_some() || partial) {
+        return HttpResponse::UnprocessableEntity()
+            .body("format=openai can't be combined with sections or partial rendering");
+    }
+    if partial {
+        if prompt.extends.is_some() {
+            return HttpResponse::UnprocessableEntity()
+                .body("Partial rendering is not supported for prompts that extend another prompt");
+        }
+        if prompt.delimiters.is_some() {
+            return HttpResponse::UnprocessableEntity()
+                .body("Partial rendering is not supported for prompts with custom delimiters");
+        }
+    }
+
+    let slot = match acquire_render_quota(&quota, &prompt) {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+    let storage = storage.get_ref().clone();
+    let render = async move {
+        let arguments =
+            references::with_references(storage.as_ref(), &prompt, &arguments, policy, engine, max_depth).await?;
+        match sections {
+            Some(names) => template::render_sections(&prompt, &names, &arguments),
+            None if partial => template::render_partial(&prompt.content, &arguments),
+            // Serialized here so the output size limit covers the whole payload
+            None if format == RenderFormat::Openai => {
+                let messages = messages::render_messages(storage.as_ref(), &prompt, &arguments, engine, max_depth).await?;
+                serde_json::to_string(&messages).map_err(|e| RenderError::Template(e.to_string()))
+            }
+            None => template::render_prompt(storage.as_ref(), &prompt, &arguments, engine, max_depth).await,
+        }
+    };
+    let result = template::run_with_limits(config.render_limits, render).await;
+    if let (Err(_), Some(slot)) = (&result, slot) {
+        quota.refund(&id_uuid, slot);
+    }
+
+    match result {
+        Ok(messages) if format == RenderFormat::Openai => HttpResponse::Ok().content_type("application/json").body(messages),
+        Ok(rendered) => HttpResponse::Ok().json(serde_json::json!({ "rendered": rendered })),
+        Err(RenderError::Template(msg)) => {
+            tracing::warn!(prompt_id = %id_uuid, error = %msg, "Failed to render prompt");
+            HttpResponse::UnprocessableEntity().body(msg)
+        }
+        Err(RenderError::LimitExceeded(msg)) => {
+            tracing::warn!(prompt_id = %id_uuid, error = %msg, "Render exceeded limits");
+            HttpResponse::UnprocessableEntity().body(msg)
+        }
+        Err(RenderError::Storage(e)) => {
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Storage error while rendering prompt");
+            HttpResponse::InternalServerError().body("Failed to render prompt")
+        }
+    }
+}
+
+/// One side of a render diff: a stored version of the prompt, or candidate content
+/// rendered in place of the prompt's current content.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RenderDiffSide {
+    Version { version: u32 },
+    Content { content: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct RenderDiffRequest {
+    #[serde(default)]
+    arguments: serde_json::Map<String, serde_json::Value>,
+    from: RenderDiffSide,
+    to: RenderDiffSide,
+}
+
+/// Resolves one side of a render diff to the prompt to render, or the response to
+/// send: `404` for a version that isn't stored.
+async fn render_diff_prompt(storage: &dyn PromptStorage, current: &Prompt, side: RenderDiffSide) -> Result<Prompt, HttpResponse> {
+    match side {
+        RenderDiffSide::Version { version } if version == current.version => Ok(current.clone()),
+        RenderDiffSide::Version { version } => match storage.get_prompt_version(&current.id, version).await {
+            Ok(Some(prompt)) => Ok(prompt),
+            Ok(None) => Err(HttpResponse::NotFound().body(format!(
+                "Version {} of prompt {} is not stored; see GET /prompts/{}/versions",
+                version, current.id, current.id
+            ))),
+            Err(e) => {
+                tracing::error!(prompt_id = %current.id, version, error = %e, "Failed to get prompt version");
+                Err(HttpResponse::InternalServerError().body("Failed to retrieve prompt version"))
+            }
+        },
+        RenderDiffSide::Content { content } => Ok(Prompt { content, ..current.clone() }),
+    }
+}
+
+/// Renders `prompt` the way `POST /prompts/{id}/render` does without query options.
+async fn render_full(
+    storage: &Arc<dyn PromptStorage>,
+    config: &AppConfig,
+    globals: &GlobalContext,
+    prompt: Prompt,
+    arguments: &serde_json::Map<String, serde_json::Value>,
+) -> Result<String, RenderError> {
+    let arguments = context::resolve_aliases(&prompt.variable_metadata, arguments);
+    let arguments = context::with_defaults(&config.default_arguments, &prompt.default_arguments, &arguments);
+    let arguments = globals.apply(&arguments);
+    let (policy, engine, max_depth) = (config.on_missing_reference, config.template_engine, config.max_template_depth);
+    let storage = storage.clone();
+    let render = async move {
+        let arguments =
+            references::with_references(storage.as_ref(), &prompt, &arguments, policy, engine, max_depth).await?;
+        template::render_prompt(storage.as_ref(), &prompt, &arguments, engine, max_depth).await
+    };
+    template::run_with_limits(config.render_limits, render).await
+}
+
+/// Renders each stored test case of `prompt` and compares it with the expected output.
+async fn run_prompt_tests(
+    storage: &Arc<dyn PromptStorage>,
+    config: &AppConfig,
+    globals: &GlobalContext,
+    prompt: &Prompt,
+) -> TestReport {
+    let mut results = Vec::with_capacity(prompt.tests.len());
+    for (index, test) in prompt.tests.iter().enumerate() {
+        let rendered = render_full(storage, config, globals, prompt.clone(), &test.arguments).await;
+        if let Err(RenderError::Storage(e)) = &rendered {
+            tracing::error!(prompt_id = %prompt.id, index, error = %e, "Storage error while running prompt test");
+        }
+        results.push(TestCaseResult::check(index, test, rendered));
+    }
+    TestReport::new(prompt, results)
+}
+
+/// Runs a prompt's stored test cases and reports which passed.
+#[post("/{id}/run-tests")]
+async fn run_tests_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    quota: web::Data<RenderQuota>,
+    globals: web::Data<GlobalContext>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling POST /prompts/{}/run-tests", id_str);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let prompt = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    // Every case is a render, so the run takes one slot per case, all or nothing
+    let mut slots = Vec::with_capacity(prompt.tests.len());
+    for _ in &prompt.tests {
+        match acquire_render_quota(&quota, &prompt) {
+            Ok(slot) => slots.push(slot),
+            Err(resp) => {
+                slots.into_iter().flatten().for_each(|slot| quota.refund(&id_uuid, slot));
+                return resp;
+            }
+        }
+    }
+    let report = run_prompt_tests(storage.get_ref(), &config, &globals, &prompt).await;
+    // As for other renders, cases that failed to render don't count
+    for (slot, result) in slots.into_iter().zip(&report.results) {
+        if let (Some(slot), Some(_)) = (slot, &result.error) {
+            quota.refund(&id_uuid, slot);
+        }
+    }
+    tracing::info!(prompt_id = %id_uuid, passed = report.passed, failed = report.failed, "Ran prompt tests");
+    HttpResponse::Ok().json(report)
+}
+
+/// Compiles the templates of every stored prompt into the render cache, logging
+/// how many were warmed and each prompt that failed to compile.
+async fn warm_template_cache(storage: &dyn PromptStorage, mode: EngineMode) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let (mut warmed, mut templates, mut failed) = (0, 0, 0);
+    for prompt in storage.list_prompts().await? {
+        match template::warm_template_cache(&prompt, mode) {
+            Ok(count) => {
+                warmed += 1;
+                templates += count;
+            }
+            Err(e) => {
+                failed += 1;
+                tracing::warn!(prompt_id = %prompt.id, name = %prompt.name, error = %e, "Failed to compile prompt template during warmup");
+            }
+        }
+    }
+    tracing::info!(
+        warmed,
+        templates,
+        failed,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "Warmed template cache"
+    );
+    Ok(())
+}
+
+/// Runs the stored test cases of the prompts `ids`, or of every prompt that has
+/// test cases when `ids` is empty, and prints a report. Returns true if all passed.
+async fn run_test_command(
+    storage: &Arc<dyn PromptStorage>,
+    config: &AppConfig,
+    globals: &GlobalContext,
+    ids: &[String],
+) -> anyhow::Result<bool> {
+    let prompts = if ids.is_empty() {
+        storage.list_prompts().await?.into_iter().filter(|p| !p.tests.is_empty()).collect()
+    } else {
+        let mut prompts = Vec::with_capacity(ids.len());
+        for id in ids {
+            let uuid = Uuid::parse_str(id).map_err(|_| anyhow::anyhow!("Invalid prompt id '{}'", id))?;
+            let prompt = storage.get_prompt(&uuid).await?.ok_or_else(|| anyhow::anyhow!("Prompt {} not found", uuid))?;
+            prompts.push(prompt);
+        }
+        prompts
+    };
+
+    let (mut passed, mut failed) = (0, 0);
+    for prompt in &prompts {
+        let report = run_prompt_tests(storage, config, globals, prompt).await;
+        passed += report.passed;
+        failed += report.failed;
+        for result in &report.results {
+            let status = if result.passed { "PASS" } else { "FAIL" };
+            println!("{} {} ({}) {}", status, report.name, report.id, result.label());
+            if let Some(error) = &result.error {
+                println!("    render error: {}", error);
+            }
+            if let Some(diff) = &result.diff {
+                for line in diff.lines() {
+                    println!("    {}", line);
+                }
+            }
+        }
+    }
+    println!("{} passed, {} failed across {} prompt(s)", passed, failed, prompts.len());
+    Ok(failed == 0)
+}
+
+/// Renders two versions of a prompt with the same arguments and diffs the outputs.
+#[post("/{id}/render-diff")]
+async fn render_diff_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    quota: web::Data<RenderQuota>,
+    globals: web::Data<GlobalContext>,
+    path: web::Path<String>,
+    request: web::Json<RenderDiffRequest>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling POST /prompts/{}/render-diff", id_str);
+
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let current = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    let RenderDiffRequest { arguments, from, to } = request.into_inner();
+    let slot = match acquire_render_quota(&quota, &current) {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+    let refund = || {
+        if let Some(slot) = slot {
+            quota.refund(&id_uuid, slot);
+        }
+    };
+    let mut rendered = Vec::with_capacity(2);
+    for (label, side) in [("from", from), ("to", to)] {
+        let prompt = match render_diff_prompt(storage.get_ref().as_ref(), &current, side).await {
+            Ok(prompt) => prompt,
+            Err(resp) => {
+                refund();
+                return resp;
+            }
+        };
+        match render_full(storage.get_ref(), &config, &globals, prompt, &arguments).await {
+            Ok(output) => rendered.push(output),
+            Err(RenderError::Storage(e)) => {
+                refund();
+                tracing::error!(prompt_id = %id_uuid, error = %e, "Storage error while rendering prompt");
+                return HttpResponse::InternalServerError().body("Failed to render prompt");
+            }
+            Err(e) => {
+                refund();
+                tracing::warn!(prompt_id = %id_uuid, side = label, error = %e, "Failed to render prompt for diff");
+                return HttpResponse::UnprocessableEntity().body(format!("Failed to render '{}': {}", label, e));
+            }
+        }
+    }
+
+    let diff = prompt_model::unified_diff(&rendered[0], &rendered[1]);
+    HttpResponse::Ok().json(serde_json::json!({
+        "id": id_uuid,
+        "changed": !diff.is_empty(),
+        "from": rendered[0],
+        "to": rendered[1],
+        "diff": diff,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct SandboxRequest {
+    content: String,
+    #[serde(default)]
+    arguments: serde_json::Map<String, serde_json::Value>,
+}
+
+#[post("/sandbox")]
+async fn sandbox_handler(
+    config: web::Data<AppConfig>,
+    request: web::Json<SandboxRequest>,
+) -> impl Responder {
+    tracing::info!("Handling POST /prompts/sandbox");
+    let Some(limits) = config.sandbox_limits else {
+        return HttpResponse::NotFound().body("The template sandbox is disabled. Start the server with --enable-sandbox.");
+    };
+
+    let SandboxRequest { content, arguments } = request.into_inner();
+    match sandbox::render(content, arguments, config.template_engine, limits).await {
+        Ok(rendered) => HttpResponse::Ok().json(serde_json::json!({ "rendered": rendered })),
+        Err(e @ RenderError::Storage(_)) => {
+            tracing::error!(error = %e, "Unexpected storage error in sandbox render");
+            HttpResponse::InternalServerError().body("Failed to render template")
+        }
+        Err(e) => {
+            tracing::debug!(error = %e, "Sandbox render failed");
+            HttpResponse::UnprocessableEntity().json(serde_json::json!({ "error": e.to_string() }))
+        }
+    }
+}
+
+/// Checks a prompt body for template syntax errors and lint warnings without saving it.
+#[post("/validate")]
+async fn validate_prompt_handler(config: web::Data<AppConfig>, body: web::Bytes) -> impl Responder {
+    tracing::info!("Handling POST /prompts/validate");
+    let prompt = match config.parse_prompt_body(&body) {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    let error = template::validate_prompt(&prompt, config.template_engine).err();
+    let warnings = lint::lint_prompt(&prompt, config.template_engine);
+    HttpResponse::Ok().json(serde_json::json!({
+        "valid": error.is_none() && warnings.is_empty(),
+        "error": error,
+        "warnings": warnings,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckEngineQuery {
+    engine: TemplateEngine,
+}
+
+#[post("/{id}/check-engine")]
+async fn check_engine_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+    query: web::Query<CheckEngineQuery>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    let engine = query.engine;
+    tracing::info!(prompt_id = %id_str, engine = engine.name(), "Handling POST /prompts/{}/check-engine", id_str);
+
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let prompt = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    let error = template::check_syntax(engine, &prompt.content).err();
+    HttpResponse::Ok().json(serde_json::json!({
+        "id": prompt.id,
+        "engine": engine,
+        "compatible": error.is_none(),
+        "error": error,
+    }))
+}
+
+#[get("/{id}/template-variables")]
+async fn template_variables_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling GET /prompts/{}/template-variables", id_str);
+
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let prompt = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    let used = match variables::prompt_variables(&prompt) {
+        Ok(used) => used,
+        Err(e) => return HttpResponse::UnprocessableEntity().body(e.to_string()),
+    };
+    HttpResponse::Ok().json(serde_json::json!({
+        "id": prompt.id,
+        "variables": used,
+        "declared": prompt.variables,
+    }))
+}
+
+#[get("/{id}/sections/{name}")]
+async fn get_section_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (id_str, name) = path.into_inner();
+    tracing::info!(prompt_id = %id_str, section = %name, "Handling GET /prompts/{}/sections/{}", id_str, name);
+
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let prompt = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    match prompt.sections.as_ref().and_then(|sections| sections.get(&name)) {
+        Some(content) => HttpResponse::Ok().json(serde_json::json!({ "name": name, "content": content })),
+        None => {
+            tracing::warn!(prompt_id = %id_uuid, section = %name, "Section not found");
+            HttpResponse::NotFound().body(format!("Prompt {} has no section '{}'", id_uuid, name))
+        }
+    }
+}
+
+#[post("/{id}/fork")]
+async fn fork_prompt_handler(
+    req: HttpRequest,
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling POST /prompts/{}/fork", id_str);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let original = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    // The draft belongs to whoever forked it, not to the original's owner
+    let mut draft = original.fork();
+    draft.owner = config.caller(&req);
+    match storage.save_prompt(&draft).await {
+        Ok(_) => {
+            tracing::info!(prompt_id = %id_uuid, draft_id = %draft.id, "Prompt forked");
+            webhooks.notify(WebhookEventKind::Created, draft.id);
+            created_prompt_response(&draft)
+        }
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to save forked prompt");
+            HttpResponse::InternalServerError().body("Failed to fork prompt")
+        }
+    }
+}
+
+#[post("/{id}/merge")]
+async fn merge_prompt_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling POST /prompts/{}/merge", id_str);
+    let draft_id = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let mut draft = match load_prompt(storage.get_ref().as_ref(), &draft_id).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+    let Some(original_id) = draft.forked_from else {
+        return HttpResponse::UnprocessableEntity().body(format!("Prompt {} is not a fork", draft_id));
+    };
+    let mut original = match load_prompt(storage.get_ref().as_ref(), &original_id).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = check_not_locked(&config, &original) {
+        return resp;
+    }
+    // Merging a draft of an older version would silently drop the changes made since
+    if let Some(base) = draft.forked_from_version.filter(|base| *base != original.version) {
+        tracing::warn!(prompt_id = %original_id, draft_id = %draft_id, base, current = original.version, "Refusing to merge a stale draft");
+        return HttpResponse::Conflict().body(format!(
+            "Prompt {} is at version {}, but draft {} was forked from version {}. Fork it again to merge.",
+            original_id, original.version, draft_id, base
+        ));
+    }
+
+    original.merge_from(&draft);
+    config.prepare_for_save(&mut original);
+    if let Err(resp) = config.check_templates(&original) {
+        return resp;
+    }
+    if let Err(resp) = config.check_variables(&original) {
+        return resp;
+    }
+    match storage.save_prompt(&original).await {
+        Ok(_) => {
+            tracing::info!(prompt_id = %original_id, draft_id = %draft_id, version = original.version, "Merged draft into prompt");
+            webhooks.notify(WebhookEventKind::Updated, original_id);
+            // The draft now matches the merged version, so it can be edited and merged again
+            draft.forked_from_version = Some(original.version);
+            if let Err(e) = storage.save_prompt(&draft).await {
+                tracing::warn!(draft_id = %draft_id, error = %e, "Failed to record the merged version on the draft");
+            }
+            HttpResponse::Ok().json(original)
+        }
+        Err(e) => {
+            tracing::error!(prompt_id = %original_id, error = %e, "Failed to save merged prompt");
+            HttpResponse::InternalServerError().body("Failed to merge prompt")
+        }
+    }
+}
+
+#[get("/{id}/forks")]
+async fn list_forks_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling GET /prompts/{}/forks", id_str);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    match storage.list_forks(&id_uuid).await {
+        Ok(forks) => HttpResponse::Ok().json(forks),
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to list forks");
+            HttpResponse::InternalServerError().body("Failed to list forks")
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TransferRequest {
+    new_owner: String,
+}
+
+#[post("/{id}/transfer")]
+async fn transfer_prompt_handler(
+    req: HttpRequest,
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
+    path: web::Path<String>,
+    request: web::Json<TransferRequest>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling POST /prompts/{}/transfer", id_str);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let new_owner = request.into_inner().new_owner.trim().to_string();
+    if new_owner.is_empty() {
+        return HttpResponse::BadRequest().body("new_owner must not be empty");
+    }
+    let mut prompt = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    let caller = config.caller(&req);
+    let is_owner = caller.is_some() && caller == prompt.owner;
+    if !is_owner && !config.is_admin(&req) {
+        tracing::warn!(prompt_id = %id_uuid, caller = ?caller, "Rejected ownership transfer");
+        return HttpResponse::Forbidden().body("Only the prompt's owner or an admin can transfer it");
+    }
+    if let Err(resp) = check_not_locked(&config, &prompt) {
+        return resp;
+    }
+
+    let previous_owner = prompt.owner.replace(new_owner);
+    match storage.save_prompt(&prompt).await {
+        Ok(_) => {
+            tracing::info!(prompt_id = %id_uuid, from = ?previous_owner, to = ?prompt.owner, "Prompt ownership transferred");
+            webhooks.notify(WebhookEventKind::Updated, id_uuid);
+            HttpResponse::Ok().json(prompt)
+        }
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to save transferred prompt");
+            HttpResponse::InternalServerError().body("Failed to transfer prompt")
+        }
+    }
+}
+
+#[post("/{id}/attachments")]
+async fn upload_attachments_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
+    path: web::Path<String>,
+    mut payload: Multipart,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling POST /prompts/{}/attachments", id_str);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let prompt = match load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = check_not_locked(&config, &prompt) {
+        return resp;
+    }
+
+    let mut stored = Vec::new();
+    loop {
+        let mut field = match payload.try_next().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return HttpResponse::BadRequest().body(format!("Invalid multipart body: {}", e)),
+        };
+        // Use the uploaded file's name, falling back to the form field name
+        let disposition = field.content_disposition();
+        let Some(name) = disposition.get_filename().or_else(|| disposition.get_name()).map(str::to_string) else {
+            return HttpResponse::BadRequest().body("Each attachment needs a file name");
+        };
+        if !attachments::is_valid_name(&name) {
+            return HttpResponse::BadRequest().body(format!(
+                "Invalid attachment name '{}': use letters, digits, '.', '-' and '_', not starting with '.'",
+                name
+            ));
+        }
+
+        let mut data = Vec::new();
+        loop {
+            match field.try_next().await {
+                Ok(Some(chunk)) => {
+                    if data.len() + chunk.len() > config.max_attachment_bytes {
+                        tracing::warn!(prompt_id = %id_uuid, attachment = %name, "Rejected oversized attachment");
+                        return HttpResponse::PayloadTooLarge().body(format!(
+                            "Attachment '{}' exceeds the limit of {} bytes",
+                            name, config.max_attachment_bytes
+                        ));
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+                Ok(None) => break,
+                Err(e) => return HttpResponse::BadRequest().body(format!("Invalid multipart body: {}", e)),
+            }
+        }
+
+        if let Err(e) = storage.save_attachment(&id_uuid, &name, &data).await {
+            tracing::error!(prompt_id = %id_uuid, attachment = %name, error = %e, "Failed to save attachment");
+            return HttpResponse::InternalServerError().body(format!("Failed to save attachment '{}'", name));
+        }
+        stored.push(AttachmentInfo { name, size: data.len() as u64 });
+    }
+
+    if stored.is_empty() {
+        return HttpResponse::BadRequest().body("No files in the multipart body");
+    }
+    tracing::info!(prompt_id = %id_uuid, count = stored.len(), "Stored prompt attachments");
+    webhooks.notify(WebhookEventKind::Updated, id_uuid);
+    HttpResponse::Created()
+        .insert_header((header::LOCATION, format!("/prompts/{}/attachments", id_uuid)))
+        .json(stored)
+}
+
+#[get("/{id}/attachments")]
+async fn list_attachments_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling GET /prompts/{}/attachments", id_str);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = load_prompt(storage.get_ref().as_ref(), &id_uuid).await {
+        return resp;
+    }
+    match storage.list_attachments(&id_uuid).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to list attachments");
+            HttpResponse::InternalServerError().body("Failed to list attachments")
+        }
+    }
+}
+
+#[get("/{id}/attachments/{name}")]
+async fn get_attachment_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (id_str, name) = path.into_inner();
+    tracing::info!(prompt_id = %id_str, attachment = %name, "Handling GET /prompts/{}/attachments/{}", id_str, name);
+    let id_uuid = match parse_prompt_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    if !attachments::is_valid_name(&name) {
+        return HttpResponse::BadRequest().body(format!("Invalid attachment name '{}'", name));
+    }
+    match storage.get_attachment(&id_uuid, &name).await {
+        // Uploads are untrusted, so browsers must download them rather than render
+        // them in the API's origin. Valid names need no quoting or escaping.
+        Ok(Some(data)) => HttpResponse::Ok()
+            .content_type(attachments::content_type(&name))
+            .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name)))
+            .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+            .insert_header((header::CONTENT_SECURITY_POLICY, "sandbox"))
+            .body(data),
+        Ok(None) => HttpResponse::NotFound().body(format!("Prompt {} has no attachment '{}'", id_uuid, name)),
+        Err(e) => {
+            tracing::error!(prompt_id = %id_uuid, attachment = %name, error = %e, "Failed to load attachment");
+            HttpResponse::InternalServerError().body("Failed to load attachment")
+        }
+    }
+}
+
+#[get("")]
+async fn list_categories_handler(storage: web::Data<Arc<dyn PromptStorage>>) -> impl Responder {
+    tracing::info!("Handling GET /categories");
+    match storage.list_categories().await {
+        Ok(categories) => HttpResponse::Ok().json(categories),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list categories");
+            HttpResponse::InternalServerError().body("Failed to list categories")
+        }
+    }
+}
+
+#[post("")]
+async fn create_category_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    category: web::Json<Category>,
+) -> impl Responder {
+    let mut category = category.into_inner();
+    category.name = category.name.trim().to_string();
+    tracing::info!(category = %category.name, "Handling POST /categories");
+    if category.name.is_empty() {
+        return HttpResponse::BadRequest().body("Category name must not be empty");
+    }
+
+    match storage.get_category(&category.name).await {
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict().body(format!("Category '{}' already exists", category.name));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!(category = %category.name, error = %e, "Failed to check for existing category");
+            return HttpResponse::InternalServerError().body("Failed to create category");
+        }
+    }
+    match storage.save_category(&category).await {
+        Ok(_) => HttpResponse::Created()
+            .insert_header((header::LOCATION, format!("/categories/{}", category.name)))
+            .json(category),
+        Err(e) => {
+            tracing::error!(category = %category.name, error = %e, "Failed to create category");
+            HttpResponse::InternalServerError().body("Failed to create category")
+        }
+    }
+}
+
+// Category names may contain `/` for subcategories, so they match the rest of the path
+#[get("/{name:.*}")]
+async fn get_category_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let name = path.into_inner();
+    tracing::info!(category = %name, "Handling GET /categories/{}", name);
+    match storage.get_category(&name).await {
+        Ok(Some(category)) => HttpResponse::Ok().json(category),
+        Ok(None) => HttpResponse::NotFound().body(format!("Category '{}' not found", name)),
+        Err(e) => {
+            tracing::error!(category = %name, error = %e, "Failed to load category");
+            HttpResponse::InternalServerError().body("Failed to retrieve category")
+        }
+    }
+}
+
+#[put("/{name:.*}")]
+async fn update_category_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+    category: web::Json<Category>,
+) -> impl Responder {
+    let name = path.into_inner();
+    tracing::info!(category = %name, "Handling PUT /categories/{}", name);
+    let category = Category { name, ..category.into_inner() };
+    let created = match storage.get_category(&category.name).await {
+        Ok(existing) => existing.is_none(),
+        Err(e) => {
+            tracing::error!(category = %category.name, error = %e, "Failed to check for existing category");
+            return HttpResponse::InternalServerError().body("Failed to update category");
+        }
+    };
+    match storage.save_category(&category).await {
+        Ok(_) if created => HttpResponse::Created()
+            .insert_header((header::LOCATION, format!("/categories/{}", category.name)))
+            .json(category),
+        Ok(_) => HttpResponse::Ok().json(category),
+        Err(e) => {
+            tracing::error!(category = %category.name, error = %e, "Failed to update category");
+            HttpResponse::InternalServerError().body("Failed to update category")
+        }
+    }
+}
 
-    /// PostgreSQL connection URL
-    #[arg(long)]
-    db_url: Option<String>,
+#[delete("/{name:.*}")]
+async fn delete_category_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let name = path.into_inner();
+    tracing::info!(category = %name, "Handling DELETE /categories/{}", name);
+    match storage.delete_category(&name).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().body(format!("Category '{}' not found", name)),
+        Err(e) => {
+            tracing::error!(category = %name, error = %e, "Failed to delete category");
+            HttpResponse::InternalServerError().body("Failed to delete category")
+        }
+    }
+}
 
-    /// Directory for prompt storage (when using filesystem storage)
-    #[arg(long, default_value = "./prompts")]
-    prompt_dir: String,
+/// Parses a collection id from a path segment, or returns the 400 response to send.
+fn parse_collection_id(id_str: &str) -> Result<Uuid, HttpResponse> {
+    Uuid::parse_str(id_str).map_err(|_| {
+        tracing::warn!(collection_id = %id_str, "Invalid collection UUID format provided");
+        HttpResponse::BadRequest().body("Invalid collection ID format. Please use UUID.")
+    })
 }
 
-// --- REST Handlers Implementation ---
+/// Returns the 422 response to send if a collection lists prompts that don't exist,
+/// or the 500 response if they can't be checked.
+async fn check_collection_members(storage: &dyn PromptStorage, collection: &Collection) -> Result<(), HttpResponse> {
+    let mut unknown = Vec::new();
+    for id in &collection.prompt_ids {
+        match storage.get_prompt(id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => unknown.push(id.to_string()),
+            Err(e) => {
+                tracing::error!(collection_id = %collection.id, prompt_id = %id, error = %e, "Failed to check collection member");
+                return Err(HttpResponse::InternalServerError().body("Failed to save collection"));
+            }
+        }
+    }
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(HttpResponse::UnprocessableEntity().body(format!("Unknown prompt IDs in collection: {}", unknown.join(", "))))
+    }
+}
 
 #[get("")]
-async fn list_prompts_handler(storage: web::Data<Arc<dyn PromptStorage>>) -> impl Responder {
-    tracing::info!("Handling GET /prompts");
-    match storage.list_prompts().await {
-        Ok(prompts) => HttpResponse::Ok().json(prompts),
+async fn list_collections_handler(storage: web::Data<Arc<dyn PromptStorage>>) -> impl Responder {
+    tracing::info!("Handling GET /collections");
+    match storage.list_collections().await {
+        Ok(collections) => HttpResponse::Ok().json(collections),
         Err(e) => {
-            tracing::error!(error = %e, "Failed to list prompts");
-            HttpResponse::InternalServerError().body("Failed to list prompts")
+            tracing::error!(error = %e, "Failed to list collections");
+            HttpResponse::InternalServerError().body("Failed to list collections")
+        }
+    }
+}
+
+#[post("")]
+async fn create_collection_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    collection: web::Json<Collection>,
+) -> impl Responder {
+    let mut collection = collection.into_inner();
+    collection.name = collection.name.trim().to_string();
+    tracing::info!(collection_id = %collection.id, name = %collection.name, "Handling POST /collections");
+    if collection.name.is_empty() {
+        return HttpResponse::BadRequest().body("Collection name must not be empty");
+    }
+
+    match storage.get_collection(&collection.id).await {
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict().body(format!("Collection {} already exists", collection.id));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!(collection_id = %collection.id, error = %e, "Failed to check for existing collection");
+            return HttpResponse::InternalServerError().body("Failed to create collection");
+        }
+    }
+    if let Err(resp) = check_collection_members(storage.get_ref().as_ref(), &collection).await {
+        return resp;
+    }
+    match storage.save_collection(&collection).await {
+        Ok(_) => HttpResponse::Created()
+            .insert_header((header::LOCATION, format!("/collections/{}", collection.id)))
+            .json(collection),
+        Err(e) => {
+            tracing::error!(collection_id = %collection.id, error = %e, "Failed to create collection");
+            HttpResponse::InternalServerError().body("Failed to create collection")
         }
     }
 }
 
 #[get("/{id}")]
-async fn get_prompt_handler(
+async fn get_collection_handler(
     storage: web::Data<Arc<dyn PromptStorage>>,
     path: web::Path<String>,
 ) -> impl Responder {
     let id_str = path.into_inner();
-    tracing::info!(prompt_id = %id_str, "Handling GET /prompts/{}", id_str);
+    tracing::info!(collection_id = %id_str, "Handling GET /collections/{}", id_str);
+    let id = match parse_collection_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    match storage.get_collection(&id).await {
+        Ok(Some(collection)) => HttpResponse::Ok().json(collection),
+        Ok(None) => HttpResponse::NotFound().body(format!("Collection {} not found", id)),
+        Err(e) => {
+            tracing::error!(collection_id = %id, error = %e, "Failed to load collection");
+            HttpResponse::InternalServerError().body("Failed to retrieve collection")
+        }
+    }
+}
 
-    match Uuid::parse_str(&id_str) {
-        Ok(id_uuid) => match storage.get_prompt(&id_uuid).await {
-            Ok(Some(prompt)) => HttpResponse::Ok().json(prompt),
-            Ok(None) => {
-                tracing::warn!(prompt_id = %id_str, "Prompt not found");
-                HttpResponse::NotFound().body(format!("Prompt with id {} not found", id_str))
-            }
-            Err(e) => {
-                tracing::error!(prompt_id = %id_str, error = %e, "Failed to get prompt");
-                HttpResponse::InternalServerError().body("Failed to retrieve prompt")
-            }
-        },
-        Err(_) => {
-            tracing::warn!(prompt_id = %id_str, "Invalid UUID format provided");
-            HttpResponse::BadRequest().body("Invalid prompt ID format. Please use UUID.")
+#[put("/{id}")]
+async fn update_collection_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+    collection: web::Json<Collection>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(collection_id = %id_str, "Handling PUT /collections/{}", id_str);
+    let id = match parse_collection_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let collection = Collection { id, ..collection.into_inner() };
+    if collection.name.trim().is_empty() {
+        return HttpResponse::BadRequest().body("Collection name must not be empty");
+    }
+    if let Err(resp) = check_collection_members(storage.get_ref().as_ref(), &collection).await {
+        return resp;
+    }
+    let created = match storage.get_collection(&id).await {
+        Ok(existing) => existing.is_none(),
+        Err(e) => {
+            tracing::error!(collection_id = %id, error = %e, "Failed to check for existing collection");
+            return HttpResponse::InternalServerError().body("Failed to update collection");
+        }
+    };
+    match storage.save_collection(&collection).await {
+        Ok(_) if created => HttpResponse::Created()
+            .insert_header((header::LOCATION, format!("/collections/{}", collection.id)))
+            .json(collection),
+        Ok(_) => HttpResponse::Ok().json(collection),
+        Err(e) => {
+            tracing::error!(collection_id = %id, error = %e, "Failed to update collection");
+            HttpResponse::InternalServerError().body("Failed to update collection")
         }
     }
 }
 
-#[post("")]
-async fn create_prompt_handler(
+#[delete("/{id}")]
+async fn delete_collection_handler(
     storage: web::Data<Arc<dyn PromptStorage>>,
-    prompt_data: web::Json<Prompt> // Expect JSON body deserialized into Prompt
+    path: web::Path<String>,
 ) -> impl Responder {
-    let prompt = prompt_data.into_inner();
-    let prompt_id = prompt.id; // ID is generated in the struct
-    tracing::info!(prompt_id = %prompt_id, "Handling POST /prompts");
+    let id_str = path.into_inner();
+    tracing::info!(collection_id = %id_str, "Handling DELETE /collections/{}", id_str);
+    let id = match parse_collection_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    match storage.delete_collection(&id).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().body(format!("Collection {} not found", id)),
+        Err(e) => {
+            tracing::error!(collection_id = %id, error = %e, "Failed to delete collection");
+            HttpResponse::InternalServerError().body("Failed to delete collection")
+        }
+    }
+}
+
+#[get("/{id}/prompts")]
+async fn collection_prompts_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(collection_id = %id_str, "Handling GET /collections/{}/prompts", id_str);
+    let id = match parse_collection_id(&id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let collection = match storage.get_collection(&id).await {
+        Ok(Some(collection)) => collection,
+        Ok(None) => return HttpResponse::NotFound().body(format!("Collection {} not found", id)),
+        Err(e) => {
+            tracing::error!(collection_id = %id, error = %e, "Failed to load collection");
+            return HttpResponse::InternalServerError().body("Failed to retrieve collection prompts");
+        }
+    };
+
+    // Members deleted since the collection was saved are left out
+    let mut prompts = Vec::with_capacity(collection.prompt_ids.len());
+    for prompt_id in &collection.prompt_ids {
+        match storage.get_prompt(prompt_id).await {
+            Ok(Some(prompt)) => prompts.push(prompt),
+            Ok(None) => tracing::debug!(collection_id = %id, prompt_id = %prompt_id, "Skipping missing collection member"),
+            Err(e) => {
+                tracing::error!(collection_id = %id, prompt_id = %prompt_id, error = %e, "Failed to load collection member");
+                return HttpResponse::InternalServerError().body("Failed to retrieve collection prompts");
+            }
+        }
+    }
+    HttpResponse::Ok().json(prompts)
+}
 
-    // Optional: Add validation for the prompt data here
+/// Sets the lock state of a prompt and saves it.
+async fn set_prompt_lock(
+    req: &HttpRequest,
+    storage: &dyn PromptStorage,
+    config: &AppConfig,
+    webhooks: &WebhookDispatcher,
+    id_str: &str,
+    locked: bool,
+) -> HttpResponse {
+    let id_uuid = match parse_prompt_id(id_str) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let mut prompt = match load_prompt(storage, &id_uuid).await {
+        Ok(prompt) => prompt,
+        Err(resp) => return resp,
+    };
+
+    let caller = config.caller(req);
+    let is_owner = caller.is_some() && caller == prompt.owner;
+    if !is_owner && !config.is_admin(req) {
+        tracing::warn!(prompt_id = %id_uuid, caller = ?caller, locked, "Rejected prompt lock change");
+        return HttpResponse::Forbidden().body("Only the prompt's owner or an admin can lock or unlock it");
+    }
 
+    prompt.locked = locked;
     match storage.save_prompt(&prompt).await {
         Ok(_) => {
-            tracing::info!(prompt_id = %prompt_id, "Prompt created successfully");
-            // Return the created prompt (including the generated ID)
-            HttpResponse::Created().json(prompt)
+            tracing::info!(prompt_id = %id_uuid, locked, "Prompt lock state changed");
+            webhooks.notify(WebhookEventKind::Updated, id_uuid);
+            HttpResponse::Ok().json(prompt)
         }
         Err(e) => {
-            tracing::error!(prompt_id = %prompt_id, error = %e, "Failed to create prompt");
-            HttpResponse::InternalServerError().body("Failed to create prompt")
+            tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to change prompt lock state");
+            HttpResponse::InternalServerError().body("Failed to change prompt lock state")
         }
     }
 }
 
-#[put("/{id}")]
-async fn update_prompt_handler(
+#[post("/{id}/lock")]
+async fn lock_prompt_handler(
+    req: HttpRequest,
     storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
     path: web::Path<String>,
-    prompt_data: web::Json<Prompt> // Expect JSON body with updated prompt
 ) -> impl Responder {
     let id_str = path.into_inner();
-    let mut prompt_update = prompt_data.into_inner();
-    tracing::info!(prompt_id = %id_str, "Handling PUT /prompts/{}", id_str);
+    tracing::info!(prompt_id = %id_str, "Handling POST /prompts/{}/lock", id_str);
+    set_prompt_lock(&req, storage.get_ref().as_ref(), &config, &webhooks, &id_str, true).await
+}
 
-    match Uuid::parse_str(&id_str) {
-        Ok(id_uuid) => {
-            // Ensure the ID in the path matches the ID in the body, or set it
-            prompt_update.id = id_uuid;
+#[post("/{id}/unlock")]
+async fn unlock_prompt_handler(
+    req: HttpRequest,
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+    webhooks: web::Data<WebhookDispatcher>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let id_str = path.into_inner();
+    tracing::info!(prompt_id = %id_str, "Handling POST /prompts/{}/unlock", id_str);
+    set_prompt_lock(&req, storage.get_ref().as_ref(), &config, &webhooks, &id_str, false).await
+}
 
-            // Optional: Add validation for the prompt data here
+async fn admin_reload_handler(
+    req: HttpRequest,
+    config: web::Data<AppConfig>,
+    globals: web::Data<GlobalContext>,
+) -> impl Responder {
+    tracing::info!("Handling POST /admin/reload");
+    if let Err(resp) = check_admin(&config, &req) {
+        return resp;
+    }
+    match globals.reload().await {
+        Ok(count) => {
+            tracing::info!(globals = count, "Reloaded render context");
+            HttpResponse::Ok().json(serde_json::json!({ "globals": count }))
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to reload render context");
+            HttpResponse::InternalServerError().body(format!("Failed to reload context: {:#}", e))
+        }
+    }
+}
 
-            match storage.save_prompt(&prompt_update).await { // Assuming save_prompt handles create/update
-                Ok(_) => {
-                    tracing::info!(prompt_id = %id_uuid, "Prompt updated successfully");
-                    HttpResponse::Ok().json(prompt_update)
-                }
-                Err(e) => {
-                    tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to update prompt");
-                    // Consider specific errors, e.g., NotFound vs InternalServerError
-                    HttpResponse::InternalServerError().body("Failed to update prompt")
-                }
-            }
+async fn admin_maintenance_handler(
+    req: HttpRequest,
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    tracing::info!("Handling POST /admin/maintenance");
+    // Maintenance deletes files and rewrites storage, so never expose it unauthenticated
+    if config.admin_token.is_none() {
+        return HttpResponse::Forbidden().body("Maintenance is disabled. Start the server with --admin-token.");
+    }
+    if let Err(resp) = check_admin(&config, &req) {
+        return resp;
+    }
+    match storage.run_maintenance().await {
+        Ok(actions) => {
+            tracing::info!(storage = storage.backend_name(), actions = ?actions, "Storage maintenance finished");
+            HttpResponse::Ok().json(serde_json::json!({
+                "storage": storage.backend_name(),
+                "actions": actions,
+            }))
         }
-        Err(_) => {
-            tracing::warn!(prompt_id = %id_str, "Invalid UUID format provided for update");
-            HttpResponse::BadRequest().body("Invalid prompt ID format. Please use UUID.")
+        Err(e) => {
+            tracing::error!(error = %e, "Storage maintenance failed");
+            HttpResponse::InternalServerError().body(format!("Maintenance failed: {:#}", e))
         }
     }
 }
 
-#[delete("/{id}")]
-async fn delete_prompt_handler(
+/// Upper bound on `ops` for a single benchmark run.
+const MAX_BENCHMARK_OPS: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+struct BenchmarkQuery {
+    #[serde(default = "default_benchmark_ops")]
+    ops: usize,
+}
+
+fn default_benchmark_ops() -> usize {
+    100
+}
+
+async fn admin_benchmark_handler(
+    req: HttpRequest,
     storage: web::Data<Arc<dyn PromptStorage>>,
-    path: web::Path<String>
+    config: web::Data<AppConfig>,
+    query: web::Query<BenchmarkQuery>,
 ) -> impl Responder {
-    let id_str = path.into_inner();
-    tracing::info!(prompt_id = %id_str, "Handling DELETE /prompts/{}", id_str);
+    tracing::info!(ops = query.ops, "Handling POST /admin/benchmark");
+    // Benchmarks write to storage, so never expose them unauthenticated
+    if config.admin_token.is_none() {
+        return HttpResponse::Forbidden().body("The benchmark is disabled. Start the server with --admin-token.");
+    }
+    if let Err(resp) = check_admin(&config, &req) {
+        return resp;
+    }
+    if query.ops == 0 || query.ops > MAX_BENCHMARK_OPS {
+        return HttpResponse::BadRequest().body(format!("ops must be between 1 and {}", MAX_BENCHMARK_OPS));
+    }
 
-    match Uuid::parse_str(&id_str) {
-        Ok(id_uuid) => match storage.delete_prompt(&id_uuid).await {
-            Ok(true) => { // Assuming delete_prompt returns true if deleted, false if not found
-                tracing::info!(prompt_id = %id_uuid, "Prompt deleted successfully");
-                HttpResponse::NoContent().finish() // 204 No Content is standard for successful DELETE
-            }
-            Ok(false) => {
-                tracing::warn!(prompt_id = %id_uuid, "Attempted to delete non-existent prompt");
-                HttpResponse::NotFound().body(format!("Prompt with id {} not found", id_str))
+    match benchmark::run_benchmark(storage.get_ref().as_ref(), query.ops).await {
+        Ok(report) => {
+            tracing::info!(ops = report.ops, total_ms = report.total_ms, "Storage benchmark finished");
+            HttpResponse::Ok().json(report)
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Storage benchmark failed");
+            HttpResponse::InternalServerError().body(format!("Benchmark failed: {:#}", e))
+        }
+    }
+}
+
+/// Describes the templating the server applies, so clients can validate templates the same way.
+async fn capabilities_handler(config: web::Data<AppConfig>) -> impl Responder {
+    tracing::info!("Handling GET /capabilities");
+    // In auto mode content that matches neither engine is rendered with Tera
+    let engine = match config.template_engine {
+        EngineMode::Handlebars => TemplateEngine::Handlebars,
+        EngineMode::Tera | EngineMode::Auto => TemplateEngine::Tera,
+    };
+    HttpResponse::Ok().json(serde_json::json!({
+        "template_engine": config.template_engine,
+        "templates": template::TemplateCapabilities::for_engine(engine),
+    }))
+}
+
+async fn prompt_schema_handler() -> impl Responder {
+    tracing::info!("Handling GET /schema/prompt");
+    HttpResponse::Ok().json(prompt_model::prompt_schema())
+}
+
+/// Moment the server started, for uptime reporting.
+struct StartedAt(Instant);
+
+async fn info_handler(
+    storage: web::Data<Arc<dyn PromptStorage>>,
+    started_at: web::Data<StartedAt>,
+) -> impl Responder {
+    tracing::info!("Handling GET /info");
+    match storage.count_prompts().await {
+        Ok(count) => HttpResponse::Ok().json(serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "storage": storage.backend_name(),
+            "uptime_seconds": started_at.0.elapsed().as_secs(),
+            "prompt_count": count,
+        })),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to count prompts for info");
+            HttpResponse::InternalServerError().body("Failed to retrieve server info")
+        }
+    }
+}
+
+async fn metrics_handler(metrics: web::Data<Metrics>) -> impl Responder {
+    match metrics.render() {
+        Ok(body) => HttpResponse::Ok().content_type(metrics::CONTENT_TYPE).body(body),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to render metrics");
+            HttpResponse::InternalServerError().body("Failed to render metrics")
+        }
+    }
+}
+
+/// Delay before the second database connection attempt; doubles up to [`MAX_DB_RETRY_DELAY`].
+const INITIAL_DB_RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_DB_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Creates the selected storage backend. Backends with a database connection are
+/// retried with backoff for up to `--db-wait-timeout`, so the server can start
+/// before its database is ready.
+async fn connect_storage(args: &Cli) -> anyhow::Result<Arc<dyn PromptStorage>> {
+    let options = storage_options(args);
+    // Only connection failures are worth waiting out, not an unknown backend name or bad namespace
+    let retryable = options.connection.is_some()
+        && options.validate_namespace().is_ok()
+        && storage::registry::registered_storage_names().contains(&args.storage);
+    let deadline = Instant::now() + Duration::from_secs(args.db_wait_timeout);
+    let mut delay = INITIAL_DB_RETRY_DELAY;
+    let mut attempt = 1;
+
+    loop {
+        match storage::create_storage(&args.storage, &options).await {
+            Ok(storage) => return Ok(storage),
+            Err(e) if retryable && Instant::now() + delay < deadline => {
+                tracing::warn!(storage = %args.storage, attempt, retry_in_ms = delay.as_millis() as u64, error = %format!("{:#}", e), "Storage not ready; retrying");
             }
             Err(e) => {
-                tracing::error!(prompt_id = %id_uuid, error = %e, "Failed to delete prompt");
-                HttpResponse::InternalServerError().body("Failed to delete prompt")
+                tracing::error!(storage = %args.storage, attempt, error = %format!("{:#}", e), "Failed to initialize storage");
+                return Err(e.context(format!("Failed to initialize {} storage", args.storage)));
             }
-        },
-        Err(_) => {
-            tracing::warn!(prompt_id = %id_str, "Invalid UUID format provided for delete");
-            HttpResponse::BadRequest().body("Invalid prompt ID format. Please use UUID.")
         }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_DB_RETRY_DELAY);
+        attempt += 1;
+    }
+}
+
+/// Fallback for unmatched routes, so API clients get a JSON 404 instead of an empty body.
+async fn not_found_handler(req: HttpRequest) -> impl Responder {
+    HttpResponse::NotFound().json(serde_json::json!({
+        "error": format!("No route for {} {}", req.method(), req.path()),
+    }))
+}
+
+/// True when the client prefers an HTML response, i.e. it is a browser.
+fn accepts_html(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"))
+}
+
+/// Replaces a 404/500 response with a styled HTML page for browsers (`--html-errors`).
+/// Responses to other clients pass through unchanged.
+fn html_error_page<B: MessageBody + 'static>(
+    res: ServiceResponse<B>,
+) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    if !accepts_html(res.request()) {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
     }
+    Ok(ErrorHandlerResponse::Future(Box::pin(async move {
+        let (req, res) = res.into_parts();
+        let status = res.status();
+        let body = actix_web::body::to_bytes(res.into_body()).await.unwrap_or_default();
+        let page = error_page::render_error_page(
+            status.as_u16(),
+            status.canonical_reason().unwrap_or("Error"),
+            &error_page::error_message(&String::from_utf8_lossy(&body)),
+        );
+        let res = HttpResponse::build(status)
+            .content_type("text/html; charset=utf-8")
+            .body(page);
+        Ok(ServiceResponse::new(req, res).map_into_right_body())
+    })))
+}
+
+/// The `--html-errors` middleware, which renders 404 and 500 responses as HTML pages for browsers.
+fn html_error_handlers<B: MessageBody + 'static>() -> ErrorHandlers<B> {
+    ErrorHandlers::new()
+        .handler(StatusCode::NOT_FOUND, html_error_page)
+        .handler(StatusCode::INTERNAL_SERVER_ERROR, html_error_page)
+}
+
+/// Registers every REST route; shared by the server and the handler tests.
+fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/prompts")
+            .service(list_prompts_handler)
+            .service(search_prompts_handler)
+            .service(category_tree_handler)
+            .service(export_prompts_handler)
+            .service(duplicates_handler)
+            .service(rename_variable_handler)
+            .service(import_prompts_handler)
+            .service(sandbox_handler)
+            .service(validate_prompt_handler)
+            .service(get_prompt_handler)
+            .service(get_raw_prompt_handler)
+            .service(create_prompt_handler)
+            .service(update_prompt_handler)
+            .service(delete_prompt_handler)
+            .service(render_prompt_handler)
+            .service(render_diff_handler)
+            .service(run_tests_handler)
+            .service(lock_prompt_handler)
+            .service(unlock_prompt_handler)
+            .service(fork_prompt_handler)
+            .service(merge_prompt_handler)
+            .service(list_forks_handler)
+            .service(transfer_prompt_handler)
+            .service(check_engine_handler)
+            .service(template_variables_handler)
+            .service(prompt_usage_handler)
+            .service(list_versions_handler)
+            .service(get_version_handler)
+            .service(get_section_handler)
+            .service(upload_attachments_handler)
+            .service(list_attachments_handler)
+            .service(get_attachment_handler),
+    )
+    .service(
+        web::scope("/categories")
+            .service(list_categories_handler)
+            .service(create_category_handler)
+            .service(get_category_handler)
+            .service(update_category_handler)
+            .service(delete_category_handler),
+    )
+    .service(
+        web::scope("/collections")
+            .service(list_collections_handler)
+            .service(create_collection_handler)
+            .service(get_collection_handler)
+            .service(update_collection_handler)
+            .service(delete_collection_handler)
+            .service(collection_prompts_handler),
+    )
+    // --- Temporarily remove SSE route ---
+    // .service(web::scope("/events").service(sse_service))
+    .route("/health", web::get().to(|| async { HttpResponse::Ok().body("OK") }))
+    .route("/info", web::get().to(info_handler))
+    .route("/metrics", web::get().to(metrics_handler))
+    .route("/capabilities", web::get().to(capabilities_handler))
+    .route("/schema/prompt", web::get().to(prompt_schema_handler))
+    .route("/admin/reload", web::post().to(admin_reload_handler))
+    .route("/admin/benchmark", web::post().to(admin_benchmark_handler))
+    .route("/admin/maintenance", web::post().to(admin_maintenance_handler))
+    .default_service(web::to(not_found_handler));
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let args = Cli::parse();
+
     // Initialize tracing subscriber
     // Use `RUST_LOG=info` (or debug, trace, etc.) to control log level
     // Example: RUST_LOG=mcp_prompts_rs=debug,rmcp=info cargo run
-    fmt().with_env_filter(EnvFilter::from_default_env()).init();
-
-    let args = Cli::parse();
+    let otlp_layer = match &args.otlp_endpoint {
+        Some(endpoint) => {
+            let tracer = telemetry::otlp_tracer(endpoint)
+                .map_err(|e| std::io::Error::other(format!("{:#}", e)))?;
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(fmt::layer())
+        .with(otlp_layer)
+        .init();
+    if let Some(endpoint) = &args.otlp_endpoint {
+        tracing::info!(endpoint = %endpoint, "Exporting traces over OTLP");
+    }
     tracing::info!(args = ?args, "Starting MCP Prompts Server");
+    prompt_model::set_omit_null_fields(!args.explicit_nulls);
 
     // Initialize storage based on args
-    let storage: Arc<dyn PromptStorage> = match args.storage.as_str() {
-        "filesystem" => {
-            tracing::info!(path = %args.prompt_dir, "Using filesystem storage");
-            Arc::new(FileSystemStorage::new(args.prompt_dir))
-        }
-        "postgres" => {
-            let db_url = args
-                .db_url
-                .clone()
-                .expect("--db-url is required for postgres storage");
-            tracing::info!(url = %db_url, "Using PostgreSQL storage");
-            let pg_storage = PostgresStorage::new(&db_url)
-                .await
-                .expect("Failed to connect to PostgreSQL");
-            // Initialize schema (consider making this optional via CLI arg)
-            pg_storage
-                .init_schema()
-                .await
-                .expect("Failed to initialize DB schema");
-            tracing::info!("Database schema initialized (if not exists)");
-            Arc::new(pg_storage)
+    // A misconfigured backend is a user mistake: report it plainly instead of as a debug dump
+    let storage = match connect_storage(&args).await {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if args.self_test {
+        match selftest::run_self_test(storage.as_ref()).await {
+            Ok(()) => {
+                println!("Self-test passed: {} storage round trip succeeded", args.storage);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("Self-test failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(seed_file) = &args.seed_file {
+        let seed_config = AppConfig::from_cli(&args);
+        match seed::seed_if_empty(storage.as_ref(), seed_file, |prompt| seed_config.prepare_for_save(prompt)).await {
+            Ok(SeedOutcome::Skipped) => tracing::info!(path = %seed_file, "Storage already contains prompts; skipping seed file"),
+            Ok(SeedOutcome::Seeded(0)) => tracing::warn!(path = %seed_file, "Seed file contains no prompts"),
+            Ok(SeedOutcome::Seeded(count)) => tracing::info!(path = %seed_file, count, "Seeded prompts from seed file"),
+            Err(e) => {
+                tracing::error!(path = %seed_file, error = %e, "Failed to seed prompts");
+                return Err(std::io::Error::other(format!("{:#}", e)));
+            }
+        }
+    }
+
+    // Keep the watcher alive for the lifetime of the server
+    let _watcher = if args.watch {
+        if args.storage != "filesystem" {
+            tracing::warn!(storage_type = %args.storage, "--watch is only supported for filesystem storage; ignoring");
+            None
+        } else {
+            let watcher = PromptDirWatcher::new(storage_options(&args).storage_dir())
+                .map_err(|e| std::io::Error::other(format!("{:#}", e)))?;
+            let mut events = watcher.subscribe();
+            let watched_storage = Arc::clone(&storage);
+            tokio::spawn(async move {
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            tracing::info!(prompt_id = %event.id, kind = ?event.kind, "Prompt changed on disk");
+                            // Templates compiled from the old file must not be served again
+                            template::clear_template_cache();
+                            // Listing picks up the change and rewrites the index
+                            if let Err(e) = watched_storage.list_prompt_summaries().await {
+                                tracing::warn!(error = %e, "Failed to refresh prompt index after change on disk");
+                            }
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                            tracing::warn!(skipped = n, "Prompt change listener lagged behind")
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+            tracing::info!(path = %watcher.dir().display(), "Watching prompt directory for changes");
+            Some(watcher)
         }
-        _ => {
-            tracing::error!(storage_type = %args.storage, "Unsupported storage type specified");
-            panic!("Unsupported storage type: {}", args.storage);
+    } else {
+        None
+    };
+
+    let metrics = Metrics::new().map_err(|e| std::io::Error::other(format!("{:#}", e)))?;
+    // REST handlers go through the wrapper so storage latency is measured
+    let instrumented: Arc<dyn PromptStorage> = Arc::new(InstrumentedStorage::new(Arc::clone(&storage), metrics.clone()));
+    let app_storage: web::Data<Arc<dyn PromptStorage>> = web::Data::new(instrumented);
+    let app_metrics = web::Data::new(metrics);
+    let mut app_config = AppConfig::from_cli(&args);
+    if let Some(path) = &args.default_args {
+        app_config.default_arguments = context::read_object_file(Path::new(path), "default arguments file")
+            .await
+            .map_err(|e| std::io::Error::other(format!("{:#}", e)))?;
+        tracing::info!(path = %path, count = app_config.default_arguments.len(), "Loaded default render arguments");
+    }
+    let app_config = web::Data::new(app_config);
+    let render_quota = web::Data::new(RenderQuota::new());
+    let started_at = web::Data::new(StartedAt(Instant::now()));
+    let webhooks = WebhookDispatcher::start(WebhookConfig {
+        urls: args.webhook_urls.clone(),
+        max_retries: args.webhook_retries,
+        initial_backoff: Duration::from_millis(args.webhook_backoff_ms),
+        timeout: Duration::from_secs(args.webhook_timeout),
+        dead_letter_path: args.webhook_dead_letter.as_ref().map(PathBuf::from),
+    })
+    .map_err(|e| std::io::Error::other(format!("{:#}", e)))?;
+    let webhooks = web::Data::new(webhooks);
+    let global_context = match &args.context_file {
+        Some(path) => {
+            let context = GlobalContext::from_file(path)
+                .await
+                .map_err(|e| std::io::Error::other(format!("{:#}", e)))?;
+            tracing::info!(path = %path, "Loaded render context file");
+            context
         }
+        None => GlobalContext::empty(),
     };
-    let app_storage: web::Data<Arc<dyn PromptStorage>> = web::Data::new(Arc::clone(&storage)); // Keep storage for REST API
+    let global_context = web::Data::new(global_context);
+    if let Some(dir) = &args.snippets_dir {
+        let library = SnippetLibrary::load_dir(dir)
+            .await
+            .map_err(|e| std::io::Error::other(format!("{:#}", e)))?;
+        tracing::info!(path = %dir, count = library.names().len(), "Loaded template snippets");
+        snippets::install(library);
+    }
+
+    if let Some(Command::Test { ids }) = &args.command {
+        match run_test_command(&storage, &app_config, &global_context, ids).await {
+            Ok(true) => std::process::exit(0),
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Failed to run prompt tests: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if args.warmup {
+        warm_template_cache(storage.as_ref(), app_config.template_engine)
+            .await
+            .map_err(|e| std::io::Error::other(format!("{:#}", e)))?;
+    }
 
     // --- Temporarily remove MCP Server Handler Initialization ---
     // let mcp_handler = McpPromptServerHandler::new(Arc::clone(&storage));
     // let mcp_server = Arc::new(Server::new(mcp_handler));
 
     // --- Configure and Start Actix Web Server ---
-    let bind_addr = format!("127.0.0.1:{}", args.port);
-    tracing::info!(address = %bind_addr, "Starting HTTP server (REST API only for now)");
+    let bind_addrs = match listen::listen_addrs(&args.host, args.port) {
+        Ok(addrs) => addrs,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    tracing::info!(addresses = ?bind_addrs, "Starting HTTP server (REST API only for now)");
+
+    let html_errors_enabled = args.html_errors;
+    let admission = AdmissionControl::new(AdmissionLimits {
+        total: args.max_inflight,
+        reads: args.max_inflight_reads,
+        writes: args.max_inflight_writes,
+    });
+    if admission.is_enabled() {
+        tracing::info!(
+            total = ?args.max_inflight,
+            reads = ?args.max_inflight_reads,
+            writes = ?args.max_inflight_writes,
+            "Limiting requests in flight"
+        );
+    }
+    let overload_retry_after = args.overload_retry_after;
+    let exempt_paths: Arc<[String]> = args.auth_exempt_paths.clone().into();
+    let api_key = args
+        .api_key
+        .clone()
+        .map(|key| key.0)
+        .or_else(|| std::env::var(API_KEY_ENV).ok());
+    let api_key_auth = ApiKeyAuth::new(api_key)
+        .also_accept(args.admin_token.clone().map(|token| token.0))
+        .open_paths(exempt_paths.iter().cloned());
+    if api_key_auth.is_enabled() {
+        tracing::info!(exempt = ?exempt_paths, "Requiring an API key on all endpoints except /health and exempt paths");
+    }
+    let otlp_enabled = args.otlp_endpoint.is_some();
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         // let mcp_server_clone = Arc::clone(&mcp_server); // Removed
         let app_storage_clone = app_storage.clone();
+        let app_metrics_clone = app_metrics.clone();
+        let app_config_clone = app_config.clone();
+        let render_quota_clone = render_quota.clone();
+        let global_context_clone = global_context.clone();
+        let started_at_clone = started_at.clone();
+        let webhooks_clone = webhooks.clone();
+        let admission_clone = admission.clone();
+        let exempt_paths_clone = Arc::clone(&exempt_paths);
+        let api_key_auth_clone = api_key_auth.clone();
+        let html_errors = html_error_handlers();
 
         // --- Temporarily remove SSE Service Integration ---
         // let sse_service = SseServerTransport::create_service(mcp_server_clone);
 
         App::new()
             .app_data(app_storage_clone)
+            .app_data(app_metrics_clone)
+            .app_data(app_config_clone)
+            .app_data(render_quota_clone)
+            .app_data(global_context_clone)
+            .app_data(started_at_clone)
+            .app_data(webhooks_clone)
+            .wrap(Condition::new(html_errors_enabled, html_errors))
+            .wrap(api_key_auth_clone)
+            // Outside everything but request IDs, so shed requests cost as little as possible
+            .wrap_fn(move |req, srv| {
+                let admitted = if admission::is_exempt(req.path(), &exempt_paths_clone) {
+                    Some(None)
+                } else {
+                    admission_clone.try_admit(RequestClass::from_method(req.method().as_str())).map(Some)
+                };
+                let call = match admitted {
+                    Some(admission) => Ok((srv.call(req), admission)),
+                    None => Err(req),
+                };
+                async move {
+                    match call {
+                        Ok((response, admission)) => {
+                            let response = response.await?;
+                            drop(admission);
+                            Ok(response.map_into_boxed_body())
+                        }
+                        Err(req) => {
+                            tracing::warn!(method = %req.method(), path = %req.path(), "Shedding request: too many requests in flight");
+                            let response = HttpResponse::ServiceUnavailable()
+                                .insert_header((header::RETRY_AFTER, overload_retry_after.to_string()))
+                                .body("The server is handling too many requests. Please retry later.");
+                            Ok(req.into_response(response))
+                        }
+                    }
+                }
+            })
+            // Outermost, so even shed and unauthorized requests are logged with their ID
+            .wrap(RequestIds)
             // .wrap(actix_web::middleware::Logger::default())
-            .service(
-                web::scope("/prompts")
-                    .service(list_prompts_handler)
-                    .service(get_prompt_handler)
-                    .service(create_prompt_handler)
-                    .service(update_prompt_handler)
-                    .service(delete_prompt_handler),
-            )
-            // --- Temporarily remove SSE route ---
-            // .service(web::scope("/events").service(sse_service))
-            .route("/health", web::get().to(|| async { HttpResponse::Ok().body("OK") }))
+            .configure(configure_routes)
     })
-    .bind(&bind_addr)?
+    .bind(&bind_addrs[..])?
     .run()
-    .await
+    .await;
+
+    if otlp_enabled {
+        telemetry::shutdown();
+    }
+    server
 }
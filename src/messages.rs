@@ -0,0 +1,73 @@
+use crate::models::prompt::Prompt;
+use crate::storage::PromptStorage;
+use crate::template::{self, EngineMode, RenderError};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Section rendered as the system message of a chat payload.
+pub const SYSTEM_SECTION: &str = "system";
+
+/// Section rendered as a trailing assistant message, e.g. to prefill the reply.
+pub const ASSISTANT_SECTION: &str = "assistant";
+
+/// Shape of a render response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderFormat {
+    /// `{"rendered": "..."}` with the rendered text.
+    #[default]
+    Text,
+    /// An array of chat messages for the OpenAI and Anthropic chat APIs.
+    Openai,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+/// One `{"role", "content"}` entry of a chat payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+/// Renders `prompt` as chat messages: the `system` section, if any, as a system
+/// message, the content as a user message, and the `assistant` section, if any,
+/// as an assistant message. A prompt without those sections becomes a single
+/// user message.
+///
+/// `arguments` should already include references (see
+/// [`crate::references::with_references`]).
+pub async fn render_messages(
+    storage: &dyn PromptStorage,
+    prompt: &Prompt,
+    arguments: &Map<String, Value>,
+    mode: EngineMode,
+    max_depth: usize,
+) -> Result<Vec<ChatMessage>, RenderError> {
+    let section = |name: &str| -> Result<Option<String>, RenderError> {
+        if prompt.sections.as_ref().is_some_and(|s| s.contains_key(name)) {
+            template::render_sections(prompt, &[name.to_string()], arguments).map(Some)
+        } else {
+            Ok(None)
+        }
+    };
+
+    let mut messages = Vec::new();
+    if let Some(content) = section(SYSTEM_SECTION)? {
+        messages.push(ChatMessage { role: Role::System, content });
+    }
+    messages.push(ChatMessage {
+        role: Role::User,
+        content: template::render_prompt(storage, prompt, arguments, mode, max_depth).await?,
+    });
+    if let Some(content) = section(ASSISTANT_SECTION)? {
+        messages.push(ChatMessage { role: Role::Assistant, content });
+    }
+    Ok(messages)
+}
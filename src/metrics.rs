@@ -0,0 +1,235 @@
+use crate::attachments::AttachmentInfo;
+use crate::models::category::Category;
+use crate::models::collection::Collection;
+use crate::models::prompt::{Prompt, PromptSummary};
+use crate::search::{SearchHit, SearchOptions};
+use crate::storage::{DeleteOutcome, PromptStorage};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+
+/// Content type of the Prometheus text exposition format served at `/metrics`.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Prompt operations counted by [`Metrics::record`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptOperation {
+    Create,
+    Update,
+    Delete,
+    Get,
+}
+
+impl PromptOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PromptOperation::Create => "create",
+            PromptOperation::Update => "update",
+            PromptOperation::Delete => "delete",
+            PromptOperation::Get => "get",
+        }
+    }
+}
+
+/// Prometheus metrics for the server, in a registry of their own.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    prompt_operations: IntCounterVec,
+    storage_duration: HistogramVec,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+        let prompt_operations = IntCounterVec::new(
+            Opts::new("mcp_prompts_prompt_operations_total", "Prompt operations completed through the API"),
+            &["operation"],
+        )?;
+        let storage_duration = HistogramVec::new(
+            HistogramOpts::new("mcp_prompts_storage_operation_duration_seconds", "Latency of storage backend calls"),
+            &["operation"],
+        )?;
+        registry
+            .register(Box::new(prompt_operations.clone()))
+            .context("Failed to register prompt operation counter")?;
+        registry
+            .register(Box::new(storage_duration.clone()))
+            .context("Failed to register storage latency histogram")?;
+        Ok(Self { registry, prompt_operations, storage_duration })
+    }
+
+    /// Counts one completed prompt operation.
+    pub fn record(&self, operation: PromptOperation) {
+        self.prompt_operations.with_label_values(&[operation.name()]).inc();
+    }
+
+    /// Encodes every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .context("Failed to encode metrics")?;
+        String::from_utf8(buffer).context("Encoded metrics are not UTF-8")
+    }
+}
+
+/// Storage backend wrapper that records the latency of every call on the inner
+/// backend, labelled with the trait method name, whether it succeeds or fails.
+pub struct InstrumentedStorage {
+    inner: Arc<dyn PromptStorage>,
+    metrics: Metrics,
+}
+
+impl InstrumentedStorage {
+    pub fn new(inner: Arc<dyn PromptStorage>, metrics: Metrics) -> Self {
+        Self { inner, metrics }
+    }
+
+    async fn timed<T>(&self, operation: &str, call: impl Future<Output = T>) -> T {
+        let started = Instant::now();
+        let result = call.await;
+        self.metrics
+            .storage_duration
+            .with_label_values(&[operation])
+            .observe(started.elapsed().as_secs_f64());
+        result
+    }
+}
+
+// Every method is forwarded, so the inner backend's own implementations are used
+// instead of the trait defaults
+#[async_trait]
+impl PromptStorage for InstrumentedStorage {
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<Prompt>> {
+        self.timed("list_prompts", self.inner.list_prompts()).await
+    }
+
+    async fn list_prompt_summaries(&self) -> Result<Vec<PromptSummary>> {
+        self.timed("list_prompt_summaries", self.inner.list_prompt_summaries()).await
+    }
+
+    async fn count_prompts(&self) -> Result<usize> {
+        self.timed("count_prompts", self.inner.count_prompts()).await
+    }
+
+    async fn list_prompts_paginated(&self, offset: usize, limit: usize) -> Result<Vec<Prompt>> {
+        self.timed("list_prompts_paginated", self.inner.list_prompts_paginated(offset, limit)).await
+    }
+
+    async fn search_prompts(&self, query: &str, options: &SearchOptions) -> Result<Vec<Prompt>> {
+        self.timed("search_prompts", self.inner.search_prompts(query, options)).await
+    }
+
+    async fn search_prompts_ranked(&self, query: &str, options: &SearchOptions, highlight: bool) -> Result<Vec<SearchHit>> {
+        self.timed("search_prompts_ranked", self.inner.search_prompts_ranked(query, options, highlight)).await
+    }
+
+    async fn list_prompts_by_category(&self, category: &str) -> Result<Vec<Prompt>> {
+        self.timed("list_prompts_by_category", self.inner.list_prompts_by_category(category)).await
+    }
+
+    async fn list_prompts_by_tag(&self, tag: &str) -> Result<Vec<Prompt>> {
+        self.timed("list_prompts_by_tag", self.inner.list_prompts_by_tag(tag)).await
+    }
+
+    async fn list_prompts_by_category_prefix(&self, prefix: &str) -> Result<Vec<Prompt>> {
+        self.timed("list_prompts_by_category_prefix", self.inner.list_prompts_by_category_prefix(prefix)).await
+    }
+
+    async fn list_prompts_by_length(&self, min: Option<usize>, max: Option<usize>) -> Result<Vec<Prompt>> {
+        self.timed("list_prompts_by_length", self.inner.list_prompts_by_length(min, max)).await
+    }
+
+    async fn list_forks(&self, id: &Uuid) -> Result<Vec<Prompt>> {
+        self.timed("list_forks", self.inner.list_forks(id)).await
+    }
+
+    async fn get_prompt(&self, id: &Uuid) -> Result<Option<Prompt>> {
+        self.timed("get_prompt", self.inner.get_prompt(id)).await
+    }
+
+    async fn save_prompt(&self, prompt: &Prompt) -> Result<()> {
+        self.timed("save_prompt", self.inner.save_prompt(prompt)).await
+    }
+
+    async fn list_prompt_versions(&self, id: &Uuid) -> Result<Vec<u32>> {
+        self.timed("list_prompt_versions", self.inner.list_prompt_versions(id)).await
+    }
+
+    async fn get_prompt_version(&self, id: &Uuid, version: u32) -> Result<Option<Prompt>> {
+        self.timed("get_prompt_version", self.inner.get_prompt_version(id, version)).await
+    }
+
+    async fn delete_prompt(&self, id: &Uuid) -> Result<bool> {
+        self.timed("delete_prompt", self.inner.delete_prompt(id)).await
+    }
+
+    async fn delete_prompt_if_version(&self, id: &Uuid, expected_version: u32) -> Result<DeleteOutcome> {
+        self.timed("delete_prompt_if_version", self.inner.delete_prompt_if_version(id, expected_version)).await
+    }
+
+    async fn is_deleted(&self, id: &Uuid) -> Result<bool> {
+        self.timed("is_deleted", self.inner.is_deleted(id)).await
+    }
+
+    async fn purge_prompt(&self, id: &Uuid) -> Result<bool> {
+        self.timed("purge_prompt", self.inner.purge_prompt(id)).await
+    }
+
+    async fn save_attachment(&self, prompt_id: &Uuid, name: &str, data: &[u8]) -> Result<()> {
+        self.timed("save_attachment", self.inner.save_attachment(prompt_id, name, data)).await
+    }
+
+    async fn get_attachment(&self, prompt_id: &Uuid, name: &str) -> Result<Option<Vec<u8>>> {
+        self.timed("get_attachment", self.inner.get_attachment(prompt_id, name)).await
+    }
+
+    async fn list_attachments(&self, prompt_id: &Uuid) -> Result<Vec<AttachmentInfo>> {
+        self.timed("list_attachments", self.inner.list_attachments(prompt_id)).await
+    }
+
+    async fn list_categories(&self) -> Result<Vec<Category>> {
+        self.timed("list_categories", self.inner.list_categories()).await
+    }
+
+    async fn get_category(&self, name: &str) -> Result<Option<Category>> {
+        self.timed("get_category", self.inner.get_category(name)).await
+    }
+
+    async fn save_category(&self, category: &Category) -> Result<()> {
+        self.timed("save_category", self.inner.save_category(category)).await
+    }
+
+    async fn delete_category(&self, name: &str) -> Result<bool> {
+        self.timed("delete_category", self.inner.delete_category(name)).await
+    }
+
+    async fn list_collections(&self) -> Result<Vec<Collection>> {
+        self.timed("list_collections", self.inner.list_collections()).await
+    }
+
+    async fn get_collection(&self, id: &Uuid) -> Result<Option<Collection>> {
+        self.timed("get_collection", self.inner.get_collection(id)).await
+    }
+
+    async fn save_collection(&self, collection: &Collection) -> Result<()> {
+        self.timed("save_collection", self.inner.save_collection(collection)).await
+    }
+
+    async fn delete_collection(&self, id: &Uuid) -> Result<bool> {
+        self.timed("delete_collection", self.inner.delete_collection(id)).await
+    }
+
+    async fn run_maintenance(&self) -> Result<Vec<String>> {
+        self.timed("run_maintenance", self.inner.run_maintenance()).await
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// Display settings for a category, referenced by name from a prompt's `category`.
+///
+/// Prompts may use categories that have no record; they simply have no color or icon.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Category {
+    /// Set from the URL on updates, so it may be left out of the request body there.
+    #[serde(default)]
+    pub name: String,
+    /// CSS color for the category, e.g. `#3b82f6`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Icon name or emoji shown next to the category.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl Category {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            color: None,
+            icon: None,
+            description: None,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A named, ordered group of prompts that are fetched together, e.g. the steps of
+/// an onboarding flow. A prompt can belong to any number of collections.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Collection {
+    /// Generated when a new collection is created without one.
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
+    pub name: String,
+    /// Member prompts, in the order they are returned.
+    #[serde(default)]
+    pub prompt_ids: Vec<Uuid>,
+}
+
+impl Collection {
+    pub fn new(name: impl Into<String>, prompt_ids: Vec<Uuid>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            prompt_ids,
+        }
+    }
+}
@@ -1,3 +1,7 @@
+pub mod category;
+pub mod collection;
 pub mod prompt;
 
+pub use category::Category;
+pub use collection::Collection;
 pub use prompt::Prompt;
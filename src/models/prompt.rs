@@ -1,32 +1,462 @@
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use crate::template::TemplateEngine;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use uuid::Uuid;
 
 /// Represents an AI prompt with metadata.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct Prompt {
     #[serde(default = "Uuid::new_v4")] // Default to a new UUID if missing during deserialization
     pub id: Uuid,
     pub name: String,
     pub content: String,
+    /// How `content` is encoded: unset for template text, or [`BASE64_ENCODING`]
+    /// for binary data such as images, which is never rendered as a template.
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub content_encoding: Option<String>,
+    /// Media type the decoded content is served with by `GET /prompts/{id}/raw`.
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "omit_none")]
     pub category: Option<String>, // e.g., "development", "writing"
+    /// Freeform labels. Stored lowercase and without duplicates (see [`normalize_tags`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "omit_none")]
     pub variables: Option<Vec<String>>, // Placeholder names like {{variable_name}}
-    // Add other relevant fields like created_at, updated_at if needed
-    // pub created_at: Option<chrono::DateTime<chrono::Utc>>,
-    // pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Extra details about individual variables, keyed by variable name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variable_metadata: BTreeMap<String, VariableMetadata>,
+    /// Engine the content is rendered with, overriding `--template-engine`: `tera`,
+    /// `handlebars`, or `none` to return the content as written. Sections always use Tera.
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub template_engine: Option<TemplateEngine>,
+    /// Parent prompt whose `{% block %}`s this prompt overrides when rendered.
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub extends: Option<Uuid>,
+    /// Locked prompts reject updates and deletes until unlocked.
+    #[serde(default)]
+    pub locked: bool,
+    /// Maximum number of renders allowed in any one-hour window. Unlimited when unset.
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub render_quota_per_hour: Option<u32>,
+    /// Arbitrary client data (e.g. color, icon, team) stored alongside the prompt.
+    #[serde(default)]
+    pub metadata: Map<String, Value>,
+    /// Named parts of the prompt (e.g. instructions, context, examples) that can be
+    /// rendered individually or assembled in a chosen order.
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub sections: Option<BTreeMap<String, String>>,
+    /// Render arguments used when the caller doesn't pass them. They override
+    /// server-wide defaults and are overridden by request arguments.
+    #[serde(default)]
+    pub default_arguments: Map<String, Value>,
+    /// Alternative expression delimiters (e.g. `<%` and `%>`) for prompts whose text
+    /// contains literal `{{ }}`. When set, only these tags are rendered.
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub delimiters: Option<Delimiters>,
+    /// Other prompts this one includes, by alias. Each is rendered with the same
+    /// arguments and exposed as `refs.<alias>`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub references: BTreeMap<String, Uuid>,
+    /// Identity of the user who owns the prompt. Only changed through a transfer.
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub owner: Option<String>,
+    /// Prompt this one is a draft copy of, set by forking.
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub forked_from: Option<Uuid>,
+    /// Version of the `forked_from` prompt the draft was copied from. Merging is
+    /// refused once the original has moved past it.
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub forked_from_version: Option<u32>,
+    /// Regression test cases, run with `POST /prompts/{id}/run-tests` or the `test` command.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<PromptTest>,
+    /// Revision number, starting at 1 and incremented each time the prompt is edited.
+    #[serde(default = "initial_version")]
+    pub version: u32,
+    /// When the prompt was first saved. Saving it again keeps the stored value.
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    /// When the prompt was last saved, set by the storage backend on every save.
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Custom start and end markers for template expressions in a prompt.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct Delimiters {
+    pub start: String,
+    pub end: String,
+}
+
+/// A stored test case for a prompt: render arguments and the exact output they must produce.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct PromptTest {
+    /// Label for the case in test reports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub arguments: Map<String, Value>,
+    pub expected_output: String,
+}
+
+/// Details about one template variable of a prompt.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct VariableMetadata {
+    /// Other argument names callers may use for this variable (e.g. `user` for
+    /// `username`). They are mapped to the variable's own name when rendering.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+/// The listing fields of a prompt, without its content.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromptSummary {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "omit_none")]
+    pub category: Option<String>,
+    pub version: u32,
+}
+
+impl From<&Prompt> for PromptSummary {
+    fn from(prompt: &Prompt) -> Self {
+        Self {
+            id: prompt.id,
+            name: prompt.name.clone(),
+            category: prompt.category.clone(),
+            version: prompt.version,
+        }
+    }
+}
+
+/// `content_encoding` of prompts whose content is base64-encoded binary data.
+pub const BASE64_ENCODING: &str = "base64";
+
+fn initial_version() -> u32 {
+    1
+}
+
+/// Whether unset optional fields are left out of serialized prompts (the default)
+/// rather than written as `null`. Deserialization accepts both forms either way.
+static OMIT_NULL_FIELDS: AtomicBool = AtomicBool::new(true);
+
+/// Sets, process-wide, whether unset optional prompt fields are omitted when
+/// serializing (`true`, the default) or serialized as explicit `null`s.
+pub fn set_omit_null_fields(omit: bool) {
+    OMIT_NULL_FIELDS.store(omit, Ordering::Relaxed);
+}
+
+/// JSON Schema describing the prompt model as accepted and returned by the API.
+pub fn prompt_schema() -> schemars::schema::RootSchema {
+    schemars::schema_for!(Prompt)
+}
+
+fn omit_none<T>(value: &Option<T>) -> bool {
+    value.is_none() && OMIT_NULL_FIELDS.load(Ordering::Relaxed)
 }
 
 // Optional: Implement methods for the Prompt struct if needed
 impl Prompt {
     // Example: A constructor function
     pub fn new(name: String, content: String, category: Option<String>, variables: Option<Vec<String>>) -> Self {
+        let now = Utc::now();
         Self {
             id: Uuid::new_v4(),
             name,
             content,
+            content_encoding: None,
+            content_type: None,
             category,
+            tags: Vec::new(),
             variables,
-            // created_at: Some(chrono::Utc::now()),
-            // updated_at: Some(chrono::Utc::now()),
+            variable_metadata: BTreeMap::new(),
+            template_engine: None,
+            extends: None,
+            locked: false,
+            render_quota_per_hour: None,
+            metadata: Map::new(),
+            sections: None,
+            default_arguments: Map::new(),
+            delimiters: None,
+            references: BTreeMap::new(),
+            owner: None,
+            forked_from: None,
+            forked_from_version: None,
+            tests: Vec::new(),
+            version: initial_version(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Marks the prompt as saved now, keeping `stored_created_at`, the creation time
+    /// of the copy already in storage, if there is one, and normalizes its tags.
+    /// Storage backends call this on what they write.
+    pub fn stamp_saved(&mut self, stored_created_at: Option<DateTime<Utc>>) {
+        self.tags = normalize_tags(&self.tags);
+        self.updated_at = Utc::now();
+        if let Some(created_at) = stored_created_at {
+            self.created_at = created_at;
+        }
+    }
+
+    /// Weak entity tag for HTTP caching and `If-Match`: the version and a hash of the
+    /// serialized prompt, e.g. `W/"3-1f0c9a2b4d6e8f10"`.
+    ///
+    /// `updated_at` is left out of the hash because backends set it on save, so the tag of
+    /// the prompt a handler saved matches the one later read back.
+    pub fn etag(&self) -> String {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Value::Object(object) = &mut value {
+            object.remove("updated_at");
+        }
+        let hash = Sha256::digest(serde_json::to_vec(&value).unwrap_or_default());
+        let hex: String = hash.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+        format!("W/\"{}-{}\"", self.version, hex)
+    }
+
+    /// True if an `If-Match` header value matches this prompt: `*`, or a comma-separated
+    /// list containing its [`etag`](Self::etag) or, as `DELETE` accepts, its version number.
+    /// Tags are compared weakly, so a `W/` prefix is optional.
+    pub fn matches_if_match(&self, if_match: &str) -> bool {
+        let etag = self.etag();
+        let own = etag.trim_start_matches("W/");
+        let version = format!("\"{}\"", self.version);
+        if_match.split(',').map(str::trim).any(|tag| {
+            let tag = tag.strip_prefix("W/").unwrap_or(tag);
+            tag == "*" || tag == own || tag == version
+        })
+    }
+
+    /// Creates an unlocked, unowned draft copy of this prompt with a new ID, linked
+    /// back to it through `forked_from` and `forked_from_version`.
+    pub fn fork(&self) -> Prompt {
+        let now = Utc::now();
+        Prompt {
+            id: Uuid::new_v4(),
+            locked: false,
+            owner: None,
+            forked_from: Some(self.id),
+            forked_from_version: Some(self.version),
+            version: initial_version(),
+            created_at: now,
+            updated_at: now,
+            ..self.clone()
+        }
+    }
+
+    /// Takes over the content, declared variables, sections and test cases of a
+    /// draft forked from this prompt, and bumps the version.
+    pub fn merge_from(&mut self, draft: &Prompt) {
+        self.content = draft.content.clone();
+        self.variables = draft.variables.clone();
+        self.sections = draft.sections.clone();
+        self.tests = draft.tests.clone();
+        self.version = self.version.saturating_add(1);
+    }
+
+    /// Returns true if metadata `key` equals `expected`. String values are compared
+    /// directly, other values by their JSON representation (e.g. `3`, `true`).
+    pub fn metadata_matches(&self, key: &str, expected: &str) -> bool {
+        match self.metadata.get(key) {
+            Some(Value::String(value)) => value == expected,
+            Some(value) => serde_json::from_str::<Value>(expected).is_ok_and(|parsed| parsed == *value),
+            None => false,
         }
     }
+
+    /// Renames template variable `old` to `new` in both the content and the declared
+    /// `variables`. Returns true if anything changed.
+    pub fn rename_variable(&mut self, old: &str, new: &str) -> bool {
+        let (content, replaced) = crate::variables::rename_variable(&self.content, old, new);
+        self.content = content;
+
+        let mut declared_changed = false;
+        if let Some(variables) = self.variables.as_mut() {
+            if variables.iter().any(|v| v == old) {
+                let already_declared = variables.iter().any(|v| v == new);
+                variables.retain(|v| v != old || !already_declared);
+                for v in variables.iter_mut().filter(|v| v.as_str() == old) {
+                    *v = new.to_string();
+                }
+                declared_changed = true;
+            }
+        }
+        if let Some(metadata) = self.variable_metadata.remove(old) {
+            self.variable_metadata.entry(new.to_string()).or_insert(metadata);
+            declared_changed = true;
+        }
+        replaced > 0 || declared_changed
+    }
+
+    /// Converts CRLF line endings to LF and trims trailing whitespace from every line.
+    /// Applying it more than once has no further effect. Encoded content is left as is.
+    pub fn normalize_content(&mut self) {
+        if self.content_encoding.is_none() {
+            self.content = normalize_text(&self.content);
+        }
+    }
+
+    /// True if the prompt has `tag`, compared the way tags are stored.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim().to_lowercase();
+        self.tags.iter().any(|t| t.trim().to_lowercase() == tag)
+    }
+
+    /// True if the content is encoded binary data rather than template text.
+    pub fn is_encoded(&self) -> bool {
+        self.content_encoding.is_some()
+    }
+
+    /// The content as bytes: decoded per `content_encoding`, or the text itself when
+    /// it isn't encoded. Whitespace in base64 content, such as line breaks, is ignored.
+    pub fn decoded_content(&self) -> Result<Vec<u8>, String> {
+        match self.content_encoding.as_deref() {
+            None => Ok(self.content.clone().into_bytes()),
+            Some(BASE64_ENCODING) => {
+                let compact: String = self.content.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+                base64::engine::general_purpose::STANDARD
+                    .decode(compact)
+                    .map_err(|e| format!("Content is not valid base64: {}", e))
+            }
+            Some(other) => Err(format!(
+                "Unsupported content_encoding '{}'; expected '{}'",
+                other, BASE64_ENCODING
+            )),
+        }
+    }
+
+    /// Checks that encoded content decodes and that `content_type` looks like a
+    /// media type (`type/subtype`, optionally with parameters).
+    pub fn check_content(&self) -> Result<(), String> {
+        if self.is_encoded() {
+            self.decoded_content()?;
+        }
+        if let Some(content_type) = &self.content_type {
+            let essence = content_type.split(';').next().unwrap_or_default().trim();
+            let valid_essence = essence.split_once('/').is_some_and(|(kind, subtype)| {
+                !kind.is_empty() && !subtype.is_empty() && !essence.contains(char::is_whitespace)
+            });
+            if !valid_essence || !content_type.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+                return Err(format!("Invalid content_type '{}'; expected e.g. 'image/png'", content_type));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the Tera content and sections only use declared variables,
+    /// variables with a default argument, names bound by the template itself (such
+    /// as `for` loop variables) and the `globals`, `refs` and `prompts` context keys.
+    ///
+    /// Prompts that don't declare `variables` pass. On success, returns the declared
+    /// variables the templates never use; otherwise a message naming every
+    /// undeclared variable.
+    pub fn validate_variables(&self) -> Result<Vec<String>, String> {
+        let Some(declared) = &self.variables else {
+            return Ok(Vec::new());
+        };
+        let used = crate::variables::prompt_variables(self).map_err(|e| e.to_string())?;
+        let context_keys = [crate::context::GLOBALS_KEY, crate::references::REFS_KEY, crate::references::PROMPTS_KEY];
+        let undeclared: Vec<&str> = used
+            .iter()
+            .map(String::as_str)
+            .filter(|name| {
+                !declared.iter().any(|d| d == name)
+                    && !self.default_arguments.contains_key(*name)
+                    && !context_keys.contains(name)
+            })
+            .collect();
+        if !undeclared.is_empty() {
+            return Err(format!("Template uses undeclared variables: {}", undeclared.join(", ")));
+        }
+        Ok(declared.iter().filter(|name| !used.contains(name)).cloned().collect())
+    }
+}
+
+/// Normalizes line endings to LF and strips trailing whitespace per line.
+pub fn normalize_text(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .split('\n')
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Lowercases and trims tags, dropping empty ones and duplicates while keeping
+/// the order in which each tag first appears.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Error from parsing a prompt from a JSON request body.
+#[derive(Debug)]
+pub enum PromptParseError {
+    /// The body is not valid JSON for a prompt.
+    Invalid(serde_json::Error),
+    /// Strict parsing found fields that aren't part of the prompt model.
+    UnknownFields(Vec<String>),
+}
+
+impl fmt::Display for PromptParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PromptParseError::Invalid(e) => write!(f, "Invalid prompt JSON: {}", e),
+            PromptParseError::UnknownFields(fields) => {
+                write!(f, "Unknown field(s) in prompt: {}", fields.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for PromptParseError {}
+
+/// Parses a prompt from JSON. In strict mode, any field that isn't part of the
+/// model is rejected instead of being silently dropped.
+pub fn parse_prompt_json(body: &[u8], strict: bool) -> Result<Prompt, PromptParseError> {
+    let mut unknown = Vec::new();
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let prompt: Prompt = serde_ignored::deserialize(&mut deserializer, |path| {
+        unknown.push(path.to_string())
+    })
+    .map_err(PromptParseError::Invalid)?;
+    deserializer.end().map_err(PromptParseError::Invalid)?;
+
+    if strict && !unknown.is_empty() {
+        return Err(PromptParseError::UnknownFields(unknown));
+    }
+    Ok(prompt)
+}
+
+/// Three-way merges two edits of prompt content made from the same `base`:
+/// `current` is the stored content and `edited` the incoming update.
+///
+/// Returns the merged content when the edits touch different lines, or the
+/// content with `<<<<<<< ours` (stored) / `>>>>>>> theirs` (incoming) conflict
+/// markers when they overlap.
+pub fn merge_content(base: &str, current: &str, edited: &str) -> Result<String, String> {
+    diffy::merge(base, current, edited)
+}
+
+/// Line-based unified diff from `old` to `new`, empty when they are equal.
+pub fn unified_diff(old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    diffy::create_patch(old, new).to_string()
 }
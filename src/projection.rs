@@ -0,0 +1,79 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Fields of a serialized prompt that can be selected with `?fields=`.
+pub const PROMPT_FIELDS: &[&str] = &[
+    "id",
+    "name",
+    "content",
+    "content_encoding",
+    "content_type",
+    "category",
+    "tags",
+    "variables",
+    "variable_metadata",
+    "template_engine",
+    "extends",
+    "locked",
+    "render_quota_per_hour",
+    "metadata",
+    "sections",
+    "default_arguments",
+    "delimiters",
+    "references",
+    "owner",
+    "forked_from",
+    "forked_from_version",
+    "tests",
+    "version",
+    "created_at",
+    "updated_at",
+];
+
+/// Parses a comma-separated `fields` list, rejecting names that aren't prompt fields.
+pub fn parse_fields(spec: &str) -> Result<Vec<String>, String> {
+    let fields: Vec<String> = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(str::to_string)
+        .collect();
+    if fields.is_empty() {
+        return Err("fields must name at least one field".to_string());
+    }
+    let unknown: Vec<&str> = fields
+        .iter()
+        .map(String::as_str)
+        .filter(|f| !PROMPT_FIELDS.contains(f))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(format!(
+            "Unknown field(s): {}. Valid fields are: {}",
+            unknown.join(", "),
+            PROMPT_FIELDS.join(", ")
+        ));
+    }
+    Ok(fields)
+}
+
+/// Serializes `value` and keeps only `fields` of each object, whether `value` is a
+/// single object or a list of them. Fields a value doesn't have are left out.
+pub fn project<T: Serialize>(value: &T, fields: &[String]) -> serde_json::Result<Value> {
+    Ok(match serde_json::to_value(value)? {
+        Value::Array(items) => Value::Array(items.into_iter().map(|item| project_object(item, fields)).collect()),
+        other => project_object(other, fields),
+    })
+}
+
+fn project_object(value: Value, fields: &[String]) -> Value {
+    match value {
+        Value::Object(mut object) => {
+            let projected: Map<String, Value> = fields
+                .iter()
+                .filter_map(|f| object.remove(f).map(|v| (f.clone(), v)))
+                .collect();
+            Value::Object(projected)
+        }
+        other => other,
+    }
+}
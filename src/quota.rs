@@ -0,0 +1,65 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Length of the sliding window that per-prompt render quotas apply to.
+pub const QUOTA_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// In-memory sliding-window counter of renders per prompt.
+///
+/// State is per process, so each instance enforces its own quota.
+#[derive(Debug, Default)]
+pub struct RenderQuota {
+    renders: Mutex<HashMap<Uuid, VecDeque<Instant>>>,
+}
+
+impl RenderQuota {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a render of prompt `id` if fewer than `limit` happened in the last hour.
+    ///
+    /// Returns the time the render was recorded at, to hand to [`Self::refund`] if
+    /// the render fails, or `Err` with the time until a slot frees up when the
+    /// quota is exhausted.
+    pub fn try_acquire(&self, id: &Uuid, limit: u32) -> Result<Instant, Duration> {
+        self.try_acquire_at(id, limit, Instant::now())
+    }
+
+    /// [`Self::try_acquire`] with an explicit current time.
+    pub fn try_acquire_at(&self, id: &Uuid, limit: u32, now: Instant) -> Result<Instant, Duration> {
+        let mut renders = self.renders.lock().unwrap_or_else(|e| e.into_inner());
+        let window = renders.entry(*id).or_default();
+
+        while let Some(oldest) = window.front() {
+            if now.duration_since(*oldest) >= QUOTA_WINDOW {
+                window.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if window.len() >= limit as usize {
+            let retry_after = window
+                .front()
+                .map_or(QUOTA_WINDOW, |oldest| QUOTA_WINDOW - now.duration_since(*oldest));
+            return Err(retry_after);
+        }
+
+        window.push_back(now);
+        Ok(now)
+    }
+
+    /// Gives back the slot a failed render of prompt `id` took at `acquired_at`,
+    /// so only renders that produced output count against the quota.
+    pub fn refund(&self, id: &Uuid, acquired_at: Instant) {
+        let mut renders = self.renders.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(window) = renders.get_mut(id) {
+            if let Some(position) = window.iter().rposition(|at| *at == acquired_at) {
+                window.remove(position);
+            }
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::models::prompt::Prompt;
+use crate::storage::PromptStorage;
+use crate::template::{self, EngineMode, RenderError};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// Render-context key under which referenced prompts are exposed, e.g. `{{ refs.preamble }}`.
+pub const REFS_KEY: &str = "refs";
+
+/// Render-context key under which prompts included with the `prompt()` template
+/// function are exposed, keyed by name. The function reads from here.
+pub const PROMPTS_KEY: &str = "prompts";
+
+/// What to do when a prompt references another prompt that doesn't exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingReferencePolicy {
+    /// Fail the render.
+    #[default]
+    Error,
+    /// Insert `[missing: <id>]` in place of the referenced prompt.
+    Placeholder,
+    /// Insert nothing.
+    Skip,
+}
+
+impl FromStr for MissingReferencePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Self::Error),
+            "placeholder" => Ok(Self::Placeholder),
+            "skip" => Ok(Self::Skip),
+            other => Err(format!(
+                "Unknown missing reference policy '{}'; expected error, placeholder or skip",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MissingReferencePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Error => "error",
+            Self::Placeholder => "placeholder",
+            Self::Skip => "skip",
+        })
+    }
+}
+
+/// What `DELETE /prompts/{id}` does when other prompts depend on the prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependentsPolicy {
+    /// Delete without checking for dependents.
+    Allow,
+    /// Delete, but log the dependents and report their number.
+    #[default]
+    Warn,
+    /// Refuse to delete while dependents exist.
+    Block,
+}
+
+impl FromStr for DependentsPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "warn" => Ok(Self::Warn),
+            "block" => Ok(Self::Block),
+            other => Err(format!("Unknown dependents policy '{}'; expected allow, warn or block", other)),
+        }
+    }
+}
+
+impl fmt::Display for DependentsPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Allow => "allow",
+            Self::Warn => "warn",
+            Self::Block => "block",
+        })
+    }
+}
+
+/// How one prompt depends on another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Dependency {
+    /// Listed in `references` under `alias`.
+    Reference { alias: String },
+    /// Named as the `extends` parent.
+    Extends,
+    /// Included by name with `{{ prompt(name="...") }}`.
+    Include,
+}
+
+/// A prompt that depends on another, and every way it does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dependent {
+    pub id: Uuid,
+    pub name: String,
+    pub via: Vec<Dependency>,
+}
+
+/// The ways `prompt` depends on `target`, empty if it doesn't.
+///
+/// Includes are matched by name, so when several prompts share `target`'s name,
+/// a prompt including that name counts as depending on each of them.
+pub fn dependencies_on(prompt: &Prompt, target: &Prompt) -> Vec<Dependency> {
+    if prompt.id == target.id {
+        return Vec::new();
+    }
+    let mut via: Vec<Dependency> = prompt
+        .references
+        .iter()
+        .filter(|(_, id)| **id == target.id)
+        .map(|(alias, _)| Dependency::Reference { alias: alias.clone() })
+        .collect();
+    if prompt.extends == Some(target.id) {
+        via.push(Dependency::Extends);
+    }
+    if included_prompt_names(prompt).contains(&target.name) {
+        via.push(Dependency::Include);
+    }
+    via
+}
+
+/// Prompts among `prompts` that reference, extend or include `target` directly,
+/// in the order given.
+pub fn dependents_of(target: &Prompt, prompts: &[Prompt]) -> Vec<Dependent> {
+    prompts
+        .iter()
+        .filter_map(|prompt| {
+            let via = dependencies_on(prompt, target);
+            (!via.is_empty()).then(|| Dependent { id: prompt.id, name: prompt.name.clone(), via })
+        })
+        .collect()
+}
+
+/// Loads every prompt and returns those that depend on `target` directly.
+pub async fn find_dependents(storage: &dyn PromptStorage, target: &Prompt) -> anyhow::Result<Vec<Dependent>> {
+    Ok(dependents_of(target, &storage.list_prompts().await?))
+}
+
+fn include_regex() -> &'static Regex {
+    static INCLUDE: OnceLock<Regex> = OnceLock::new();
+    INCLUDE.get_or_init(|| {
+        Regex::new(r#"\bprompt\s*\(\s*name\s*=\s*(?:"([^"]*)"|'([^']*)')\s*\)"#).expect("valid include regex")
+    })
+}
+
+/// Names of the prompts that `prompt`'s content and sections include with
+/// `{{ prompt(name="...") }}`, in order of first use. Only literal names count.
+pub fn included_prompt_names(prompt: &Prompt) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for text in std::iter::once(&prompt.content).chain(prompt.sections.iter().flat_map(|s| s.values())) {
+        for captures in include_regex().captures_iter(text) {
+            let name = captures.get(1).or_else(|| captures.get(2)).map_or("", |m| m.as_str());
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Returns the render arguments with the prompt's rendered references added under
+/// [`REFS_KEY`] and the prompts it includes by name added under [`PROMPTS_KEY`].
+/// Prompts with neither get the arguments unchanged.
+///
+/// Each referenced or included prompt is rendered with the same arguments,
+/// resolving its own references in turn, with the engine `mode` picks for it.
+/// Included names are looked up among all prompts; if several share a name, the
+/// first in listing order is used. Cycles fail, as do prompts nested more than
+/// `max_depth` levels deep, counting each reference, include and `extends`
+/// parent along the way.
+pub async fn with_references(
+    storage: &dyn PromptStorage,
+    prompt: &Prompt,
+    arguments: &Map<String, Value>,
+    policy: MissingReferencePolicy,
+    mode: EngineMode,
+    max_depth: usize,
+) -> Result<Map<String, Value>, RenderError> {
+    let mut chain = vec![prompt.id];
+    add_references(storage, prompt, arguments, policy, mode, max_depth, &mut chain).await
+}
+
+type RenderFuture<'a> = Pin<Box<dyn Future<Output = Result<Map<String, Value>, RenderError>> + Send + 'a>>;
+
+fn add_references<'a>(
+    storage: &'a dyn PromptStorage,
+    prompt: &'a Prompt,
+    arguments: &'a Map<String, Value>,
+    policy: MissingReferencePolicy,
+    mode: EngineMode,
+    max_depth: usize,
+    chain: &'a mut Vec<Uuid>,
+) -> RenderFuture<'a> {
+    Box::pin(async move {
+        let included = included_prompt_names(prompt);
+        if prompt.references.is_empty() && included.is_empty() {
+            return Ok(arguments.clone());
+        }
+        let mut merged = arguments.clone();
+
+        if !prompt.references.is_empty() {
+            let mut refs = Map::new();
+            for (alias, id) in &prompt.references {
+                check_chain(chain, id, &format!("'{}'", alias), max_depth)?;
+                let rendered = match storage.get_prompt(id).await.map_err(RenderError::Storage)? {
+                    Some(referenced) => {
+                        render_nested(storage, &referenced, arguments, policy, mode, max_depth, chain).await?
+                    }
+                    None => missing(policy, id.to_string(), || format!("Referenced prompt {} ('{}') not found", id, alias))?,
+                };
+                refs.insert(alias.clone(), Value::String(rendered));
+            }
+            merged.insert(REFS_KEY.to_string(), Value::Object(refs));
+        }
+
+        if !included.is_empty() {
+            let summaries = storage.list_prompt_summaries().await.map_err(RenderError::Storage)?;
+            let mut prompts = Map::new();
+            for name in included {
+                let found = match summaries.iter().find(|s| s.name == name) {
+                    Some(summary) => {
+                        check_chain(chain, &summary.id, &format!("prompt(name=\"{}\")", name), max_depth)?;
+                        storage.get_prompt(&summary.id).await.map_err(RenderError::Storage)?
+                    }
+                    None => None,
+                };
+                let rendered = match found {
+                    Some(included) => render_nested(storage, &included, arguments, policy, mode, max_depth, chain).await?,
+                    None => missing(policy, name.clone(), || format!("Included prompt '{}' not found", name))?,
+                };
+                prompts.insert(name, Value::String(rendered));
+            }
+            merged.insert(PROMPTS_KEY.to_string(), Value::Object(prompts));
+        }
+        Ok(merged)
+    })
+}
+
+/// Fails if rendering prompt `id` from the end of `chain` would loop or nest
+/// more than `max_depth` levels deep.
+fn check_chain(chain: &[Uuid], id: &Uuid, via: &str, max_depth: usize) -> Result<(), RenderError> {
+    if chain.contains(id) {
+        return Err(RenderError::Template(format!(
+            "Reference cycle detected: prompt {} references itself through {}",
+            id, via
+        )));
+    }
+    if chain.len() > max_depth {
+        return Err(template::depth_exceeded(max_depth));
+    }
+    Ok(())
+}
+
+/// Renders a referenced or included prompt after resolving its own references.
+/// Its `extends` parents may use whatever depth the chain leading to it left.
+async fn render_nested(
+    storage: &dyn PromptStorage,
+    prompt: &Prompt,
+    arguments: &Map<String, Value>,
+    policy: MissingReferencePolicy,
+    mode: EngineMode,
+    max_depth: usize,
+    chain: &mut Vec<Uuid>,
+) -> Result<String, RenderError> {
+    let remaining = max_depth.saturating_sub(chain.len());
+    chain.push(prompt.id);
+    let nested = add_references(storage, prompt, arguments, policy, mode, max_depth, chain).await;
+    chain.pop();
+    template::render_prompt(storage, prompt, &nested?, mode, remaining)
+        .await
+        // Report the configured limit rather than what was left of it at this level
+        .map_err(|e| match e {
+            RenderError::Template(msg) if msg == template::depth_exceeded(remaining).to_string() => {
+                template::depth_exceeded(max_depth)
+            }
+            e => e,
+        })
+}
+
+/// What to render for a related prompt that doesn't exist, per `policy`.
+fn missing(policy: MissingReferencePolicy, label: String, error: impl FnOnce() -> String) -> Result<String, RenderError> {
+    match policy {
+        MissingReferencePolicy::Error => Err(RenderError::Template(error())),
+        MissingReferencePolicy::Placeholder => Ok(format!("[missing: {}]", label)),
+        MissingReferencePolicy::Skip => Ok(String::new()),
+    }
+}
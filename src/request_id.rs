@@ -0,0 +1,95 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{Error, HttpMessage};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Header a request ID is read from and echoed back in.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied request ID that is kept; longer ones are replaced.
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// ID correlating the log lines of one request, available to handlers from the
+/// request extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Generates a new random request ID.
+pub fn generate() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// Returns the client's request ID if it is usable in logs and headers: 1 to
+/// [`MAX_REQUEST_ID_LEN`] visible ASCII characters.
+pub fn accept(supplied: &str) -> Option<&str> {
+    let valid = !supplied.is_empty()
+        && supplied.len() <= MAX_REQUEST_ID_LEN
+        && supplied.bytes().all(|b| b.is_ascii_graphic());
+    valid.then_some(supplied)
+}
+
+/// Middleware that takes the `X-Request-Id` header of each request, or generates
+/// one, and runs the rest of the request in a `request` span with a `request_id`
+/// field, so every event logged while handling it carries the ID. The ID is
+/// echoed back in the response's `X-Request-Id` header.
+#[derive(Clone, Copy, Default)]
+pub struct RequestIds;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIds
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestIdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddleware { service: Rc::new(service) }))
+    }
+}
+
+/// Service created by [`RequestIds`] for each worker.
+pub struct RequestIdMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(accept)
+            .map(str::to_string)
+            .unwrap_or_else(generate);
+        let span = tracing::info_span!("request", request_id = %id, method = %req.method(), path = %req.path());
+        req.extensions_mut().insert(RequestId(id.clone()));
+        let response = span.in_scope(|| self.service.call(req));
+        Box::pin(
+            async move {
+                let mut response = response.await?;
+                if let Ok(value) = HeaderValue::from_str(&id) {
+                    response.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                }
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
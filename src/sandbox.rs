@@ -0,0 +1,213 @@
+use crate::models::prompt::Prompt;
+use crate::template::{self, BoundedWriter, EngineMode, RenderError, RenderLimits, TemplateEngine};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use tera::ast::{ExprVal, Node};
+use tera::Tera;
+
+/// Most items a `range()` call may produce in a sandbox render.
+pub const MAX_RANGE_LEN: usize = 10_000;
+
+/// Most loop iterations a sandbox template may run, estimated before rendering.
+pub const MAX_LOOP_ITERATIONS: usize = 100_000;
+
+const TEMPLATE_NAME: &str = "__sandbox__";
+
+/// Renders untrusted `content` with the engine `mode` picks for it, the way a
+/// prompt with that content would be rendered, but without snippets or other prompts.
+///
+/// Before rendering, templates that could run away are rejected: macros and
+/// partials, which can recurse without bound, and loops that could run more than
+/// [`MAX_LOOP_ITERATIONS`] times. Loops over arguments or template literals are
+/// assumed to be as long as the largest of those, and `range()` may produce at
+/// most [`MAX_RANGE_LEN`] items. Output is written into a buffer that stops the
+/// render as soon as it grows past `limits.max_output_bytes`.
+pub async fn render(
+    content: String,
+    arguments: Map<String, Value>,
+    mode: EngineMode,
+    limits: RenderLimits,
+) -> Result<String, RenderError> {
+    let prompt = Prompt::new("sandbox".to_string(), content, None, None);
+    let width = data_width(&arguments).max(prompt.content.len());
+    let max_output = limits.max_output_bytes;
+
+    let render = match mode.engine_for(&prompt) {
+        TemplateEngine::Tera => {
+            let mut tera = Tera::default();
+            tera.register_function("range", bounded_range);
+            tera.add_raw_template(TEMPLATE_NAME, &prompt.content)?;
+            check_iterations(tera_iterations(&tera.get_template(TEMPLATE_NAME)?.ast, width)?)?;
+            Box::new(move |out: &mut BoundedWriter| {
+                tera.render_to(TEMPLATE_NAME, &template::build_context(&arguments), out)
+                    .map_err(RenderError::from)
+            }) as Box<dyn FnOnce(&mut BoundedWriter) -> Result<(), RenderError> + Send>
+        }
+        TemplateEngine::Handlebars => {
+            let compiled = handlebars::Template::compile(&prompt.content)
+                .map_err(|e| RenderError::Template(e.to_string()))?;
+            check_iterations(handlebars_iterations(&compiled, width)?)?;
+            let mut handlebars = handlebars::Handlebars::new();
+            handlebars.register_escape_fn(handlebars::no_escape);
+            handlebars.register_template(TEMPLATE_NAME, compiled);
+            Box::new(move |out: &mut BoundedWriter| {
+                handlebars
+                    .render_to_write(TEMPLATE_NAME, &Value::Object(arguments), out)
+                    .map_err(|e| RenderError::Template(e.to_string()))
+            })
+        }
+        TemplateEngine::None => return template::check_output_size(prompt.content, max_output),
+    };
+
+    template::run_with_limits(limits, async move {
+        let mut out = BoundedWriter::new(max_output);
+        let result = render(&mut out);
+        out.finish(result)
+    })
+    .await
+}
+
+/// Tera's `range()`, refusing to produce more than [`MAX_RANGE_LEN`] items.
+fn bounded_range(args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let arg = |name: &str, default: Option<usize>| match args.get(name) {
+        Some(value) => value
+            .as_u64()
+            .map(|n| n as usize)
+            .ok_or_else(|| tera::Error::msg(format!("range() needs a non-negative integer `{}`", name))),
+        None => default.ok_or_else(|| tera::Error::msg(format!("range() needs an `{}` argument", name))),
+    };
+    let (start, end, step_by) = (arg("start", Some(0))?, arg("end", None)?, arg("step_by", Some(1))?);
+    let len = range_len(start, end, step_by)
+        .ok_or_else(|| tera::Error::msg("range() needs `start` <= `end` and a `step_by` above 0"))?;
+    if len > MAX_RANGE_LEN {
+        return Err(tera::Error::msg(format!(
+            "range() would produce {} items, more than the {} allowed in the sandbox",
+            len, MAX_RANGE_LEN
+        )));
+    }
+    Ok(Value::from((start..end).step_by(step_by).collect::<Vec<usize>>()))
+}
+
+fn range_len(start: usize, end: usize, step_by: usize) -> Option<usize> {
+    (start <= end && step_by > 0).then(|| (end - start).div_ceil(step_by))
+}
+
+fn check_iterations(iterations: usize) -> Result<(), RenderError> {
+    if iterations > MAX_LOOP_ITERATIONS {
+        return Err(RenderError::LimitExceeded(format!(
+            "Loops could run up to {} times, more than the {} allowed in the sandbox",
+            iterations, MAX_LOOP_ITERATIONS
+        )));
+    }
+    Ok(())
+}
+
+/// Length of the largest array, object or string in `arguments`, at any depth.
+fn data_width(arguments: &Map<String, Value>) -> usize {
+    fn width(value: &Value) -> usize {
+        match value {
+            Value::Array(items) => items.iter().map(width).fold(items.len(), usize::max),
+            Value::Object(map) => map.values().map(width).fold(map.len(), usize::max),
+            Value::String(s) => s.chars().count(),
+            _ => 0,
+        }
+    }
+    arguments.values().map(width).max().unwrap_or(0)
+}
+
+fn unsupported(what: &str) -> RenderError {
+    RenderError::Template(format!("{} are not supported in the sandbox", what))
+}
+
+/// Upper bound on the loop iterations of Tera `nodes`, where a loop over anything
+/// but `range()` with literal arguments runs at most `width` times.
+fn tera_iterations(nodes: &[Node], width: usize) -> Result<usize, RenderError> {
+    let mut total = 0usize;
+    for node in nodes {
+        let iterations = match node {
+            Node::Forloop(_, for_loop, _) => {
+                let container = &for_loop.container;
+                let len = match &container.val {
+                    ExprVal::FunctionCall(call) if call.name == "range" => {
+                        literal_range_len(&call.args).unwrap_or(MAX_RANGE_LEN)
+                    }
+                    _ => width,
+                };
+                // Filters such as concat can combine the container with others of any size
+                let len = match container.filters.len() {
+                    0 => len,
+                    filters => len.max(width).max(MAX_RANGE_LEN).saturating_mul(filters + 1),
+                };
+                let body = tera_iterations(&for_loop.body, width)?;
+                let empty = match &for_loop.empty_body {
+                    Some(nodes) => tera_iterations(nodes, width)?,
+                    None => 0,
+                };
+                len.saturating_mul(body.saturating_add(1)).saturating_add(empty)
+            }
+            Node::If(if_node, _) => {
+                let mut sum = 0usize;
+                for (_, _, body) in &if_node.conditions {
+                    sum = sum.saturating_add(tera_iterations(body, width)?);
+                }
+                if let Some((_, body)) = &if_node.otherwise {
+                    sum = sum.saturating_add(tera_iterations(body, width)?);
+                }
+                sum
+            }
+            Node::Block(_, block, _) => tera_iterations(&block.body, width)?,
+            Node::FilterSection(_, section, _) => tera_iterations(&section.body, width)?,
+            Node::MacroDefinition(..) => return Err(unsupported("Macros")),
+            _ => 0,
+        };
+        total = total.saturating_add(iterations);
+    }
+    Ok(total)
+}
+
+/// Number of items `range()` produces for literal integer arguments.
+fn literal_range_len(args: &HashMap<String, tera::ast::Expr>) -> Option<usize> {
+    let arg = |name: &str| match args.get(name).map(|expr| &expr.val) {
+        Some(ExprVal::Int(n)) => usize::try_from(*n).ok().map(Some),
+        Some(_) => None,
+        None => Some(None),
+    };
+    range_len(arg("start")?.unwrap_or(0), arg("end")??, arg("step_by")?.unwrap_or(1))
+}
+
+/// Upper bound on the `{{#each}}` iterations of a Handlebars template, where each
+/// loop runs at most `width` times.
+fn handlebars_iterations(template: &handlebars::Template, width: usize) -> Result<usize, RenderError> {
+    use handlebars::template::TemplateElement;
+
+    let mut total = 0usize;
+    for element in &template.elements {
+        let iterations = match element {
+            TemplateElement::HelperBlock(helper) => {
+                let body = match &helper.template {
+                    Some(body) => handlebars_iterations(body, width)?,
+                    None => 0,
+                };
+                let inverse = match &helper.inverse {
+                    Some(inverse) => handlebars_iterations(inverse, width)?,
+                    None => 0,
+                };
+                let body = if helper.name.as_name() == Some("each") {
+                    width.saturating_mul(body.saturating_add(1))
+                } else {
+                    body
+                };
+                body.saturating_add(inverse)
+            }
+            TemplateElement::PartialExpression(_) | TemplateElement::PartialBlock(_) => {
+                return Err(unsupported("Partials"))
+            }
+            TemplateElement::DecoratorExpression(_) | TemplateElement::DecoratorBlock(_) => {
+                return Err(unsupported("Decorators and inline partials"))
+            }
+            _ => 0,
+        };
+        total = total.saturating_add(iterations);
+    }
+    Ok(total)
+}
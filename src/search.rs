@@ -0,0 +1,124 @@
+use crate::models::prompt::Prompt;
+use deunicode::{deunicode, deunicode_char};
+use serde::Serialize;
+use std::cmp::Reverse;
+
+/// Options controlling how `search_prompts` matches text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Also ignore accents, so `cafe` matches `Café`.
+    pub fold: bool,
+}
+
+/// Normalizes text for matching: lowercase, and with accents stripped when `fold` is set.
+pub fn normalize_for_search(text: &str, fold: bool) -> String {
+    if fold {
+        deunicode(text).to_lowercase()
+    } else {
+        text.to_lowercase()
+    }
+}
+
+/// Returns true if the prompt's name or content contains `query`, ignoring case
+/// (and accents when `options.fold` is set).
+pub fn prompt_matches(prompt: &Prompt, query: &str, options: &SearchOptions) -> bool {
+    let needle = normalize_for_search(query, options.fold);
+    normalize_for_search(&prompt.name, options.fold).contains(&needle)
+        || normalize_for_search(&prompt.content, options.fold).contains(&needle)
+}
+
+/// Markers wrapped around the matched text in search snippets.
+pub const HIGHLIGHT_START: &str = "<mark>";
+pub const HIGHLIGHT_END: &str = "</mark>";
+
+/// Characters of content shown on either side of the match in a snippet.
+pub const SNIPPET_CONTEXT: usize = 40;
+
+/// A search result with its relevance and, if requested, a highlighted excerpt.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub prompt: Prompt,
+    /// How often the query occurs in the name and content.
+    pub matches: usize,
+    /// Excerpt of the content around the first match, with the match wrapped in
+    /// [`HIGHLIGHT_START`] and [`HIGHLIGHT_END`]. `None` when only the name matched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+/// Counts the non-overlapping occurrences of `query` in the prompt's name and content.
+pub fn count_matches(prompt: &Prompt, query: &str, options: &SearchOptions) -> usize {
+    let needle = normalize_for_search(query, options.fold);
+    if needle.is_empty() {
+        return 0;
+    }
+    normalize_for_search(&prompt.name, options.fold).matches(&needle).count()
+        + normalize_for_search(&prompt.content, options.fold).matches(&needle).count()
+}
+
+/// Normalizes `text` like [`normalize_for_search`], also returning for each byte of
+/// the result the byte range of the original character it came from.
+fn normalize_with_offsets(text: &str, fold: bool) -> (String, Vec<(usize, usize)>) {
+    let mut normalized = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len());
+    for (start, c) in text.char_indices() {
+        let piece: String = if fold {
+            deunicode_char(c).unwrap_or("").to_lowercase()
+        } else {
+            c.to_lowercase().collect()
+        };
+        normalized.push_str(&piece);
+        offsets.extend(std::iter::repeat_n((start, start + c.len_utf8()), piece.len()));
+    }
+    (normalized, offsets)
+}
+
+/// Returns an excerpt of `text` around the first occurrence of `query`, with the
+/// occurrence wrapped in highlight markers and `…` where text was cut off.
+pub fn snippet(text: &str, query: &str, options: &SearchOptions) -> Option<String> {
+    let needle = normalize_for_search(query, options.fold);
+    if needle.is_empty() {
+        return None;
+    }
+    let (normalized, offsets) = normalize_with_offsets(text, options.fold);
+    let found = normalized.find(&needle)?;
+    let start = offsets[found].0;
+    let end = offsets[found + needle.len() - 1].1;
+
+    let before: Vec<(usize, char)> = text[..start].char_indices().collect();
+    let from = before.len().checked_sub(SNIPPET_CONTEXT).map_or(0, |i| before[i].0);
+    let to = text[end..].char_indices().nth(SNIPPET_CONTEXT).map_or(text.len(), |(i, _)| end + i);
+
+    let mut excerpt = String::new();
+    if from > 0 {
+        excerpt.push('…');
+    }
+    excerpt.push_str(&text[from..start]);
+    excerpt.push_str(HIGHLIGHT_START);
+    excerpt.push_str(&text[start..end]);
+    excerpt.push_str(HIGHLIGHT_END);
+    excerpt.push_str(&text[end..to]);
+    if to < text.len() {
+        excerpt.push('…');
+    }
+    Some(excerpt)
+}
+
+/// Turns search results into hits ordered by relevance: prompts whose name matches
+/// come first, then by match count, most first. Prompts that tie keep their order.
+/// Snippets are only computed if `highlight` is set.
+pub fn rank_hits(prompts: Vec<Prompt>, query: &str, options: &SearchOptions, highlight: bool) -> Vec<SearchHit> {
+    let needle = normalize_for_search(query, options.fold);
+    let name_matches = |hit: &SearchHit| !needle.is_empty() && normalize_for_search(&hit.prompt.name, options.fold).contains(&needle);
+    let mut hits: Vec<SearchHit> = prompts
+        .into_iter()
+        .map(|prompt| SearchHit {
+            matches: count_matches(&prompt, query, options),
+            snippet: if highlight { snippet(&prompt.content, query, options) } else { None },
+            prompt,
+        })
+        .collect();
+    hits.sort_by_cached_key(|hit| (Reverse(name_matches(hit)), Reverse(hit.matches)));
+    hits
+}
@@ -0,0 +1,59 @@
+use crate::models::prompt::Prompt;
+use crate::storage::PromptStorage;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Reads a list of prompts from a seed file.
+///
+/// Files ending in `.yaml` or `.yml` are parsed as YAML, anything else as JSON.
+pub async fn load_seed_file<P: AsRef<Path>>(path: P) -> Result<Vec<Prompt>> {
+    let path = path.as_ref();
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read seed file '{}'", path.display()))?;
+
+    let is_yaml = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"));
+
+    if is_yaml {
+        serde_yaml::from_str(&contents)
+            .with_context(|| format!("Failed to parse YAML seed file '{}'", path.display()))
+    } else {
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse JSON seed file '{}'", path.display()))
+    }
+}
+
+/// What [`seed_if_empty`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedOutcome {
+    /// The store already held prompts, so the seed file was not read.
+    Skipped,
+    /// The store was empty and this many prompts were saved from the seed file,
+    /// which may be zero for a file with an empty list.
+    Seeded(usize),
+}
+
+/// Upserts the prompts from `path` into `storage` if it holds no prompts yet,
+/// passing each through `prepare` first, as the REST handlers do before saving.
+pub async fn seed_if_empty<P: AsRef<Path>>(
+    storage: &dyn PromptStorage,
+    path: P,
+    prepare: impl Fn(&mut Prompt),
+) -> Result<SeedOutcome> {
+    if storage.count_prompts().await? > 0 {
+        return Ok(SeedOutcome::Skipped);
+    }
+
+    let mut prompts = load_seed_file(path).await?;
+    for prompt in &mut prompts {
+        prepare(prompt);
+        storage
+            .save_prompt(prompt)
+            .await
+            .with_context(|| format!("Failed to seed prompt '{}' ({})", prompt.name, prompt.id))?;
+    }
+    Ok(SeedOutcome::Seeded(prompts.len()))
+}
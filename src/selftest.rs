@@ -0,0 +1,52 @@
+use crate::models::prompt::Prompt;
+use crate::storage::PromptStorage;
+use anyhow::{bail, Context, Result};
+
+/// Runs a write/read/delete round trip against a scratch prompt.
+///
+/// Exercises the configured backend end to end without touching existing prompts.
+pub async fn run_self_test(storage: &dyn PromptStorage) -> Result<()> {
+    let scratch = Prompt::new(
+        "__self_test__".to_string(),
+        "Self-test prompt for {{ name }}".to_string(),
+        Some("self-test".to_string()),
+        Some(vec!["name".to_string()]),
+    );
+    let id = scratch.id;
+
+    storage
+        .save_prompt(&scratch)
+        .await
+        .context("Self-test failed to write scratch prompt")?;
+
+    let read_back = storage.get_prompt(&id).await;
+    // Clean up before reporting read failures so the scratch prompt never lingers,
+    // not even as a tombstone
+    let deleted = storage.purge_prompt(&id).await;
+
+    match read_back.context("Self-test failed to read scratch prompt")? {
+        Some(prompt) if prompt.content == scratch.content && prompt.name == scratch.name => {}
+        Some(_) => bail!("Self-test read back a scratch prompt that differs from what was written"),
+        None => bail!("Self-test could not find the scratch prompt after writing it"),
+    }
+
+    if !deleted.context("Self-test failed to delete scratch prompt")? {
+        bail!("Self-test delete reported the scratch prompt as missing");
+    }
+    if storage
+        .get_prompt(&id)
+        .await
+        .context("Self-test failed to verify deletion")?
+        .is_some()
+    {
+        bail!("Self-test scratch prompt still exists after deletion");
+    }
+    if storage
+        .is_deleted(&id)
+        .await
+        .context("Self-test failed to verify deletion")?
+    {
+        bail!("Self-test left a tombstone for the scratch prompt");
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::sync::RwLock;
+use tera::Tera;
+
+/// Prefix of the template names snippets are registered under, as in
+/// `{% include "snippet:greeting" %}`.
+pub const SNIPPET_PREFIX: &str = "snippet:";
+
+/// The installed library, shared by every render.
+static SNIPPETS: RwLock<Option<Tera>> = RwLock::new(None);
+
+/// Reusable template fragments that prompts can include but that are not
+/// prompts themselves: they have no id, aren't stored, and aren't listed.
+#[derive(Debug, Default)]
+pub struct SnippetLibrary {
+    tera: Tera,
+    names: Vec<String>,
+}
+
+impl SnippetLibrary {
+    /// Compiles named snippets, failing on the first invalid template.
+    pub fn from_snippets<I>(snippets: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut library = Self::default();
+        for (name, content) in snippets {
+            if library.names.contains(&name) {
+                bail!("Duplicate snippet '{}'", name);
+            }
+            library
+                .tera
+                .add_raw_template(&format!("{}{}", SNIPPET_PREFIX, name), &content)
+                .with_context(|| format!("Invalid template in snippet '{}'", name))?;
+            library.names.push(name);
+        }
+        library.names.sort();
+        Ok(library)
+    }
+
+    /// Loads every file directly in `dir` as a snippet named after the file without
+    /// its extension, so `greeting.txt` becomes `snippet:greeting`. Hidden files and
+    /// subdirectories are skipped.
+    pub async fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut entries = tokio::fs::read_dir(dir)
+            .await
+            .with_context(|| format!("Failed to read snippets directory '{}'", dir.display()))?;
+        let mut snippets = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if name.starts_with('.') || !entry.file_type().await?.is_file() {
+                continue;
+            }
+            let content = tokio::fs::read_to_string(&path)
+                .await
+                .with_context(|| format!("Failed to read snippet '{}'", path.display()))?;
+            snippets.push((name.to_string(), content));
+        }
+        Self::from_snippets(snippets)
+    }
+
+    /// Snippet names, without the prefix, in sorted order.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}
+
+/// Makes `library` available to every subsequent render, replacing any library
+/// installed before.
+pub fn install(library: SnippetLibrary) {
+    *SNIPPETS.write().unwrap_or_else(|e| e.into_inner()) = Some(library.tera);
+    // Cached templates were compiled with the previous snippets
+    crate::template::clear_template_cache();
+}
+
+/// A Tera instance holding the installed snippets, for renders to add their own
+/// templates to.
+pub(crate) fn base_tera() -> Tera {
+    SNIPPETS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
@@ -0,0 +1,106 @@
+use crate::models::prompt::{Prompt, PromptSummary};
+use std::cmp::Ordering;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Field a prompt listing can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortField {
+    #[default]
+    Name,
+    Category,
+    Version,
+    Id,
+}
+
+/// Ordering for prompt listings. The default, used by every backend, is by name
+/// ascending; ties are always broken by name and then id so the order is total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SortOrder {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    /// Parses `name`, `category`, `version` or `id`, prefixed with `-` for descending order.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (descending, name) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let field = match name {
+            "name" => SortField::Name,
+            "category" => SortField::Category,
+            "version" => SortField::Version,
+            "id" => SortField::Id,
+            other => {
+                return Err(format!(
+                    "Unknown sort field '{}'; expected name, category, version or id (prefix with - to reverse)",
+                    other
+                ))
+            }
+        };
+        Ok(Self { field, descending })
+    }
+}
+
+/// Listing fields that prompts and prompt summaries share, for sorting either.
+pub trait Sortable {
+    fn sort_name(&self) -> &str;
+    fn sort_category(&self) -> Option<&str>;
+    fn sort_version(&self) -> u32;
+    fn sort_id(&self) -> Uuid;
+}
+
+impl Sortable for Prompt {
+    fn sort_name(&self) -> &str {
+        &self.name
+    }
+    fn sort_category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+    fn sort_version(&self) -> u32 {
+        self.version
+    }
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+}
+
+impl Sortable for PromptSummary {
+    fn sort_name(&self) -> &str {
+        &self.name
+    }
+    fn sort_category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+    fn sort_version(&self) -> u32 {
+        self.version
+    }
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+}
+
+/// Sorts `items` in `order`, breaking ties by name and then id.
+pub fn sort_items<T: Sortable>(items: &mut [T], order: SortOrder) {
+    items.sort_by(|a, b| {
+        let primary = match order.field {
+            SortField::Name => Ordering::Equal,
+            SortField::Category => a.sort_category().cmp(&b.sort_category()),
+            SortField::Version => a.sort_version().cmp(&b.sort_version()),
+            SortField::Id => a.sort_id().cmp(&b.sort_id()),
+        };
+        let ordering = primary
+            .then_with(|| a.sort_name().cmp(b.sort_name()))
+            .then_with(|| a.sort_id().cmp(&b.sort_id()));
+        if order.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
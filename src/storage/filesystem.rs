@@ -1,16 +1,44 @@
-use crate::models::prompt::Prompt;
+use crate::attachments::AttachmentInfo;
+use crate::categories;
+use crate::models::category::Category;
+use crate::models::collection::Collection;
+use crate::models::prompt::{Prompt, PromptSummary};
+use crate::storage::fs_index::{self, IndexEntries, IndexEntry};
+use crate::sorting::{self, SortOrder};
 use crate::storage::PromptStorage;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Mutex;
 use tracing::{error, warn};
 use uuid::Uuid;
 
+/// File in the prompt directory holding the category records.
+const CATEGORIES_FILE: &str = "categories.json";
+
+/// File in the prompt directory holding the prompt collections.
+const COLLECTIONS_FILE: &str = "collections.json";
+
+/// Byte order mark some tools write at the start of UTF-8 files. Stripped on read, never written.
+const UTF8_BOM: char = '\u{feff}';
+
 #[derive(Debug, Clone)]
 pub struct FileSystemStorage {
     prompt_dir: PathBuf,
+    /// In-memory copy of `index.json`, loaded on first use.
+    index: Arc<Mutex<Option<IndexEntries>>>,
+    /// Serializes read-modify-write cycles of `categories.json`.
+    categories_lock: Arc<Mutex<()>>,
+    /// Serializes read-modify-write cycles of `collections.json`.
+    collections_lock: Arc<Mutex<()>>,
+    /// Whether writes are synced to disk before they are reported as done.
+    fsync: bool,
 }
 
 impl FileSystemStorage {
@@ -23,52 +51,155 @@ impl FileSystemStorage {
             // Log error but proceed; async methods will handle failures
             error!(path = %path_buf.display(), error = %e, "Failed to create prompt directory during initialization");
         }
-        Self { prompt_dir: path_buf }
+        Self {
+            prompt_dir: path_buf,
+            index: Arc::new(Mutex::new(None)),
+            categories_lock: Arc::new(Mutex::new(())),
+            collections_lock: Arc::new(Mutex::new(())),
+            fsync: false,
+        }
+    }
+
+    /// Enables or disables syncing each written file to disk (`fsync`) before a
+    /// write returns or the file is renamed into place. Without it, a write that
+    /// succeeded can still be lost if the machine crashes before the OS flushes
+    /// its page cache; with it, every write waits on the disk. Off by default.
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
     }
 
     fn get_prompt_path(&self, id: &Uuid) -> PathBuf {
         self.prompt_dir.join(format!("{}.json", id))
     }
-}
 
-#[async_trait]
-impl PromptStorage for FileSystemStorage {
-    async fn list_prompts(&self) -> Result<Vec<Prompt>> {
-        let mut prompts = Vec::new();
+    /// Marker file left behind when a prompt is deleted.
+    fn get_tombstone_path(&self, id: &Uuid) -> PathBuf {
+        self.prompt_dir.join(format!("{}.deleted", id))
+    }
+
+    /// Directory holding the attachments of a prompt.
+    fn get_attachments_dir(&self, id: &Uuid) -> PathBuf {
+        self.prompt_dir.join(format!("{}.attachments", id))
+    }
+
+    /// Directory holding the earlier versions of a prompt, one `v<N>.json` file each.
+    fn get_versions_dir(&self, id: &Uuid) -> PathBuf {
+        self.prompt_dir.join(format!("{}.versions", id))
+    }
+
+    fn get_version_path(&self, id: &Uuid, version: u32) -> PathBuf {
+        self.get_versions_dir(id).join(format!("v{}.json", version))
+    }
+
+    /// Copies the stored prompt into its version history before it is replaced by an edit.
+    async fn archive_version(&self, previous: &Prompt) -> Result<()> {
+        let dir = self.get_versions_dir(&previous.id);
+        fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("Failed to create version directory '{}'", dir.display()))?;
+        let path = self.get_version_path(&previous.id, previous.version);
+        let contents = serde_json::to_string_pretty(previous)
+            .with_context(|| format!("Failed to serialize version {} of prompt ID {}", previous.version, previous.id))?;
+        self.write_file(&path, contents.as_bytes())
+            .await
+            .with_context(|| format!("Failed to save prompt version file: {}", path.display()))
+    }
+
+    /// Writes `contents` to `path`, replacing any existing file, and syncs it to
+    /// disk when fsync is enabled.
+    async fn write_file(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let mut file = fs::File::create(path)
+            .await
+            .with_context(|| format!("Failed to create/open file for writing: {}", path.display()))?;
+        file.write_all(contents)
+            .await
+            .with_context(|| format!("Failed to write to file: {}", path.display()))?;
+        file.flush()
+            .await
+            .with_context(|| format!("Failed to flush file: {}", path.display()))?;
+        if self.fsync {
+            file.sync_all()
+                .await
+                .with_context(|| format!("Failed to sync file to disk: {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Reads a JSON file of records in the prompt directory, such as
+    /// `categories.json`. A missing file means no records.
+    async fn read_records<K: Ord + DeserializeOwned, V: DeserializeOwned>(&self, file: &str) -> Result<BTreeMap<K, V>> {
+        let path = self.prompt_dir.join(file);
+        match fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("Failed to parse record file: {}", path.display())),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read record file: {}", path.display())),
+        }
+    }
+
+    /// Writes a JSON file of records via a temporary file, so readers never see a partial file.
+    async fn write_records<K: Ord + Serialize, V: Serialize>(&self, file: &str, records: &BTreeMap<K, V>) -> Result<()> {
+        let path = self.prompt_dir.join(file);
+        let tmp = self.prompt_dir.join(format!("{}.tmp", file));
+        let contents = serde_json::to_vec_pretty(records)
+            .with_context(|| format!("Failed to serialize records for {}", file))?;
+        self.write_file(&tmp, &contents)
+            .await
+            .with_context(|| format!("Failed to write record file: {}", tmp.display()))?;
+        fs::rename(&tmp, &path)
+            .await
+            .with_context(|| format!("Failed to replace record file: {}", path.display()))
+    }
+
+    /// Reads the category records, keyed by name.
+    async fn read_categories(&self) -> Result<BTreeMap<String, Category>> {
+        self.read_records(CATEGORIES_FILE).await
+    }
+
+    async fn write_categories(&self, categories: &BTreeMap<String, Category>) -> Result<()> {
+        self.write_records(CATEGORIES_FILE, categories).await
+    }
+
+    /// Reads the collections, keyed by ID.
+    async fn read_collections(&self) -> Result<BTreeMap<Uuid, Collection>> {
+        self.read_records(COLLECTIONS_FILE).await
+    }
+
+    /// Ids of all `{uuid}.json` prompt files in the directory, with their paths.
+    async fn prompt_files(&self) -> Result<Vec<(Uuid, PathBuf)>> {
+        let mut files = Vec::new();
         let mut read_dir = fs::read_dir(&self.prompt_dir)
             .await
             .with_context(|| format!("Failed to read prompt directory '{}'", self.prompt_dir.display()))?;
 
         while let Some(entry) = read_dir.next_entry().await? {
             let path = entry.path();
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
-                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                    if let Ok(id) = Uuid::parse_str(stem) {
-                        match self.get_prompt(&id).await {
-                            Ok(Some(prompt)) => prompts.push(prompt),
-                            Ok(None) => warn!(path = %path.display(), "Prompt file found but failed to read/deserialize"),
-                            Err(e) => warn!(path = %path.display(), error = %e, "Error reading prompt file during list"),
-                        }
-                    }
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(id) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| Uuid::parse_str(s).ok()) {
+                    files.push((id, path));
                 }
             }
         }
-        Ok(prompts)
+        Ok(files)
     }
 
-    async fn get_prompt(&self, id: &Uuid) -> Result<Option<Prompt>> {
-        let path = self.get_prompt_path(id);
+    /// Reads and parses one prompt file. Returns `None` if it doesn't exist.
+    async fn read_prompt_file(&self, path: &Path) -> Result<Option<Prompt>> {
         if !path.exists() {
             return Ok(None);
         }
-
-        match fs::File::open(&path).await {
+        match fs::File::open(path).await {
             Ok(mut file) => {
-                let mut contents = String::new();
-                if let Err(e) = file.read_to_string(&mut contents).await {
+                let mut bytes = Vec::new();
+                if let Err(e) = file.read_to_end(&mut bytes).await {
                     return Err(e).with_context(|| format!("Failed to read prompt file: {}", path.display()));
                 }
-                serde_json::from_str(&contents)
+                let contents = String::from_utf8(bytes)
+                    .with_context(|| format!("Prompt file is not valid UTF-8: {}", path.display()))?;
+                // Editors on Windows may prepend a UTF-8 BOM, which serde_json rejects
+                let contents = contents.strip_prefix(UTF8_BOM).unwrap_or(&contents);
+                serde_json::from_str(contents)
                     .map(Some)
                     .with_context(|| format!("Failed to deserialize prompt from file: {}", path.display()))
             }
@@ -77,8 +208,186 @@ impl PromptStorage for FileSystemStorage {
         }
     }
 
+    /// Brings the index up to date with the directory, re-reading only prompt files
+    /// that are new or were modified since they were indexed, and dropping removed
+    /// ones. Returns the refreshed entries, keyed by the id in each file's name.
+    async fn refresh_index(&self) -> Result<IndexEntries> {
+        let mut cache = self.index.lock().await;
+        let mut entries = match cache.take() {
+            Some(entries) => entries,
+            None => fs_index::read_index(&self.prompt_dir).await.unwrap_or_default(),
+        };
+        let mut changed = false;
+        let mut present = HashSet::new();
+
+        for (id, path) in self.prompt_files().await? {
+            present.insert(id);
+            let modified_ms = match fs_index::modified_ms(&path).await {
+                Ok(modified_ms) => modified_ms,
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "Skipping prompt file during index refresh");
+                    continue;
+                }
+            };
+            if entries.get(&id).is_some_and(|e| e.modified_ms == modified_ms) {
+                continue;
+            }
+            match self.read_prompt_file(&path).await {
+                Ok(Some(prompt)) => {
+                    entries.insert(id, IndexEntry { summary: PromptSummary::from(&prompt), modified_ms });
+                    changed = true;
+                }
+                Ok(None) => warn!(path = %path.display(), "Prompt file disappeared during index refresh"),
+                Err(e) => warn!(path = %path.display(), error = %e, "Error reading prompt file during index refresh"),
+            }
+        }
+
+        let indexed = entries.len();
+        entries.retain(|id, _| present.contains(id));
+        changed |= entries.len() != indexed;
+
+        if changed {
+            if let Err(e) = fs_index::write_index(&self.prompt_dir, &entries).await {
+                warn!(error = %e, "Failed to write prompt index");
+            }
+        }
+        *cache = Some(entries.clone());
+        Ok(entries)
+    }
+
+    /// Paths of the prompt files whose index summary passes `keep`, in listing order.
+    ///
+    /// A copied file can claim the same id as another file. Only the most recently
+    /// modified of them is returned, preferring the one named after the id on a tie.
+    async fn indexed_files(&self, keep: impl Fn(&PromptSummary) -> bool) -> Result<Vec<PathBuf>> {
+        // File id and modification time of the file chosen for each prompt id
+        let mut chosen: HashMap<Uuid, (Uuid, u64)> = HashMap::new();
+        let mut summaries = Vec::new();
+        for (file_id, entry) in self.refresh_index().await? {
+            let id = entry.summary.id;
+            let Some((kept_file_id, kept_modified_ms)) = chosen.get_mut(&id) else {
+                chosen.insert(id, (file_id, entry.modified_ms));
+                summaries.push(entry.summary);
+                continue;
+            };
+            let newer = entry.modified_ms > *kept_modified_ms
+                || (entry.modified_ms == *kept_modified_ms && file_id == id);
+            let (kept, ignored) = if newer { (file_id, *kept_file_id) } else { (*kept_file_id, file_id) };
+            warn!(
+                prompt_id = %id,
+                kept = %self.get_prompt_path(&kept).display(),
+                ignored = %self.get_prompt_path(&ignored).display(),
+                "Several prompt files claim the same id; listing the most recently modified"
+            );
+            if newer {
+                *kept_file_id = file_id;
+                *kept_modified_ms = entry.modified_ms;
+                if let Some(summary) = summaries.iter_mut().find(|s| s.id == id) {
+                    *summary = entry.summary;
+                }
+            }
+        }
+        summaries.retain(|summary| keep(summary));
+        sorting::sort_items(&mut summaries, SortOrder::default());
+        Ok(summaries
+            .iter()
+            .map(|summary| self.get_prompt_path(&chosen[&summary.id].0))
+            .collect())
+    }
+
+    /// Reads the given prompt files in order, skipping (and logging) unreadable ones.
+    async fn read_prompt_files(&self, paths: Vec<PathBuf>) -> Vec<Prompt> {
+        let mut prompts = Vec::with_capacity(paths.len());
+        for path in paths {
+            match self.read_prompt_file(&path).await {
+                Ok(Some(prompt)) => prompts.push(prompt),
+                Ok(None) => warn!(path = %path.display(), "Prompt file disappeared while listing"),
+                Err(e) => warn!(path = %path.display(), error = %e, "Error reading prompt file during list"),
+            }
+        }
+        prompts
+    }
+
+    /// Applies `update` to the index and persists it. The index is only a cache,
+    /// so failures are logged rather than failing the write that triggered them.
+    /// Does nothing if no index has been built yet; the next listing builds it.
+    async fn update_index(&self, update: impl FnOnce(&mut IndexEntries)) {
+        let mut cache = self.index.lock().await;
+        if cache.is_none() {
+            *cache = fs_index::read_index(&self.prompt_dir).await;
+        }
+        let Some(entries) = cache.as_mut() else {
+            return;
+        };
+        update(entries);
+        if let Err(e) = fs_index::write_index(&self.prompt_dir, entries).await {
+            warn!(error = %e, "Failed to update prompt index");
+        }
+    }
+}
+
+#[async_trait]
+impl PromptStorage for FileSystemStorage {
+    fn backend_name(&self) -> &'static str {
+        "filesystem"
+    }
+
+    /// Reads every prompt file in index order. A copied file can claim the same id
+    /// as another file; only the most recently modified of them is listed.
+    async fn list_prompts(&self) -> Result<Vec<Prompt>> {
+        let files = self.indexed_files(|_| true).await?;
+        Ok(self.read_prompt_files(files).await)
+    }
+
+    /// Serves summaries from `index.json`, re-reading only prompt files that are
+    /// new or were modified since they were indexed, and dropping removed ones.
+    async fn list_prompt_summaries(&self) -> Result<Vec<PromptSummary>> {
+        let mut summaries: Vec<PromptSummary> = self.refresh_index().await?.into_values().map(|e| e.summary).collect();
+        sorting::sort_items(&mut summaries, SortOrder::default());
+        Ok(summaries)
+    }
+
+    async fn count_prompts(&self) -> Result<usize> {
+        Ok(self.indexed_files(|_| true).await?.len())
+    }
+
+    /// Pages through the index, which is sorted like [`PromptStorage::list_prompts`],
+    /// and reads only the prompt files on the requested page.
+    async fn list_prompts_paginated(&self, offset: usize, limit: usize) -> Result<Vec<Prompt>> {
+        let files = self.indexed_files(|_| true).await?.into_iter().skip(offset).take(limit).collect();
+        Ok(self.read_prompt_files(files).await)
+    }
+
+    /// Filters on the index and reads only the matching prompt files.
+    async fn list_prompts_by_category(&self, category: &str) -> Result<Vec<Prompt>> {
+        let files = self
+            .indexed_files(|summary| summary.category.as_deref() == Some(category))
+            .await?;
+        Ok(self.read_prompt_files(files).await)
+    }
+
+    /// Filters on the index and reads only the matching prompt files.
+    async fn list_prompts_by_category_prefix(&self, prefix: &str) -> Result<Vec<Prompt>> {
+        let files = self
+            .indexed_files(|summary| summary.category.as_deref().is_some_and(|c| categories::in_subtree(c, prefix)))
+            .await?;
+        Ok(self.read_prompt_files(files).await)
+    }
+
+    async fn get_prompt(&self, id: &Uuid) -> Result<Option<Prompt>> {
+        self.read_prompt_file(&self.get_prompt_path(id)).await
+    }
+
     async fn save_prompt(&self, prompt: &Prompt) -> Result<()> {
         let path = self.get_prompt_path(&prompt.id);
+        // An unreadable existing file is simply replaced
+        let previous = self.get_prompt(&prompt.id).await.ok().flatten();
+        if let Some(previous) = previous.as_ref().filter(|p| p.version != prompt.version) {
+            self.archive_version(previous).await?;
+        }
+        let mut prompt = prompt.clone();
+        prompt.stamp_saved(previous.map(|p| p.created_at));
+        let prompt = &prompt;
         let contents = serde_json::to_string_pretty(prompt)
             .with_context(|| format!("Failed to serialize prompt ID {}", prompt.id))?;
 
@@ -89,13 +398,22 @@ impl PromptStorage for FileSystemStorage {
                 .with_context(|| format!("Failed to create prompt directory '{}'", self.prompt_dir.display()))?;
         }
 
-        let mut file = fs::File::create(&path)
+        // Written and flushed before reading the modification time, so the index records the final one
+        self.write_file(&path, contents.as_bytes())
             .await
-            .with_context(|| format!("Failed to create/open prompt file for writing: {}", path.display()))?;
+            .with_context(|| format!("Failed to save prompt file: {}", path.display()))?;
 
-        file.write_all(contents.as_bytes())
-            .await
-            .with_context(|| format!("Failed to write to prompt file: {}", path.display()))
+        match fs_index::modified_ms(&path).await {
+            Ok(modified_ms) => {
+                let entry = IndexEntry { summary: PromptSummary::from(prompt), modified_ms };
+                self.update_index(|entries| {
+                    entries.insert(prompt.id, entry);
+                })
+                .await
+            }
+            Err(e) => warn!(path = %path.display(), error = %e, "Not indexing saved prompt"),
+        }
+        Ok(())
     }
 
     async fn delete_prompt(&self, id: &Uuid) -> Result<bool> {
@@ -105,9 +423,198 @@ impl PromptStorage for FileSystemStorage {
         }
 
         match fs::remove_file(&path).await {
-            Ok(_) => Ok(true),
+            Ok(_) => {
+                let tombstone = self.get_tombstone_path(id);
+                if let Err(e) = self.write_file(&tombstone, b"").await {
+                    warn!(path = %tombstone.display(), error = %e, "Failed to write tombstone for deleted prompt");
+                }
+                self.update_index(|entries| {
+                    entries.remove(id);
+                })
+                .await;
+                for dir in [self.get_attachments_dir(id), self.get_versions_dir(id)] {
+                    if let Err(e) = fs::remove_dir_all(&dir).await {
+                        if e.kind() != std::io::ErrorKind::NotFound {
+                            warn!(path = %dir.display(), error = %e, "Failed to remove files of deleted prompt");
+                        }
+                    }
+                }
+                Ok(true)
+            }
             Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
             Err(e) => Err(e).with_context(|| format!("Failed to delete prompt file: {}", path.display())),
         }
     }
+
+    async fn is_deleted(&self, id: &Uuid) -> Result<bool> {
+        Ok(fs::try_exists(self.get_tombstone_path(id)).await.unwrap_or(false))
+    }
+
+    async fn purge_prompt(&self, id: &Uuid) -> Result<bool> {
+        let removed = self.delete_prompt(id).await?;
+        let tombstone = self.get_tombstone_path(id);
+        match fs::remove_file(&tombstone).await {
+            Ok(()) => Ok(removed),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(removed),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove tombstone: {}", tombstone.display())),
+        }
+    }
+
+    async fn list_prompt_versions(&self, id: &Uuid) -> Result<Vec<u32>> {
+        let Some(current) = self.get_prompt(id).await? else {
+            return Ok(Vec::new());
+        };
+        let mut versions = vec![current.version];
+        let dir = self.get_versions_dir(id);
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(versions),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read version directory '{}'", dir.display())),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let version = name
+                .to_str()
+                .and_then(|name| name.strip_prefix('v')?.strip_suffix(".json")?.parse::<u32>().ok());
+            if let Some(version) = version.filter(|v| !versions.contains(v)) {
+                versions.push(version);
+            }
+        }
+        versions.sort_unstable();
+        Ok(versions)
+    }
+
+    async fn get_prompt_version(&self, id: &Uuid, version: u32) -> Result<Option<Prompt>> {
+        let Some(current) = self.get_prompt(id).await? else {
+            return Ok(None);
+        };
+        if current.version == version {
+            return Ok(Some(current));
+        }
+        let path = self.get_version_path(id, version);
+        match fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .with_context(|| format!("Failed to deserialize prompt version from file: {}", path.display())),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to open prompt version file: {}", path.display())),
+        }
+    }
+
+    async fn save_attachment(&self, prompt_id: &Uuid, name: &str, data: &[u8]) -> Result<()> {
+        let dir = self.get_attachments_dir(prompt_id);
+        fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("Failed to create attachment directory '{}'", dir.display()))?;
+        let path = dir.join(name);
+        self.write_file(&path, data)
+            .await
+            .with_context(|| format!("Failed to write attachment: {}", path.display()))
+    }
+
+    async fn get_attachment(&self, prompt_id: &Uuid, name: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.get_attachments_dir(prompt_id).join(name);
+        match fs::read(&path).await {
+            Ok(data) => Ok(Some(data)),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read attachment: {}", path.display())),
+        }
+    }
+
+    async fn list_attachments(&self, prompt_id: &Uuid) -> Result<Vec<AttachmentInfo>> {
+        let dir = self.get_attachments_dir(prompt_id);
+        let mut read_dir = match fs::read_dir(&dir).await {
+            Ok(read_dir) => read_dir,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read attachment directory '{}'", dir.display())),
+        };
+        let mut attachments = Vec::new();
+        while let Some(entry) = read_dir.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if let (true, Some(name)) = (metadata.is_file(), entry.file_name().to_str()) {
+                attachments.push(AttachmentInfo { name: name.to_string(), size: metadata.len() });
+            }
+        }
+        attachments.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(attachments)
+    }
+
+    async fn list_categories(&self) -> Result<Vec<Category>> {
+        Ok(self.read_categories().await?.into_values().collect())
+    }
+
+    async fn save_category(&self, category: &Category) -> Result<()> {
+        let _guard = self.categories_lock.lock().await;
+        let mut categories = self.read_categories().await?;
+        categories.insert(category.name.clone(), category.clone());
+        self.write_categories(&categories).await
+    }
+
+    async fn delete_category(&self, name: &str) -> Result<bool> {
+        let _guard = self.categories_lock.lock().await;
+        let mut categories = self.read_categories().await?;
+        if categories.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.write_categories(&categories).await?;
+        Ok(true)
+    }
+
+    async fn list_collections(&self) -> Result<Vec<Collection>> {
+        let mut collections: Vec<Collection> = self.read_collections().await?.into_values().collect();
+        collections.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        Ok(collections)
+    }
+
+    async fn get_collection(&self, id: &Uuid) -> Result<Option<Collection>> {
+        Ok(self.read_collections().await?.remove(id))
+    }
+
+    async fn save_collection(&self, collection: &Collection) -> Result<()> {
+        let _guard = self.collections_lock.lock().await;
+        let mut collections = self.read_collections().await?;
+        collections.insert(collection.id, collection.clone());
+        self.write_records(COLLECTIONS_FILE, &collections).await
+    }
+
+    async fn delete_collection(&self, id: &Uuid) -> Result<bool> {
+        let _guard = self.collections_lock.lock().await;
+        let mut collections = self.read_collections().await?;
+        if collections.remove(id).is_none() {
+            return Ok(false);
+        }
+        self.write_records(COLLECTIONS_FILE, &collections).await?;
+        Ok(true)
+    }
+
+    /// Removes `.tmp` files left behind by interrupted writes and rebuilds the index
+    /// from every prompt file.
+    async fn run_maintenance(&self) -> Result<Vec<String>> {
+        // Index and record writes go through temporary files under these locks, so none is in flight
+        let _categories_guard = self.categories_lock.lock().await;
+        let _collections_guard = self.collections_lock.lock().await;
+        let mut cache = self.index.lock().await;
+        let mut removed = 0;
+        let mut read_dir = fs::read_dir(&self.prompt_dir)
+            .await
+            .with_context(|| format!("Failed to read prompt directory '{}'", self.prompt_dir.display()))?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "tmp") {
+                fs::remove_file(&path)
+                    .await
+                    .with_context(|| format!("Failed to remove temporary file: {}", path.display()))?;
+                removed += 1;
+            }
+        }
+        // An empty cache makes the next listing re-read every prompt file
+        *cache = Some(IndexEntries::new());
+        drop(cache);
+
+        let indexed = self.list_prompt_summaries().await?.len();
+        Ok(vec![
+            format!("Removed {} stray temporary file(s)", removed),
+            format!("Rebuilt index of {} prompt(s)", indexed),
+        ])
+    }
 }
@@ -0,0 +1,70 @@
+use crate::models::prompt::PromptSummary;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::fs;
+use uuid::Uuid;
+
+/// Name of the index file kept in the prompt directory.
+pub const INDEX_FILE: &str = "index.json";
+
+/// Bumped whenever the index layout changes; older indexes are rebuilt.
+const INDEX_FORMAT: u32 = 1;
+
+/// Listing fields of one prompt file, with the file's modification time so
+/// edits made outside the server can be detected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub summary: PromptSummary,
+    pub modified_ms: u64,
+}
+
+pub type IndexEntries = BTreeMap<Uuid, IndexEntry>;
+
+#[derive(Serialize, Deserialize)]
+struct IndexFile {
+    format: u32,
+    entries: IndexEntries,
+}
+
+pub fn index_path(dir: &Path) -> PathBuf {
+    dir.join(INDEX_FILE)
+}
+
+/// Reads the index from `dir`. Returns `None` if it is missing, unreadable or
+/// in an older format, in which case it should be rebuilt.
+pub async fn read_index(dir: &Path) -> Option<IndexEntries> {
+    let bytes = fs::read(index_path(dir)).await.ok()?;
+    let index: IndexFile = serde_json::from_slice(&bytes).ok()?;
+    (index.format == INDEX_FORMAT).then_some(index.entries)
+}
+
+/// Writes the index to `dir` via a temporary file, so readers never see a partial index.
+pub async fn write_index(dir: &Path, entries: &IndexEntries) -> Result<()> {
+    let path = index_path(dir);
+    let tmp = dir.join(format!("{}.tmp", INDEX_FILE));
+    let contents = serde_json::to_vec(&IndexFile {
+        format: INDEX_FORMAT,
+        entries: entries.clone(),
+    })
+    .context("Failed to serialize prompt index")?;
+    fs::write(&tmp, contents)
+        .await
+        .with_context(|| format!("Failed to write prompt index: {}", tmp.display()))?;
+    fs::rename(&tmp, &path)
+        .await
+        .with_context(|| format!("Failed to replace prompt index: {}", path.display()))
+}
+
+/// Modification time of `path` in milliseconds since the Unix epoch.
+pub async fn modified_ms(path: &Path) -> Result<u64> {
+    let modified = fs::metadata(path)
+        .await
+        .and_then(|m| m.modified())
+        .with_context(|| format!("Failed to read modification time of {}", path.display()))?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64))
+}
@@ -0,0 +1,100 @@
+use super::PromptStorage;
+use crate::models::prompt::Prompt;
+use crate::sorting::{self, SortOrder};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Storage that keeps prompts in memory only, for tests and ephemeral demo servers.
+/// Everything is lost when the process exits.
+#[derive(Debug, Default)]
+pub struct InMemoryStorage {
+    prompts: RwLock<HashMap<Uuid, Prompt>>,
+    /// Earlier versions of each prompt, replaced by edits.
+    versions: RwLock<HashMap<Uuid, BTreeMap<u32, Prompt>>>,
+    /// IDs of prompts that were deleted, so reads can answer `410 Gone`.
+    deleted: RwLock<HashSet<Uuid>>,
+}
+
+impl InMemoryStorage {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PromptStorage for InMemoryStorage {
+    fn backend_name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<Prompt>> {
+        let mut prompts: Vec<Prompt> = self.prompts.read().await.values().cloned().collect();
+        sorting::sort_items(&mut prompts, SortOrder::default());
+        Ok(prompts)
+    }
+
+    async fn count_prompts(&self) -> Result<usize> {
+        Ok(self.prompts.read().await.len())
+    }
+
+    async fn get_prompt(&self, id: &Uuid) -> Result<Option<Prompt>> {
+        Ok(self.prompts.read().await.get(id).cloned())
+    }
+
+    async fn save_prompt(&self, prompt: &Prompt) -> Result<()> {
+        let mut prompts = self.prompts.write().await;
+        let mut stored = prompt.clone();
+        stored.stamp_saved(prompts.get(&prompt.id).map(|p| p.created_at));
+        if let Some(previous) = prompts.insert(prompt.id, stored) {
+            if previous.version != prompt.version {
+                self.versions.write().await.entry(prompt.id).or_default().insert(previous.version, previous);
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_prompt(&self, id: &Uuid) -> Result<bool> {
+        let removed = self.prompts.write().await.remove(id).is_some();
+        if removed {
+            self.versions.write().await.remove(id);
+            self.deleted.write().await.insert(*id);
+        }
+        Ok(removed)
+    }
+
+    async fn is_deleted(&self, id: &Uuid) -> Result<bool> {
+        Ok(self.deleted.read().await.contains(id))
+    }
+
+    async fn purge_prompt(&self, id: &Uuid) -> Result<bool> {
+        let removed = self.delete_prompt(id).await?;
+        self.deleted.write().await.remove(id);
+        Ok(removed)
+    }
+
+    async fn list_prompt_versions(&self, id: &Uuid) -> Result<Vec<u32>> {
+        let prompts = self.prompts.read().await;
+        let Some(current) = prompts.get(id) else {
+            return Ok(Vec::new());
+        };
+        let mut versions: Vec<u32> = self.versions.read().await.get(id).map(|v| v.keys().copied().collect()).unwrap_or_default();
+        if !versions.contains(&current.version) {
+            versions.push(current.version);
+            versions.sort_unstable();
+        }
+        Ok(versions)
+    }
+
+    async fn get_prompt_version(&self, id: &Uuid, version: u32) -> Result<Option<Prompt>> {
+        let prompts = self.prompts.read().await;
+        match prompts.get(id) {
+            Some(current) if current.version == version => Ok(Some(current.clone())),
+            Some(_) => Ok(self.versions.read().await.get(id).and_then(|v| v.get(&version)).cloned()),
+            None => Ok(None),
+        }
+    }
+}
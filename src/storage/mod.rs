@@ -1,27 +1,270 @@
-use crate::models::prompt::Prompt;
-use anyhow::Result;
+use crate::attachments::AttachmentInfo;
+use crate::categories;
+use crate::models::category::Category;
+use crate::models::collection::Collection;
+use crate::models::prompt::{Prompt, PromptSummary};
+use crate::search::{self, SearchHit, SearchOptions};
+use anyhow::{bail, Result};
 use async_trait::async_trait;
+use std::time::Duration;
 use uuid::Uuid;
 
 pub mod filesystem;
+mod fs_index;
+pub mod memory;
 pub mod postgres;
+pub mod redis;
+pub mod registry;
+pub mod sqlite;
+
+pub use registry::{create_storage, register_storage, StorageFactory, StorageOptions, StorageSetupError};
+
+/// Outcome of [`PromptStorage::delete_prompt_if_version`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteOutcome {
+    /// The prompt existed at the expected version and was deleted.
+    Deleted,
+    /// No prompt with this ID exists.
+    NotFound,
+    /// The prompt exists but has been edited since; it was not deleted.
+    VersionMismatch { current: u32 },
+}
+
+/// Connection settings shared by the networked storage backends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageConfig {
+    /// Connection URL for the backend.
+    pub url: String,
+    /// Maximum number of pooled connections.
+    pub max_connections: u32,
+    /// How long to wait for a pooled connection before failing.
+    pub acquire_timeout: Duration,
+    /// Close pooled connections idle for longer than this. `None` keeps them open.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl StorageConfig {
+    /// Creates a config for `url` with default pool settings.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            max_connections: 5,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+        }
+    }
+}
 
 /// Trait defining the interface for prompt storage backends.
 #[async_trait]
 pub trait PromptStorage: Send + Sync + 'static { // Ensure Send + Sync for Arc<dyn T>
-    /// Lists all prompts available in the storage.
+    /// Short name of the backend type, e.g. "filesystem" or "postgres".
+    fn backend_name(&self) -> &'static str;
+
+    /// Lists all prompts available in the storage, ordered by name then ID.
+    ///
+    /// Every backend must return this same order, so clients see consistent
+    /// listings regardless of the backend in use.
     async fn list_prompts(&self) -> Result<Vec<Prompt>>;
 
+    /// Lists the id, name, category and version of every prompt, without content,
+    /// in the same order as [`PromptStorage::list_prompts`].
+    /// Backends that can do this more cheaply than loading every prompt should override it.
+    async fn list_prompt_summaries(&self) -> Result<Vec<PromptSummary>> {
+        Ok(self.list_prompts().await?.iter().map(PromptSummary::from).collect())
+    }
+
+    /// Counts the prompts in storage.
+    async fn count_prompts(&self) -> Result<usize> {
+        Ok(self.list_prompts().await?.len())
+    }
+
+    /// Lists up to `limit` prompts starting at `offset`, ordered by name then ID
+    /// so that consecutive pages are stable.
+    async fn list_prompts_paginated(&self, offset: usize, limit: usize) -> Result<Vec<Prompt>> {
+        let prompts = self.list_prompts().await?;
+        Ok(prompts.into_iter().skip(offset).take(limit).collect())
+    }
+
+    /// Finds prompts whose name or content contains `query`, ignoring case.
+    async fn search_prompts(&self, query: &str, options: &SearchOptions) -> Result<Vec<Prompt>> {
+        let prompts = self.list_prompts().await?;
+        Ok(prompts
+            .into_iter()
+            .filter(|p| search::prompt_matches(p, query, options))
+            .collect())
+    }
+
+    /// Finds prompts like [`PromptStorage::search_prompts`], most matches of `query`
+    /// first, with a highlighted content snippet per result when `highlight` is set.
+    async fn search_prompts_ranked(&self, query: &str, options: &SearchOptions, highlight: bool) -> Result<Vec<SearchHit>> {
+        let prompts = self.search_prompts(query, options).await?;
+        Ok(search::rank_hits(prompts, query, options, highlight))
+    }
+
+    /// Lists prompts whose category is exactly `category`. Prompts without a
+    /// category never match.
+    async fn list_prompts_by_category(&self, category: &str) -> Result<Vec<Prompt>> {
+        let prompts = self.list_prompts().await?;
+        Ok(prompts
+            .into_iter()
+            .filter(|p| p.category.as_deref() == Some(category))
+            .collect())
+    }
+
+    /// Lists prompts that have `tag`, ignoring case.
+    async fn list_prompts_by_tag(&self, tag: &str) -> Result<Vec<Prompt>> {
+        let prompts = self.list_prompts().await?;
+        Ok(prompts.into_iter().filter(|p| p.has_tag(tag)).collect())
+    }
+
+    /// Lists prompts in the category `prefix` or any of its subcategories, e.g.
+    /// `writing` for the whole `writing/...` subtree (see [`categories::in_subtree`]).
+    async fn list_prompts_by_category_prefix(&self, prefix: &str) -> Result<Vec<Prompt>> {
+        let prompts = self.list_prompts().await?;
+        Ok(prompts
+            .into_iter()
+            .filter(|p| p.category.as_deref().is_some_and(|c| categories::in_subtree(c, prefix)))
+            .collect())
+    }
+
+    /// Lists prompts whose content is between `min` and `max` characters long,
+    /// inclusive. An unset bound is open.
+    async fn list_prompts_by_length(&self, min: Option<usize>, max: Option<usize>) -> Result<Vec<Prompt>> {
+        let prompts = self.list_prompts().await?;
+        Ok(prompts
+            .into_iter()
+            .filter(|p| {
+                let length = p.content.chars().count();
+                min.is_none_or(|min| length >= min) && max.is_none_or(|max| length <= max)
+            })
+            .collect())
+    }
+
+    /// Lists the drafts forked from the prompt with this ID.
+    async fn list_forks(&self, id: &Uuid) -> Result<Vec<Prompt>> {
+        let prompts = self.list_prompts().await?;
+        Ok(prompts.into_iter().filter(|p| p.forked_from == Some(*id)).collect())
+    }
+
     /// Retrieves a specific prompt by its ID.
     async fn get_prompt(&self, id: &Uuid) -> Result<Option<Prompt>>;
 
     /// Saves a prompt (creates if new, updates if exists based on ID).
+    ///
+    /// When the stored prompt has a different `version`, backends that keep history
+    /// archive it first, so it stays available from [`PromptStorage::get_prompt_version`].
+    /// Saves that keep the version, such as locking, replace the stored prompt in place.
     async fn save_prompt(&self, prompt: &Prompt) -> Result<()>;
 
-    /// Deletes a prompt by its ID.
+    /// Lists the versions of a prompt that can be retrieved, oldest first and
+    /// including the current one. Empty if the prompt doesn't exist.
+    /// Backends that don't keep history only have the current version.
+    async fn list_prompt_versions(&self, id: &Uuid) -> Result<Vec<u32>> {
+        Ok(self.get_prompt(id).await?.map(|p| vec![p.version]).unwrap_or_default())
+    }
+
+    /// Retrieves a prompt as it was at `version`, or `None` if that version isn't stored.
+    async fn get_prompt_version(&self, id: &Uuid, version: u32) -> Result<Option<Prompt>> {
+        Ok(self.get_prompt(id).await?.filter(|p| p.version == version))
+    }
+
+    /// Deletes a prompt by its ID, along with its version history.
     /// Returns true if the prompt was deleted, false if it was not found.
     async fn delete_prompt(&self, id: &Uuid) -> Result<bool>;
 
+    /// Deletes the prompt only if its current version is `expected_version`.
+    ///
+    /// The default implementation checks and deletes in two steps, so a concurrent
+    /// edit can slip in between; backends that can should do both atomically.
+    async fn delete_prompt_if_version(&self, id: &Uuid, expected_version: u32) -> Result<DeleteOutcome> {
+        match self.get_prompt(id).await? {
+            None => Ok(DeleteOutcome::NotFound),
+            Some(prompt) if prompt.version != expected_version => {
+                Ok(DeleteOutcome::VersionMismatch { current: prompt.version })
+            }
+            Some(_) if self.delete_prompt(id).await? => Ok(DeleteOutcome::Deleted),
+            Some(_) => Ok(DeleteOutcome::NotFound),
+        }
+    }
+
+    /// Returns true if a prompt with this ID existed and was deleted.
+    /// Backends that don't keep tombstones report every missing ID as never existing.
+    async fn is_deleted(&self, _id: &Uuid) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Deletes a prompt without leaving a tombstone, so it reads as never having
+    /// existed. Meant for scratch prompts no client has seen, such as the
+    /// self-test's. Returns true if the prompt existed.
+    async fn purge_prompt(&self, id: &Uuid) -> Result<bool> {
+        self.delete_prompt(id).await
+    }
+
+    /// Stores a file under `name` alongside the prompt, replacing any attachment
+    /// of the same name. Attachments are removed when their prompt is deleted.
+    async fn save_attachment(&self, _prompt_id: &Uuid, _name: &str, _data: &[u8]) -> Result<()> {
+        bail!("The {} storage backend does not support attachments", self.backend_name())
+    }
+
+    /// Reads the attachment `name` of a prompt, or `None` if there is none.
+    async fn get_attachment(&self, _prompt_id: &Uuid, _name: &str) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    /// Lists the attachments of a prompt, ordered by name.
+    async fn list_attachments(&self, _prompt_id: &Uuid) -> Result<Vec<AttachmentInfo>> {
+        Ok(Vec::new())
+    }
+
+    /// Lists the category records, ordered by name.
+    async fn list_categories(&self) -> Result<Vec<Category>> {
+        Ok(Vec::new())
+    }
+
+    /// Retrieves the category record with this name.
+    async fn get_category(&self, name: &str) -> Result<Option<Category>> {
+        Ok(self.list_categories().await?.into_iter().find(|c| c.name == name))
+    }
+
+    /// Saves a category record, replacing any record with the same name.
+    async fn save_category(&self, _category: &Category) -> Result<()> {
+        bail!("The {} storage backend does not support category records", self.backend_name())
+    }
+
+    /// Deletes a category record. Prompts in the category keep their `category` value.
+    /// Returns true if the record was deleted, false if it was not found.
+    async fn delete_category(&self, _name: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Lists the prompt collections, ordered by name then ID.
+    async fn list_collections(&self) -> Result<Vec<Collection>> {
+        Ok(Vec::new())
+    }
+
+    /// Retrieves the collection with this ID.
+    async fn get_collection(&self, id: &Uuid) -> Result<Option<Collection>> {
+        Ok(self.list_collections().await?.into_iter().find(|c| c.id == *id))
+    }
+
+    /// Saves a collection (creates if new, updates if exists based on ID).
+    async fn save_collection(&self, _collection: &Collection) -> Result<()> {
+        bail!("The {} storage backend does not support collections", self.backend_name())
+    }
+
+    /// Deletes a collection. Its member prompts are not affected.
+    /// Returns true if the collection was deleted, false if it was not found.
+    async fn delete_collection(&self, _id: &Uuid) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Runs backend housekeeping such as compacting tables or removing leftover files.
+    /// Returns a description of each action taken; backends with nothing to do return none.
+    async fn run_maintenance(&self) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
     // Optional: Add methods for initialization or schema management if needed
     // async fn init_storage(&self) -> Result<()>;
 }
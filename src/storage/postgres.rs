@@ -1,21 +1,58 @@
-use super::PromptStorage;
-use crate::models::prompt::Prompt;
-use anyhow::{Context, Result};
+use super::{DeleteOutcome, PromptStorage, StorageConfig};
+use crate::attachments::AttachmentInfo;
+use crate::categories::CATEGORY_SEPARATOR;
+use crate::models::category::Category;
+use crate::models::collection::Collection;
+use crate::models::prompt::{normalize_tags, Prompt, PromptSummary};
+use crate::search::{self, SearchHit, SearchOptions};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
-use sqlx::postgres::{PgPool, PgPoolOptions};
-use sqlx::{FromRow, Row};
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
+use sqlx::{FromRow, Postgres, Row, Transaction};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use tracing::{info, warn};
 use uuid::Uuid;
 
+/// `ts_headline` options producing the same markers and roughly the same length
+/// as [`search::snippet`].
+/// Full-text document searched alongside the substring match, covered by the
+/// `prompts_search_idx` GIN index. Queries must repeat it verbatim to use the index.
+const SEARCH_VECTOR: &str =
+    "to_tsvector('simple', name || ' ' || content || ' ' || coalesce(description, ''))";
+const HEADLINE_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>, MinWords=5, MaxWords=15, MaxFragments=1";
+
 // Define a struct that maps to the database table row
 #[derive(FromRow, Debug, Clone)]
 struct PromptRow {
     id: Uuid,
     name: String,
     content: String,
+    content_encoding: Option<String>,
+    content_type: Option<String>,
     category: Option<String>,
+    tags: serde_json::Value,
     variables: Option<serde_json::Value>,
+    variable_metadata: serde_json::Value,
+    template_engine: Option<String>,
     description: Option<String>,
+    extends: Option<Uuid>,
+    locked: bool,
+    render_quota_per_hour: Option<i32>,
+    metadata: serde_json::Value,
+    sections: Option<serde_json::Value>,
+    default_arguments: serde_json::Value,
+    delimiters: Option<serde_json::Value>,
+    references: serde_json::Value,
+    owner: Option<String>,
+    forked_from: Option<Uuid>,
+    forked_from_version: Option<i32>,
+    tests: serde_json::Value,
+    version: i32,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
 }
 
 // Helper to convert from DB row struct to our application Prompt struct
@@ -25,35 +62,166 @@ impl From<PromptRow> for Prompt {
             id: row.id,
             name: row.name,
             content: row.content,
+            content_encoding: row.content_encoding,
+            content_type: row.content_type,
             category: row.category,
-            variables: row.variables.and_then(|v| serde_json::from_value(v).ok()),
+            tags: decode_column(row.id, "tags", row.tags).unwrap_or_default(),
+            variables: row.variables.and_then(|v| decode_column(row.id, "variables", v)),
+            variable_metadata: decode_column(row.id, "variable_metadata", row.variable_metadata).unwrap_or_default(),
+            template_engine: row
+                .template_engine
+                .and_then(|engine| decode_column(row.id, "template_engine", serde_json::Value::String(engine))),
+            extends: row.extends,
+            locked: row.locked,
+            render_quota_per_hour: row.render_quota_per_hour.map(|q| q.max(0) as u32),
+            metadata: match row.metadata {
+                serde_json::Value::Object(map) => map,
+                _ => serde_json::Map::new(),
+            },
+            sections: row.sections.and_then(|v| decode_column(row.id, "sections", v)),
+            default_arguments: match row.default_arguments {
+                serde_json::Value::Object(map) => map,
+                _ => serde_json::Map::new(),
+            },
+            delimiters: row.delimiters.and_then(|v| decode_column(row.id, "delimiters", v)),
+            references: decode_column(row.id, "references", row.references).unwrap_or_default(),
+            owner: row.owner,
+            forked_from: row.forked_from,
+            forked_from_version: row.forked_from_version.map(|v| v.max(1) as u32),
+            tests: decode_column(row.id, "tests", row.tests).unwrap_or_default(),
+            version: row.version.max(1) as u32,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+/// Deserializes a JSONB column of a prompt row. A value that doesn't fit the model is
+/// logged and treated as unset, so one bad column doesn't make the whole prompt unreadable.
+fn decode_column<T: serde::de::DeserializeOwned>(id: Uuid, column: &str, value: serde_json::Value) -> Option<T> {
+    match serde_json::from_value(value) {
+        Ok(decoded) => Some(decoded),
+        Err(e) => {
+            warn!(prompt_id = %id, column, error = %e, "Discarding prompt column that failed to deserialize");
+            None
         }
     }
 }
 
+/// Decodes prompt rows one at a time. A row that can't be decoded at all, e.g. one
+/// left with a NULL in a column this version requires, is logged and skipped so a
+/// single bad row never fails a whole listing.
+fn decode_prompt_rows(rows: &[PgRow]) -> Vec<Prompt> {
+    rows.iter()
+        .filter_map(|row| match PromptRow::from_row(row) {
+            Ok(decoded) => Some(Prompt::from(decoded)),
+            Err(e) => {
+                let id: Option<Uuid> = row.try_get("id").ok();
+                warn!(prompt_id = ?id, error = %e, "Skipping prompt row that failed to decode");
+                None
+            }
+        })
+        .collect()
+}
+
 /// PostgreSQL storage implementation.
 #[derive(Clone)]
 pub struct PostgresStorage {
     pool: Arc<PgPool>,
+    /// Whether the `unaccent` extension could be enabled, for accent-insensitive search.
+    unaccent_available: Arc<AtomicBool>,
+    /// Namespace every query is scoped to; empty for the default namespace.
+    namespace: String,
 }
 
 impl PostgresStorage {
-    pub async fn new(database_url: &str) -> Result<Self> {
+    /// Connects to PostgreSQL using the URL and pool settings in `config`.
+    pub async fn new(config: &StorageConfig) -> Result<Self> {
         let pool = PgPoolOptions::new()
-            .max_connections(5) // Configure pool size
-            .connect(database_url)
+            .max_connections(config.max_connections)
+            .acquire_timeout(config.acquire_timeout)
+            .idle_timeout(config.idle_timeout)
+            .connect(&config.url)
             .await
             .with_context(|| {
                 format!(
                     "Failed to create PostgreSQL connection pool for URL: {}",
-                    database_url
+                    config.url
                 )
             })?;
         Ok(PostgresStorage {
             pool: Arc::new(pool),
+            unaccent_available: Arc::new(AtomicBool::new(false)),
+            namespace: String::new(),
         })
     }
 
+    /// Scopes every query to `namespace`, stored in the `namespace` column of each
+    /// table. Prompts, categories, collections and deletion records of other
+    /// namespaces are invisible, and ids already used by another namespace can't
+    /// be saved. Attachments are scoped through their prompt.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// Brings a `prompts` table created by an older version up to date: fails with a
+    /// description of every column whose type doesn't match what this version expects,
+    /// then adds the missing columns, logging each one.
+    async fn upgrade_prompts_table(&self) -> Result<()> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT column_name, data_type FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = 'prompts'",
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .context("Failed to inspect the columns of the prompts table")?;
+        let existing: HashMap<String, String> = rows.into_iter().collect();
+
+        let incompatible: Vec<String> = PROMPT_COLUMNS
+            .iter()
+            .filter_map(|(column, data_type, _)| {
+                let actual = existing.get(*column)?;
+                (actual != data_type).then(|| format!("{} is {}, expected {}", column, actual, data_type))
+            })
+            .collect();
+        if !incompatible.is_empty() {
+            bail!(
+                "The existing prompts table is incompatible with this version: {}. Migrate or rename the table before starting the server.",
+                incompatible.join("; ")
+            );
+        }
+
+        for (column, _, definition) in PROMPT_COLUMNS.iter().filter(|(column, _, _)| !existing.contains_key(*column)) {
+            info!(column, "Adding missing column to prompts table");
+            // Quoted because some names, like references, are reserved words
+            sqlx::query(&format!(r#"ALTER TABLE prompts ADD COLUMN IF NOT EXISTS "{}" {}"#, column, definition))
+                .execute(&*self.pool)
+                .await
+                .with_context(|| format!("Failed to add {} column to prompts table", column))?;
+        }
+        Ok(())
+    }
+
+    /// Adds the `namespace` column to tables created by versions without namespaces,
+    /// and makes category names unique per namespace instead of globally.
+    async fn upgrade_namespaces(&self) -> Result<()> {
+        let statements = [
+            "ALTER TABLE deleted_prompts ADD COLUMN IF NOT EXISTS namespace TEXT NOT NULL DEFAULT ''",
+            "ALTER TABLE categories ADD COLUMN IF NOT EXISTS namespace TEXT NOT NULL DEFAULT ''",
+            "ALTER TABLE collections ADD COLUMN IF NOT EXISTS namespace TEXT NOT NULL DEFAULT ''",
+            "ALTER TABLE categories DROP CONSTRAINT IF EXISTS categories_pkey",
+            "CREATE UNIQUE INDEX IF NOT EXISTS categories_namespace_name_idx ON categories (namespace, name)",
+            "CREATE INDEX IF NOT EXISTS prompts_namespace_idx ON prompts (namespace)",
+        ];
+        for statement in statements {
+            sqlx::query(statement)
+                .execute(&*self.pool)
+                .await
+                .with_context(|| format!("Failed to upgrade schema for namespaces: {}", statement))?;
+        }
+        Ok(())
+    }
+
     /// Initializes the database schema if it doesn't exist.
     pub async fn init_schema(&self) -> Result<()> {
         sqlx::query(
@@ -65,31 +233,321 @@ impl PostgresStorage {
                 category TEXT,
                 variables JSONB,
                 description TEXT,
+                extends UUID,
+                locked BOOLEAN NOT NULL DEFAULT FALSE,
+                render_quota_per_hour INTEGER,
+                metadata JSONB NOT NULL DEFAULT '{}'::jsonb,
+                sections JSONB,
+                version INTEGER NOT NULL DEFAULT 1,
+                default_arguments JSONB NOT NULL DEFAULT '{}'::jsonb,
+                forked_from UUID,
+                delimiters JSONB,
+                owner TEXT,
+                "references" JSONB NOT NULL DEFAULT '{}'::jsonb,
+                variable_metadata JSONB NOT NULL DEFAULT '{}'::jsonb,
+                namespace TEXT NOT NULL DEFAULT '',
+                tests JSONB NOT NULL DEFAULT '[]'::jsonb,
+                content_encoding TEXT,
+                content_type TEXT,
                 created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                tags JSONB NOT NULL DEFAULT '[]'::jsonb,
+                template_engine TEXT,
+                forked_from_version INTEGER
             );
             "#,
         )
         .execute(&*self.pool)
         .await
         .context("Failed to initialize database schema")?;
+
+        self.upgrade_prompts_table().await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS deleted_prompts (
+                id UUID PRIMARY KEY,
+                deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                namespace TEXT NOT NULL DEFAULT ''
+            );
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .context("Failed to initialize deleted_prompts table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS attachments (
+                prompt_id UUID NOT NULL REFERENCES prompts(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                data BYTEA NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (prompt_id, name)
+            );
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .context("Failed to initialize attachments table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS prompt_versions (
+                id UUID NOT NULL REFERENCES prompts(id) ON DELETE CASCADE,
+                version INTEGER NOT NULL,
+                namespace TEXT NOT NULL DEFAULT '',
+                document JSONB NOT NULL,
+                archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (id, version)
+            );
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .context("Failed to initialize prompt_versions table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS categories (
+                name TEXT NOT NULL,
+                color TEXT,
+                icon TEXT,
+                description TEXT,
+                namespace TEXT NOT NULL DEFAULT ''
+            );
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .context("Failed to initialize categories table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS collections (
+                id UUID PRIMARY KEY,
+                name TEXT NOT NULL,
+                prompt_ids UUID[] NOT NULL DEFAULT '{}',
+                namespace TEXT NOT NULL DEFAULT ''
+            );
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .context("Failed to initialize collections table")?;
+
+        self.upgrade_namespaces().await?;
+
+        sqlx::query(&format!("CREATE INDEX IF NOT EXISTS prompts_search_idx ON prompts USING GIN ({})", SEARCH_VECTOR))
+            .execute(&*self.pool)
+            .await
+            .context("Failed to create full-text search index")?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS prompts_tags_idx ON prompts USING GIN (tags)")
+            .execute(&*self.pool)
+            .await
+            .context("Failed to create tags index")?;
+
+        // Optional: needs CREATE privilege; search falls back to folding in Rust without it
+        match sqlx::query("CREATE EXTENSION IF NOT EXISTS unaccent")
+            .execute(&*self.pool)
+            .await
+        {
+            Ok(_) => self.unaccent_available.store(true, Ordering::Relaxed),
+            Err(e) => warn!(error = %e, "unaccent extension unavailable; accent-insensitive search will run in the application"),
+        }
         Ok(())
     }
 }
 
 #[async_trait]
 impl PromptStorage for PostgresStorage {
+    fn backend_name(&self) -> &'static str {
+        "postgres"
+    }
+
     async fn list_prompts(&self) -> Result<Vec<Prompt>> {
-        let rows: Vec<PromptRow> = sqlx::query_as("SELECT * FROM prompts")
+        let rows = sqlx::query(&format!("SELECT * FROM prompts WHERE namespace = $1 {}", ORDER_BY_NAME))
+            .bind(&self.namespace)
             .fetch_all(&*self.pool)
             .await
             .context("Failed to fetch prompts from database")?;
-        Ok(rows.into_iter().map(Prompt::from).collect())
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn list_prompt_summaries(&self) -> Result<Vec<PromptSummary>> {
+        let rows: Vec<(Uuid, String, Option<String>, i32)> =
+            sqlx::query_as(&format!("SELECT id, name, category, version FROM prompts WHERE namespace = $1 {}", ORDER_BY_NAME))
+                .bind(&self.namespace)
+                .fetch_all(&*self.pool)
+                .await
+                .context("Failed to fetch prompt summaries from database")?;
+        Ok(rows
+            .into_iter()
+            .map(|(id, name, category, version)| PromptSummary {
+                id,
+                name,
+                category,
+                version: version.max(1) as u32,
+            })
+            .collect())
+    }
+
+    async fn count_prompts(&self) -> Result<usize> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM prompts WHERE namespace = $1")
+            .bind(&self.namespace)
+            .fetch_one(&*self.pool)
+            .await
+            .context("Failed to count prompts in database")?;
+        Ok(count as usize)
+    }
+
+    async fn list_prompts_paginated(&self, offset: usize, limit: usize) -> Result<Vec<Prompt>> {
+        let bound = |n: usize| i64::try_from(n).unwrap_or(i64::MAX);
+        let rows = sqlx::query(&format!("SELECT * FROM prompts WHERE namespace = $1 {} LIMIT $2 OFFSET $3", ORDER_BY_NAME))
+            .bind(&self.namespace)
+            .bind(bound(limit))
+            .bind(bound(offset))
+            .fetch_all(&*self.pool)
+            .await
+            .context("Failed to fetch page of prompts from database")?;
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn search_prompts(&self, query: &str, options: &SearchOptions) -> Result<Vec<Prompt>> {
+        if options.fold && !self.unaccent_available.load(Ordering::Relaxed) {
+            let prompts = self.list_prompts().await?;
+            return Ok(prompts
+                .into_iter()
+                .filter(|p| search::prompt_matches(p, query, options))
+                .collect());
+        }
+
+        // Full-text matching also finds every word of a multi-word query in any order
+        let filter = if options.fold {
+            "unaccent(name) ILIKE unaccent($1) OR unaccent(content) ILIKE unaccent($1)".to_string()
+        } else {
+            format!("name ILIKE $1 OR content ILIKE $1 OR {} @@ plainto_tsquery('simple', $3)", SEARCH_VECTOR)
+        };
+        let sql = format!("SELECT * FROM prompts WHERE namespace = $2 AND ({}) {}", filter, ORDER_BY_NAME);
+        let mut search = sqlx::query(&sql).bind(like_pattern(query)).bind(&self.namespace);
+        if !options.fold {
+            search = search.bind(query);
+        }
+        let rows = search
+            .fetch_all(&*self.pool)
+            .await
+            .context("Failed to search prompts in database")?;
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn search_prompts_ranked(&self, query: &str, options: &SearchOptions, highlight: bool) -> Result<Vec<SearchHit>> {
+        let prompts = self.search_prompts(query, options).await?;
+        let mut hits = search::rank_hits(prompts, query, options, false);
+        if !highlight || hits.is_empty() {
+            return Ok(hits);
+        }
+
+        let ids: Vec<Uuid> = hits.iter().map(|hit| hit.prompt.id).collect();
+        let headlines: HashMap<Uuid, String> = sqlx::query_as::<_, (Uuid, String)>(
+            "SELECT id, ts_headline('simple', content, plainto_tsquery('simple', $2), $3) FROM prompts WHERE id = ANY($1) AND namespace = $4",
+        )
+        .bind(&ids)
+        .bind(query)
+        .bind(HEADLINE_OPTIONS)
+        .bind(&self.namespace)
+        .fetch_all(&*self.pool)
+        .await
+        .context("Failed to highlight search results in database")?
+        .into_iter()
+        .collect();
+        // ts_headline only matches whole words, so partial-word matches get a snippet computed here
+        for hit in &mut hits {
+            hit.snippet = headlines
+                .get(&hit.prompt.id)
+                .filter(|headline| headline.contains(search::HIGHLIGHT_START))
+                .cloned()
+                .or_else(|| search::snippet(&hit.prompt.content, query, options));
+        }
+        Ok(hits)
+    }
+
+    async fn list_prompts_by_category(&self, category: &str) -> Result<Vec<Prompt>> {
+        let rows = sqlx::query(&format!("SELECT * FROM prompts WHERE category = $1 AND namespace = $2 {}", ORDER_BY_NAME))
+            .bind(category)
+            .bind(&self.namespace)
+            .fetch_all(&*self.pool)
+            .await
+            .context("Failed to list prompts by category from database")?;
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn list_prompts_by_tag(&self, tag: &str) -> Result<Vec<Prompt>> {
+        // Tags are stored normalized, so containment can use the GIN index
+        let tag = normalize_tags(&[tag.to_string()]);
+        if tag.is_empty() {
+            return Ok(Vec::new());
+        }
+        let rows = sqlx::query(&format!("SELECT * FROM prompts WHERE tags @> $1 AND namespace = $2 {}", ORDER_BY_NAME))
+            .bind(serde_json::Value::from(tag))
+            .bind(&self.namespace)
+            .fetch_all(&*self.pool)
+            .await
+            .context("Failed to list prompts by tag from database")?;
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn list_prompts_by_category_prefix(&self, prefix: &str) -> Result<Vec<Prompt>> {
+        // The category itself, or anything below it; an empty prefix matches every category
+        let prefix = prefix.trim_end_matches(CATEGORY_SEPARATOR);
+        let below = if prefix.is_empty() {
+            "%".to_string()
+        } else {
+            format!("{}{}%", escape_like(prefix), CATEGORY_SEPARATOR)
+        };
+        let rows = sqlx::query(&format!(
+            "SELECT * FROM prompts WHERE namespace = $3 AND (category = $1 OR category LIKE $2) {}",
+            ORDER_BY_NAME
+        ))
+            .bind(prefix)
+            .bind(below)
+            .bind(&self.namespace)
+            .fetch_all(&*self.pool)
+            .await
+            .context("Failed to list prompts by category prefix from database")?;
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn list_prompts_by_length(&self, min: Option<usize>, max: Option<usize>) -> Result<Vec<Prompt>> {
+        let bound = |n: usize| i64::try_from(n).unwrap_or(i64::MAX);
+        // char_length counts characters, not bytes, matching the other backends
+        let rows =
+            sqlx::query(&format!(
+                "SELECT * FROM prompts WHERE namespace = $3 AND char_length(content) BETWEEN $1 AND $2 {}",
+                ORDER_BY_NAME
+            ))
+                .bind(min.map_or(0, bound))
+                .bind(max.map_or(i64::MAX, bound))
+                .bind(&self.namespace)
+                .fetch_all(&*self.pool)
+                .await
+                .context("Failed to list prompts by content length from database")?;
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn list_forks(&self, id: &Uuid) -> Result<Vec<Prompt>> {
+        let rows = sqlx::query(&format!("SELECT * FROM prompts WHERE forked_from = $1 AND namespace = $2 {}", ORDER_BY_NAME))
+            .bind(id)
+            .bind(&self.namespace)
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to list forks of prompt with id '{}'", id))?;
+        Ok(decode_prompt_rows(&rows))
     }
 
     async fn get_prompt(&self, id: &Uuid) -> Result<Option<Prompt>> {
-        let row: Option<PromptRow> = sqlx::query_as("SELECT * FROM prompts WHERE id = $1")
+        let row: Option<PromptRow> = sqlx::query_as("SELECT * FROM prompts WHERE id = $1 AND namespace = $2")
             .bind(id)
+            .bind(&self.namespace)
             .fetch_optional(&*self.pool)
             .await
             .with_context(|| format!("Failed to fetch prompt with id '{}' from database", id))?;
@@ -104,19 +562,73 @@ impl PromptStorage for PostgresStorage {
             .transpose()
             .context("Failed to serialize prompt variables to JSON")?;
 
+        let sections_json = prompt
+            .sections
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()
+            .context("Failed to serialize prompt sections to JSON")?;
+
+        let delimiters_json = prompt
+            .delimiters
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()
+            .context("Failed to serialize prompt delimiters to JSON")?;
+
         let description = prompt.category.clone();
 
-        sqlx::query(
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+        // Keep the stored revision when an edit replaces it with another version
+        if let Some(previous) = self.get_prompt(&prompt.id).await?.filter(|p| p.version != prompt.version) {
+            let document = serde_json::to_value(&previous)
+                .with_context(|| format!("Failed to serialize version {} of prompt ID {}", previous.version, previous.id))?;
+            sqlx::query(
+                r#"
+                INSERT INTO prompt_versions (id, version, namespace, document)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (id, version) DO UPDATE SET document = EXCLUDED.document, archived_at = NOW()
+                "#,
+            )
+            .bind(previous.id)
+            .bind(i32::try_from(previous.version).unwrap_or(i32::MAX))
+            .bind(&self.namespace)
+            .bind(document)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to archive previous version of prompt with id '{}'", prompt.id))?;
+        }
+
+        let result = sqlx::query(
             r#"
-            INSERT INTO prompts (id, name, content, category, variables, description)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO prompts (id, name, content, category, variables, description, extends, locked, render_quota_per_hour, metadata, sections, version, default_arguments, forked_from, delimiters, owner, "references", variable_metadata, namespace, tests, content_encoding, content_type, created_at, updated_at, tags, template_engine, forked_from_version)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27)
             ON CONFLICT (id) DO UPDATE SET
                 name = EXCLUDED.name,
                 content = EXCLUDED.content,
                 category = EXCLUDED.category,
                 variables = EXCLUDED.variables,
                 description = EXCLUDED.description,
-                updated_at = NOW();
+                extends = EXCLUDED.extends,
+                locked = EXCLUDED.locked,
+                render_quota_per_hour = EXCLUDED.render_quota_per_hour,
+                metadata = EXCLUDED.metadata,
+                sections = EXCLUDED.sections,
+                version = EXCLUDED.version,
+                default_arguments = EXCLUDED.default_arguments,
+                forked_from = EXCLUDED.forked_from,
+                delimiters = EXCLUDED.delimiters,
+                owner = EXCLUDED.owner,
+                "references" = EXCLUDED."references",
+                variable_metadata = EXCLUDED.variable_metadata,
+                tests = EXCLUDED.tests,
+                content_encoding = EXCLUDED.content_encoding,
+                content_type = EXCLUDED.content_type,
+                updated_at = EXCLUDED.updated_at,
+                tags = EXCLUDED.tags,
+                template_engine = EXCLUDED.template_engine,
+                forked_from_version = EXCLUDED.forked_from_version
+            WHERE prompts.namespace = EXCLUDED.namespace;
             "#,
         )
         .bind(prompt.id)
@@ -125,18 +637,385 @@ impl PromptStorage for PostgresStorage {
         .bind(&prompt.category)
         .bind(&variables_json)
         .bind(&description)
-        .execute(&*self.pool)
+        .bind(prompt.extends)
+        .bind(prompt.locked)
+        .bind(prompt.render_quota_per_hour.map(|q| i32::try_from(q).unwrap_or(i32::MAX)))
+        .bind(serde_json::Value::Object(prompt.metadata.clone()))
+        .bind(&sections_json)
+        .bind(i32::try_from(prompt.version).unwrap_or(i32::MAX))
+        .bind(serde_json::Value::Object(prompt.default_arguments.clone()))
+        .bind(prompt.forked_from)
+        .bind(&delimiters_json)
+        .bind(&prompt.owner)
+        .bind(serde_json::to_value(&prompt.references).context("Failed to serialize prompt references to JSON")?)
+        .bind(serde_json::to_value(&prompt.variable_metadata).context("Failed to serialize prompt variable metadata to JSON")?)
+        .bind(&self.namespace)
+        .bind(serde_json::to_value(&prompt.tests).context("Failed to serialize prompt tests to JSON")?)
+        .bind(&prompt.content_encoding)
+        .bind(&prompt.content_type)
+        .bind(prompt.created_at)
+        .bind(Utc::now())
+        .bind(serde_json::to_value(normalize_tags(&prompt.tags)).context("Failed to serialize prompt tags to JSON")?)
+        .bind(prompt.template_engine.map(|engine| engine.name()))
+        .bind(prompt.forked_from_version.map(|v| i32::try_from(v).unwrap_or(i32::MAX)))
+        .execute(&mut *tx)
         .await
         .with_context(|| format!("Failed to save prompt with id '{}' to database", prompt.id))?;
+        if result.rows_affected() == 0 {
+            bail!("Prompt id '{}' is already used in another namespace", prompt.id);
+        }
+        tx.commit().await.context("Failed to commit prompt save")?;
         Ok(())
     }
 
+    async fn list_prompt_versions(&self, id: &Uuid) -> Result<Vec<u32>> {
+        let versions: Vec<i32> = sqlx::query_scalar(
+            r#"
+            SELECT version FROM prompts WHERE id = $1 AND namespace = $2
+            UNION
+            SELECT v.version FROM prompt_versions v JOIN prompts p ON p.id = v.id
+            WHERE v.id = $1 AND p.namespace = $2
+            ORDER BY version
+            "#,
+        )
+        .bind(id)
+        .bind(&self.namespace)
+        .fetch_all(&*self.pool)
+        .await
+        .with_context(|| format!("Failed to list versions of prompt with id '{}'", id))?;
+        Ok(versions.into_iter().map(|v| v.max(1) as u32).collect())
+    }
+
+    async fn get_prompt_version(&self, id: &Uuid, version: u32) -> Result<Option<Prompt>> {
+        match self.get_prompt(id).await? {
+            None => return Ok(None),
+            Some(current) if current.version == version => return Ok(Some(current)),
+            Some(_) => {}
+        }
+        let document: Option<serde_json::Value> = sqlx::query_scalar(
+            "SELECT document FROM prompt_versions WHERE id = $1 AND version = $2 AND namespace = $3",
+        )
+        .bind(id)
+        .bind(i32::try_from(version).unwrap_or(i32::MAX))
+        .bind(&self.namespace)
+        .fetch_optional(&*self.pool)
+        .await
+        .with_context(|| format!("Failed to fetch version {} of prompt with id '{}'", version, id))?;
+        document
+            .map(serde_json::from_value)
+            .transpose()
+            .with_context(|| format!("Failed to deserialize version {} of prompt with id '{}'", version, id))
+    }
+
     async fn delete_prompt(&self, id: &Uuid) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM prompts WHERE id = $1")
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+        let result = sqlx::query("DELETE FROM prompts WHERE id = $1 AND namespace = $2")
             .bind(id)
-            .execute(&*self.pool)
+            .bind(&self.namespace)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to delete prompt with id '{}' from database", id))?;
+        let deleted = result.rows_affected() > 0;
+        if deleted {
+            record_deletion(&mut tx, id, &self.namespace).await?;
+        }
+        tx.commit().await.context("Failed to commit prompt deletion")?;
+        Ok(deleted)
+    }
+
+    async fn delete_prompt_if_version(&self, id: &Uuid, expected_version: u32) -> Result<DeleteOutcome> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+        let result = sqlx::query("DELETE FROM prompts WHERE id = $1 AND version = $2 AND namespace = $3")
+            .bind(id)
+            .bind(i32::try_from(expected_version).unwrap_or(i32::MAX))
+            .bind(&self.namespace)
+            .execute(&mut *tx)
             .await
             .with_context(|| format!("Failed to delete prompt with id '{}' from database", id))?;
+
+        let outcome = if result.rows_affected() > 0 {
+            record_deletion(&mut tx, id, &self.namespace).await?;
+            DeleteOutcome::Deleted
+        } else {
+            let current: Option<i32> = sqlx::query_scalar("SELECT version FROM prompts WHERE id = $1 AND namespace = $2")
+                .bind(id)
+                .bind(&self.namespace)
+                .fetch_optional(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to fetch version of prompt with id '{}'", id))?;
+            match current {
+                Some(version) => DeleteOutcome::VersionMismatch { current: version.max(1) as u32 },
+                None => DeleteOutcome::NotFound,
+            }
+        };
+        tx.commit().await.context("Failed to commit prompt deletion")?;
+        Ok(outcome)
+    }
+
+    async fn is_deleted(&self, id: &Uuid) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM deleted_prompts WHERE id = $1 AND namespace = $2")
+            .bind(id)
+            .bind(&self.namespace)
+            .fetch_optional(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to check deletion state of prompt with id '{}'", id))?;
+        Ok(row.is_some())
+    }
+
+    async fn purge_prompt(&self, id: &Uuid) -> Result<bool> {
+        let removed = self.delete_prompt(id).await?;
+        sqlx::query("DELETE FROM deleted_prompts WHERE id = $1 AND namespace = $2")
+            .bind(id)
+            .bind(&self.namespace)
+            .execute(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to remove tombstone of prompt with id '{}'", id))?;
+        Ok(removed)
+    }
+
+    async fn save_attachment(&self, prompt_id: &Uuid, name: &str, data: &[u8]) -> Result<()> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO attachments (prompt_id, name, data)
+            SELECT $1, $2, $3 WHERE EXISTS (SELECT 1 FROM prompts WHERE id = $1 AND namespace = $4)
+            ON CONFLICT (prompt_id, name) DO UPDATE SET data = EXCLUDED.data, created_at = NOW();
+            "#,
+        )
+        .bind(prompt_id)
+        .bind(name)
+        .bind(data)
+        .bind(&self.namespace)
+        .execute(&*self.pool)
+        .await
+        .with_context(|| format!("Failed to save attachment '{}' of prompt '{}'", name, prompt_id))?;
+        if result.rows_affected() == 0 {
+            bail!("Prompt '{}' not found for attachment '{}'", prompt_id, name);
+        }
+        Ok(())
+    }
+
+    async fn get_attachment(&self, prompt_id: &Uuid, name: &str) -> Result<Option<Vec<u8>>> {
+        let row = sqlx::query(
+            "SELECT a.data FROM attachments a JOIN prompts p ON p.id = a.prompt_id WHERE a.prompt_id = $1 AND a.name = $2 AND p.namespace = $3",
+        )
+            .bind(prompt_id)
+            .bind(name)
+            .bind(&self.namespace)
+            .fetch_optional(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to load attachment '{}' of prompt '{}'", name, prompt_id))?;
+        Ok(row.map(|row| row.get("data")))
+    }
+
+    async fn list_attachments(&self, prompt_id: &Uuid) -> Result<Vec<AttachmentInfo>> {
+        let rows = sqlx::query(
+            r#"SELECT a.name, octet_length(a.data) AS size FROM attachments a JOIN prompts p ON p.id = a.prompt_id WHERE a.prompt_id = $1 AND p.namespace = $2 ORDER BY a.name COLLATE "C""#,
+        )
+            .bind(prompt_id)
+            .bind(&self.namespace)
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to list attachments of prompt '{}'", prompt_id))?;
+        Ok(rows
+            .into_iter()
+            .map(|row| AttachmentInfo {
+                name: row.get("name"),
+                size: row.get::<i32, _>("size").max(0) as u64,
+            })
+            .collect())
+    }
+
+    async fn list_categories(&self) -> Result<Vec<Category>> {
+        let rows = sqlx::query(r#"SELECT name, color, icon, description FROM categories WHERE namespace = $1 ORDER BY name COLLATE "C""#)
+            .bind(&self.namespace)
+            .fetch_all(&*self.pool)
+            .await
+            .context("Failed to list categories from database")?;
+        Ok(rows.iter().map(category_from_row).collect())
+    }
+
+    async fn get_category(&self, name: &str) -> Result<Option<Category>> {
+        let row = sqlx::query("SELECT name, color, icon, description FROM categories WHERE name = $1 AND namespace = $2")
+            .bind(name)
+            .bind(&self.namespace)
+            .fetch_optional(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to load category '{}' from database", name))?;
+        Ok(row.as_ref().map(category_from_row))
+    }
+
+    async fn save_category(&self, category: &Category) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO categories (name, color, icon, description, namespace)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (namespace, name) DO UPDATE SET
+                color = EXCLUDED.color,
+                icon = EXCLUDED.icon,
+                description = EXCLUDED.description;
+            "#,
+        )
+        .bind(&category.name)
+        .bind(&category.color)
+        .bind(&category.icon)
+        .bind(&category.description)
+        .bind(&self.namespace)
+        .execute(&*self.pool)
+        .await
+        .with_context(|| format!("Failed to save category '{}' to database", category.name))?;
+        Ok(())
+    }
+
+    async fn delete_category(&self, name: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM categories WHERE name = $1 AND namespace = $2")
+            .bind(name)
+            .bind(&self.namespace)
+            .execute(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to delete category '{}' from database", name))?;
         Ok(result.rows_affected() > 0)
     }
+
+    async fn list_collections(&self) -> Result<Vec<Collection>> {
+        let rows = sqlx::query(r#"SELECT id, name, prompt_ids FROM collections WHERE namespace = $1 ORDER BY name COLLATE "C", id"#)
+            .bind(&self.namespace)
+            .fetch_all(&*self.pool)
+            .await
+            .context("Failed to list collections from database")?;
+        Ok(rows.iter().map(collection_from_row).collect())
+    }
+
+    async fn get_collection(&self, id: &Uuid) -> Result<Option<Collection>> {
+        let row = sqlx::query("SELECT id, name, prompt_ids FROM collections WHERE id = $1 AND namespace = $2")
+            .bind(id)
+            .bind(&self.namespace)
+            .fetch_optional(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to load collection '{}' from database", id))?;
+        Ok(row.as_ref().map(collection_from_row))
+    }
+
+    async fn save_collection(&self, collection: &Collection) -> Result<()> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO collections (id, name, prompt_ids, namespace)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (id) DO UPDATE SET
+                name = EXCLUDED.name,
+                prompt_ids = EXCLUDED.prompt_ids
+            WHERE collections.namespace = EXCLUDED.namespace;
+            "#,
+        )
+        .bind(collection.id)
+        .bind(&collection.name)
+        .bind(&collection.prompt_ids)
+        .bind(&self.namespace)
+        .execute(&*self.pool)
+        .await
+        .with_context(|| format!("Failed to save collection '{}' to database", collection.id))?;
+        if result.rows_affected() == 0 {
+            bail!("Collection id '{}' is already used in another namespace", collection.id);
+        }
+        Ok(())
+    }
+
+    async fn delete_collection(&self, id: &Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM collections WHERE id = $1 AND namespace = $2")
+            .bind(id)
+            .bind(&self.namespace)
+            .execute(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to delete collection '{}' from database", id))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn run_maintenance(&self) -> Result<Vec<String>> {
+        let mut actions = Vec::new();
+        for table in ["prompts", "deleted_prompts", "attachments"] {
+            // VACUUM cannot run inside a transaction, so it goes straight to the pool
+            sqlx::query(&format!("VACUUM ANALYZE {}", table))
+                .execute(&*self.pool)
+                .await
+                .with_context(|| format!("Failed to vacuum table '{}'", table))?;
+            actions.push(format!("Vacuumed and analyzed table {}", table));
+        }
+        Ok(actions)
+    }
+}
+
+fn category_from_row(row: &sqlx::postgres::PgRow) -> Category {
+    Category {
+        name: row.get("name"),
+        color: row.get("color"),
+        icon: row.get("icon"),
+        description: row.get("description"),
+    }
+}
+
+fn collection_from_row(row: &sqlx::postgres::PgRow) -> Collection {
+    Collection {
+        id: row.get("id"),
+        name: row.get("name"),
+        prompt_ids: row.get("prompt_ids"),
+    }
+}
+
+/// Records a tombstone for a deleted prompt so reads can answer `410 Gone`.
+async fn record_deletion(tx: &mut Transaction<'_, Postgres>, id: &Uuid, namespace: &str) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO deleted_prompts (id, namespace) VALUES ($1, $2) ON CONFLICT (id) DO UPDATE SET deleted_at = NOW(), namespace = EXCLUDED.namespace",
+    )
+    .bind(id)
+    .bind(namespace)
+    .execute(&mut **tx)
+    .await
+    .with_context(|| format!("Failed to record deletion of prompt with id '{}'", id))?;
+    Ok(())
+}
+
+/// Default listing order shared by all backends. The "C" collation compares bytes,
+/// matching how the other backends compare names in Rust.
+/// Columns of the `prompts` table besides `id`: name, type as reported by
+/// `information_schema.columns`, and the definition used to add it to an older table.
+const PROMPT_COLUMNS: &[(&str, &str, &str)] = &[
+    ("name", "text", "TEXT NOT NULL DEFAULT ''"),
+    ("content", "text", "TEXT NOT NULL DEFAULT ''"),
+    ("category", "text", "TEXT"),
+    ("variables", "jsonb", "JSONB"),
+    ("description", "text", "TEXT"),
+    ("extends", "uuid", "UUID"),
+    ("locked", "boolean", "BOOLEAN NOT NULL DEFAULT FALSE"),
+    ("render_quota_per_hour", "integer", "INTEGER"),
+    ("metadata", "jsonb", "JSONB NOT NULL DEFAULT '{}'::jsonb"),
+    ("sections", "jsonb", "JSONB"),
+    ("version", "integer", "INTEGER NOT NULL DEFAULT 1"),
+    ("default_arguments", "jsonb", "JSONB NOT NULL DEFAULT '{}'::jsonb"),
+    ("forked_from", "uuid", "UUID"),
+    ("delimiters", "jsonb", "JSONB"),
+    ("owner", "text", "TEXT"),
+    ("references", "jsonb", "JSONB NOT NULL DEFAULT '{}'::jsonb"),
+    ("variable_metadata", "jsonb", "JSONB NOT NULL DEFAULT '{}'::jsonb"),
+    ("namespace", "text", "TEXT NOT NULL DEFAULT ''"),
+    ("tests", "jsonb", "JSONB NOT NULL DEFAULT '[]'::jsonb"),
+    ("content_encoding", "text", "TEXT"),
+    ("content_type", "text", "TEXT"),
+    ("created_at", "timestamp with time zone", "TIMESTAMPTZ NOT NULL DEFAULT NOW()"),
+    ("updated_at", "timestamp with time zone", "TIMESTAMPTZ NOT NULL DEFAULT NOW()"),
+    ("tags", "jsonb", "JSONB NOT NULL DEFAULT '[]'::jsonb"),
+    ("template_engine", "text", "TEXT"),
+    ("forked_from_version", "integer", "INTEGER"),
+];
+
+const ORDER_BY_NAME: &str = r#"ORDER BY name COLLATE "C", id"#;
+
+/// Builds a `LIKE` pattern matching `text` anywhere, escaping LIKE wildcards.
+fn like_pattern(text: &str) -> String {
+    format!("%{}%", escape_like(text))
+}
+
+/// Escapes `LIKE` wildcards (and the escape character itself) in `text`.
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
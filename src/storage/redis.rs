@@ -0,0 +1,278 @@
+use super::{PromptStorage, StorageConfig};
+use crate::models::prompt::Prompt;
+use crate::sorting::{self, SortOrder};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Number of prompts fetched per `MGET` when listing.
+const FETCH_BATCH: usize = 500;
+
+/// Redis storage implementation, for several server instances sharing one set of prompts.
+///
+/// Each prompt is stored as JSON under `prompt:{id}`, and the `prompts` set holds the
+/// ids of all prompts, so listings use `SMEMBERS` instead of a blocking `KEYS` scan.
+/// Earlier versions are kept in the `prompt_versions:{id}` hash and ids of deleted
+/// prompts in the `deleted_prompts` set. With a namespace every key is prefixed with
+/// `{namespace}:`.
+#[derive(Clone)]
+pub struct RedisStorage {
+    /// Multiplexed connection that reconnects on failure; cheap to clone per call.
+    connection: ConnectionManager,
+    /// Namespace every key is scoped to; empty for the default namespace.
+    namespace: String,
+}
+
+impl RedisStorage {
+    /// Connects to the server at the URL in `config`, e.g. `redis://localhost:6379/0`.
+    /// The pool settings don't apply: one multiplexed connection serves all requests.
+    pub async fn new(config: &StorageConfig) -> Result<Self> {
+        let client = redis::Client::open(config.url.as_str())
+            .with_context(|| format!("Invalid Redis URL: {}", config.url))?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .with_context(|| format!("Failed to connect to Redis: {}", config.url))?;
+        Ok(RedisStorage {
+            connection,
+            namespace: String::new(),
+        })
+    }
+
+    /// Scopes every key to `namespace`, like [`super::postgres::PostgresStorage::with_namespace`].
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    fn key(&self, name: &str) -> String {
+        if self.namespace.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}:{}", self.namespace, name)
+        }
+    }
+
+    fn prompt_key(&self, id: &Uuid) -> String {
+        self.key(&format!("prompt:{}", id))
+    }
+
+    fn versions_key(&self, id: &Uuid) -> String {
+        self.key(&format!("prompt_versions:{}", id))
+    }
+
+    /// Ids in the `prompts` set, skipping members that aren't UUIDs.
+    async fn prompt_ids(&self) -> Result<Vec<Uuid>> {
+        let members: Vec<String> = self
+            .connection
+            .clone()
+            .smembers(self.key("prompts"))
+            .await
+            .context("Failed to list prompt ids from Redis")?;
+        Ok(members
+            .into_iter()
+            .filter_map(|member| match Uuid::parse_str(&member) {
+                Ok(id) => Some(id),
+                Err(e) => {
+                    warn!(member = %member, error = %e, "Skipping invalid id in the Redis prompts set");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Fetches the stored JSON of each id, `None` where the key is missing.
+    async fn fetch_documents(&self, ids: &[Uuid]) -> Result<Vec<Option<String>>> {
+        let mut connection = self.connection.clone();
+        let mut documents = Vec::with_capacity(ids.len());
+        for batch in ids.chunks(FETCH_BATCH) {
+            let keys: Vec<String> = batch.iter().map(|id| self.prompt_key(id)).collect();
+            let fetched: Vec<Option<String>> = redis::cmd("MGET")
+                .arg(&keys)
+                .query_async(&mut connection)
+                .await
+                .context("Failed to fetch prompts from Redis")?;
+            documents.extend(fetched);
+        }
+        Ok(documents)
+    }
+}
+
+/// Decodes a stored prompt. A value that can't be decoded is logged and skipped so a
+/// single bad entry never fails a whole listing.
+fn decode_prompt(id: &Uuid, document: &str) -> Option<Prompt> {
+    match serde_json::from_str(document) {
+        Ok(prompt) => Some(prompt),
+        Err(e) => {
+            warn!(prompt_id = %id, error = %e, "Skipping Redis prompt that failed to deserialize");
+            None
+        }
+    }
+}
+
+#[async_trait]
+impl PromptStorage for RedisStorage {
+    fn backend_name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<Prompt>> {
+        let ids = self.prompt_ids().await?;
+        let documents = self.fetch_documents(&ids).await?;
+        // Ids whose key is gone were deleted concurrently; run_maintenance drops them from the set
+        let mut prompts: Vec<Prompt> = ids
+            .iter()
+            .zip(documents)
+            .filter_map(|(id, document)| decode_prompt(id, &document?))
+            .collect();
+        sorting::sort_items(&mut prompts, SortOrder::default());
+        Ok(prompts)
+    }
+
+    async fn count_prompts(&self) -> Result<usize> {
+        self.connection
+            .clone()
+            .scard(self.key("prompts"))
+            .await
+            .context("Failed to count prompts in Redis")
+    }
+
+    async fn get_prompt(&self, id: &Uuid) -> Result<Option<Prompt>> {
+        let document: Option<String> = self
+            .connection
+            .clone()
+            .get(self.prompt_key(id))
+            .await
+            .with_context(|| format!("Failed to fetch prompt with id '{}' from Redis", id))?;
+        document
+            .map(|document| serde_json::from_str(&document))
+            .transpose()
+            .with_context(|| format!("Failed to deserialize prompt with id '{}'", id))
+    }
+
+    async fn save_prompt(&self, prompt: &Prompt) -> Result<()> {
+        let mut connection = self.connection.clone();
+        let key = self.prompt_key(&prompt.id);
+        let previous: Option<String> = connection
+            .get(&key)
+            .await
+            .with_context(|| format!("Failed to fetch prompt with id '{}' from Redis", prompt.id))?;
+        let previous = previous.and_then(|document| Some((decode_prompt(&prompt.id, &document)?, document)));
+
+        let mut stored = prompt.clone();
+        stored.stamp_saved(previous.as_ref().map(|(p, _)| p.created_at));
+        let document = serde_json::to_string(&stored)
+            .with_context(|| format!("Failed to serialize prompt ID {}", prompt.id))?;
+
+        // Written in one MULTI/EXEC so other instances never see the key without its set entry
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .set(&key, document)
+            .ignore()
+            .sadd(self.key("prompts"), prompt.id.to_string())
+            .ignore();
+        // Keep the stored revision when an edit replaces it with another version
+        if let Some((previous, previous_document)) = previous.filter(|(p, _)| p.version != prompt.version) {
+            pipe.hset(self.versions_key(&prompt.id), previous.version, previous_document).ignore();
+        }
+        pipe.query_async::<_, ()>(&mut connection)
+            .await
+            .with_context(|| format!("Failed to save prompt with id '{}' to Redis", prompt.id))
+    }
+
+    async fn delete_prompt(&self, id: &Uuid) -> Result<bool> {
+        let mut connection = self.connection.clone();
+        let (removed, _, _): (u64, u64, u64) = redis::pipe()
+            .atomic()
+            .del(self.prompt_key(id))
+            .srem(self.key("prompts"), id.to_string())
+            .del(self.versions_key(id))
+            .query_async(&mut connection)
+            .await
+            .with_context(|| format!("Failed to delete prompt with id '{}' from Redis", id))?;
+        let deleted = removed > 0;
+        if deleted {
+            connection
+                .sadd::<_, _, ()>(self.key("deleted_prompts"), id.to_string())
+                .await
+                .with_context(|| format!("Failed to record deletion of prompt with id '{}'", id))?;
+        }
+        Ok(deleted)
+    }
+
+    async fn is_deleted(&self, id: &Uuid) -> Result<bool> {
+        self.connection
+            .clone()
+            .sismember(self.key("deleted_prompts"), id.to_string())
+            .await
+            .with_context(|| format!("Failed to check deletion state of prompt with id '{}'", id))
+    }
+
+    async fn purge_prompt(&self, id: &Uuid) -> Result<bool> {
+        let removed = self.delete_prompt(id).await?;
+        self.connection
+            .clone()
+            .srem::<_, _, ()>(self.key("deleted_prompts"), id.to_string())
+            .await
+            .with_context(|| format!("Failed to remove tombstone of prompt with id '{}'", id))?;
+        Ok(removed)
+    }
+
+    async fn list_prompt_versions(&self, id: &Uuid) -> Result<Vec<u32>> {
+        let Some(current) = self.get_prompt(id).await? else {
+            return Ok(Vec::new());
+        };
+        let mut versions: Vec<u32> = self
+            .connection
+            .clone()
+            .hkeys(self.versions_key(id))
+            .await
+            .with_context(|| format!("Failed to list versions of prompt with id '{}'", id))?;
+        if !versions.contains(&current.version) {
+            versions.push(current.version);
+        }
+        versions.sort_unstable();
+        Ok(versions)
+    }
+
+    async fn get_prompt_version(&self, id: &Uuid, version: u32) -> Result<Option<Prompt>> {
+        match self.get_prompt(id).await? {
+            Some(current) if current.version == version => Ok(Some(current)),
+            Some(_) => {
+                let document: Option<String> = self
+                    .connection
+                    .clone()
+                    .hget(self.versions_key(id), version)
+                    .await
+                    .with_context(|| format!("Failed to fetch version {} of prompt with id '{}'", version, id))?;
+                document
+                    .map(|document| serde_json::from_str(&document))
+                    .transpose()
+                    .with_context(|| format!("Failed to deserialize version {} of prompt with id '{}'", version, id))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn run_maintenance(&self) -> Result<Vec<String>> {
+        let ids = self.prompt_ids().await?;
+        let documents = self.fetch_documents(&ids).await?;
+        let stale: Vec<String> = ids
+            .iter()
+            .zip(&documents)
+            .filter(|(_, document)| document.is_none())
+            .map(|(id, _)| id.to_string())
+            .collect();
+        if stale.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.connection
+            .clone()
+            .srem::<_, _, ()>(self.key("prompts"), &stale)
+            .await
+            .context("Failed to remove stale ids from the Redis prompts set")?;
+        Ok(vec![format!("Removed {} stale id(s) from the prompts set", stale.len())])
+    }
+}
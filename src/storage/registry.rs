@@ -0,0 +1,215 @@
+use super::filesystem::FileSystemStorage;
+use super::memory::InMemoryStorage;
+use super::postgres::PostgresStorage;
+use super::redis::RedisStorage;
+use super::sqlite::SqliteStorage;
+use super::{PromptStorage, StorageConfig};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock, RwLock};
+use tracing::info;
+
+/// Settings passed to a [`StorageFactory`] when the server starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageOptions {
+    /// Directory for file-based backends.
+    pub prompt_dir: PathBuf,
+    /// Connection settings for networked backends; `None` when no URL was given.
+    pub connection: Option<StorageConfig>,
+    /// Sync written files to disk before acknowledging writes, for file-based backends.
+    pub fsync: bool,
+    /// Scopes every operation to this namespace, so several environments can share
+    /// one backing store. `None` uses the default namespace.
+    pub namespace: Option<String>,
+}
+
+/// Mistakes in the storage settings, as opposed to failures of a backend itself.
+/// Returned inside the `anyhow::Error` of [`create_storage`], so callers can
+/// `downcast_ref` to tell them apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageSetupError {
+    /// No backend is registered under `name`.
+    Unsupported { name: String, available: Vec<String> },
+    /// The backend needs `--db-url`, but none was given.
+    MissingConnection { backend: String },
+}
+
+impl fmt::Display for StorageSetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageSetupError::Unsupported { name, available } => write!(
+                f,
+                "Unsupported storage type '{}'; expected one of: {}",
+                name,
+                available.join(", ")
+            ),
+            StorageSetupError::MissingConnection { backend } => {
+                write!(f, "--db-url is required for {} storage", backend)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageSetupError {}
+
+impl StorageOptions {
+    /// Returns the connection settings, or an error naming the backend that needs them.
+    pub fn require_connection(&self, backend: &str) -> Result<&StorageConfig> {
+        self.connection.as_ref().ok_or_else(|| {
+            StorageSetupError::MissingConnection {
+                backend: backend.to_string(),
+            }
+            .into()
+        })
+    }
+
+    /// Fails unless the namespace, if any, is 1 to 64 ASCII letters, digits, `-` or `_`,
+    /// so it is safe to use as a directory name or key prefix.
+    pub fn validate_namespace(&self) -> Result<()> {
+        match &self.namespace {
+            Some(namespace)
+                if namespace.is_empty()
+                    || namespace.len() > 64
+                    || !namespace.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+            {
+                Err(anyhow!(
+                    "Invalid namespace '{}': use 1 to 64 letters, digits, '-' or '_'",
+                    namespace
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Directory for file-based backends: `prompt_dir`, or the namespace's
+    /// subdirectory of it when a namespace is set.
+    pub fn storage_dir(&self) -> PathBuf {
+        match &self.namespace {
+            Some(namespace) => self.prompt_dir.join(namespace),
+            None => self.prompt_dir.clone(),
+        }
+    }
+}
+
+/// Creates a storage backend. Implement this and call [`register_storage`] to make
+/// a custom backend selectable with `--storage <name>`.
+#[async_trait]
+pub trait StorageFactory: Send + Sync + 'static {
+    async fn create(&self, options: &StorageOptions) -> Result<Arc<dyn PromptStorage>>;
+}
+
+type Registry = RwLock<BTreeMap<String, Arc<dyn StorageFactory>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut factories: BTreeMap<String, Arc<dyn StorageFactory>> = BTreeMap::new();
+        factories.insert("filesystem".to_string(), Arc::new(FileSystemFactory));
+        factories.insert("memory".to_string(), Arc::new(MemoryFactory));
+        factories.insert("postgres".to_string(), Arc::new(PostgresFactory));
+        factories.insert("redis".to_string(), Arc::new(RedisFactory));
+        factories.insert("sqlite".to_string(), Arc::new(SqliteFactory));
+        RwLock::new(factories)
+    })
+}
+
+/// Registers a storage backend under `name`, replacing any existing registration.
+pub fn register_storage(name: impl Into<String>, factory: impl StorageFactory) {
+    registry()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.into(), Arc::new(factory));
+}
+
+/// Names of all registered storage backends, in sorted order.
+pub fn registered_storage_names() -> Vec<String> {
+    registry()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Creates the storage backend registered under `name`.
+pub async fn create_storage(name: &str, options: &StorageOptions) -> Result<Arc<dyn PromptStorage>> {
+    options.validate_namespace()?;
+    let factory = registry()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned();
+    // Listing the names takes the read lock again, so only once the guard above is dropped
+    let Some(factory) = factory else {
+        return Err(StorageSetupError::Unsupported {
+            name: name.to_string(),
+            available: registered_storage_names(),
+        }
+        .into());
+    };
+    factory.create(options).await
+}
+
+struct FileSystemFactory;
+
+#[async_trait]
+impl StorageFactory for FileSystemFactory {
+    async fn create(&self, options: &StorageOptions) -> Result<Arc<dyn PromptStorage>> {
+        let dir = options.storage_dir();
+        info!(path = %dir.display(), fsync = options.fsync, "Using filesystem storage");
+        Ok(Arc::new(FileSystemStorage::new(&dir).with_fsync(options.fsync)))
+    }
+}
+
+struct MemoryFactory;
+
+#[async_trait]
+impl StorageFactory for MemoryFactory {
+    async fn create(&self, _options: &StorageOptions) -> Result<Arc<dyn PromptStorage>> {
+        info!("Using in-memory storage; prompts are lost when the server stops");
+        Ok(Arc::new(InMemoryStorage::new()))
+    }
+}
+
+struct PostgresFactory;
+
+#[async_trait]
+impl StorageFactory for PostgresFactory {
+    async fn create(&self, options: &StorageOptions) -> Result<Arc<dyn PromptStorage>> {
+        let config = options.require_connection("postgres")?;
+        info!(url = %config.url, namespace = options.namespace.as_deref().unwrap_or_default(), "Using PostgreSQL storage");
+        let storage = PostgresStorage::new(config).await?.with_namespace(options.namespace.clone().unwrap_or_default());
+        storage.init_schema().await?;
+        info!("Database schema initialized (if not exists)");
+        Ok(Arc::new(storage))
+    }
+}
+
+struct RedisFactory;
+
+#[async_trait]
+impl StorageFactory for RedisFactory {
+    async fn create(&self, options: &StorageOptions) -> Result<Arc<dyn PromptStorage>> {
+        let config = options.require_connection("redis")?;
+        info!(url = %config.url, namespace = options.namespace.as_deref().unwrap_or_default(), "Using Redis storage");
+        let storage = RedisStorage::new(config).await?.with_namespace(options.namespace.clone().unwrap_or_default());
+        Ok(Arc::new(storage))
+    }
+}
+
+struct SqliteFactory;
+
+#[async_trait]
+impl StorageFactory for SqliteFactory {
+    async fn create(&self, options: &StorageOptions) -> Result<Arc<dyn PromptStorage>> {
+        let config = options.require_connection("sqlite")?;
+        info!(url = %config.url, namespace = options.namespace.as_deref().unwrap_or_default(), "Using SQLite storage");
+        let storage = SqliteStorage::new(config).await?.with_namespace(options.namespace.clone().unwrap_or_default());
+        storage.init_schema().await?;
+        info!("Database schema initialized (if not exists)");
+        Ok(Arc::new(storage))
+    }
+}
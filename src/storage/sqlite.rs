@@ -0,0 +1,441 @@
+use super::{DeleteOutcome, PromptStorage, StorageConfig};
+use crate::models::prompt::{Prompt, PromptSummary};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::{Row, Sqlite, Transaction};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Default listing order shared by all backends. SQLite's default BINARY collation
+/// compares bytes, and ids are stored as lowercase hyphenated text, which sorts
+/// like the UUIDs themselves.
+const ORDER_BY_NAME: &str = "ORDER BY name, id";
+
+/// SQLite storage implementation, for running the server on a single database file.
+///
+/// The queryable fields have columns of their own, as in the PostgreSQL backend;
+/// SQLite has no JSONB, so `variables` is JSON text and the complete prompt is kept
+/// as JSON in `document`, from which prompts are read back.
+#[derive(Clone)]
+pub struct SqliteStorage {
+    pool: Arc<SqlitePool>,
+    /// Namespace every query is scoped to; empty for the default namespace.
+    namespace: String,
+}
+
+impl SqliteStorage {
+    /// Opens the database at the URL in `config`, e.g. `sqlite://prompts.db`,
+    /// creating the file if it doesn't exist.
+    pub async fn new(config: &StorageConfig) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(&config.url)
+            .with_context(|| format!("Invalid SQLite URL: {}", config.url))?
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(config.max_connections)
+            .acquire_timeout(config.acquire_timeout)
+            .idle_timeout(config.idle_timeout)
+            .connect_with(options)
+            .await
+            .with_context(|| format!("Failed to open SQLite database: {}", config.url))?;
+        Ok(SqliteStorage {
+            pool: Arc::new(pool),
+            namespace: String::new(),
+        })
+    }
+
+    /// Scopes every query to `namespace`, like [`super::postgres::PostgresStorage::with_namespace`].
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// Initializes the database schema if it doesn't exist.
+    pub async fn init_schema(&self) -> Result<()> {
+        let statements = [
+            r#"
+            CREATE TABLE IF NOT EXISTS prompts (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                content TEXT NOT NULL,
+                category TEXT,
+                variables TEXT,
+                description TEXT,
+                version INTEGER NOT NULL DEFAULT 1,
+                namespace TEXT NOT NULL DEFAULT '',
+                document TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS prompts_namespace_idx ON prompts (namespace)",
+            r#"
+            CREATE TABLE IF NOT EXISTS prompt_versions (
+                id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                namespace TEXT NOT NULL DEFAULT '',
+                document TEXT NOT NULL,
+                archived_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (id, version)
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS deleted_prompts (
+                id TEXT PRIMARY KEY,
+                deleted_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                namespace TEXT NOT NULL DEFAULT ''
+            )
+            "#,
+        ];
+        for statement in statements {
+            sqlx::query(statement)
+                .execute(&*self.pool)
+                .await
+                .context("Failed to initialize database schema")?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes prompt rows from their `document` column. A row that can't be decoded is
+/// logged and skipped so a single bad row never fails a whole listing.
+fn decode_prompt_rows(rows: &[SqliteRow]) -> Vec<Prompt> {
+    rows.iter().filter_map(decode_prompt_row).collect()
+}
+
+fn decode_prompt_row(row: &SqliteRow) -> Option<Prompt> {
+    let id: Option<String> = row.try_get("id").ok();
+    match row.try_get::<String, _>("document").map(|document| serde_json::from_str(&document)) {
+        Ok(Ok(prompt)) => Some(prompt),
+        Ok(Err(e)) => {
+            warn!(prompt_id = ?id, error = %e, "Skipping prompt row that failed to deserialize");
+            None
+        }
+        Err(e) => {
+            warn!(prompt_id = ?id, error = %e, "Skipping prompt row that failed to decode");
+            None
+        }
+    }
+}
+
+#[async_trait]
+impl PromptStorage for SqliteStorage {
+    fn backend_name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<Prompt>> {
+        let rows = sqlx::query(&format!("SELECT id, document FROM prompts WHERE namespace = ? {}", ORDER_BY_NAME))
+            .bind(&self.namespace)
+            .fetch_all(&*self.pool)
+            .await
+            .context("Failed to fetch prompts from database")?;
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn list_prompt_summaries(&self) -> Result<Vec<PromptSummary>> {
+        let rows: Vec<(String, String, Option<String>, i64)> =
+            sqlx::query_as(&format!("SELECT id, name, category, version FROM prompts WHERE namespace = ? {}", ORDER_BY_NAME))
+                .bind(&self.namespace)
+                .fetch_all(&*self.pool)
+                .await
+                .context("Failed to fetch prompt summaries from database")?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, name, category, version)| match Uuid::parse_str(&id) {
+                Ok(id) => Some(PromptSummary {
+                    id,
+                    name,
+                    category,
+                    version: version.clamp(1, u32::MAX as i64) as u32,
+                }),
+                Err(e) => {
+                    warn!(prompt_id = %id, error = %e, "Skipping prompt row with an invalid id");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    async fn count_prompts(&self) -> Result<usize> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM prompts WHERE namespace = ?")
+            .bind(&self.namespace)
+            .fetch_one(&*self.pool)
+            .await
+            .context("Failed to count prompts in database")?;
+        Ok(count as usize)
+    }
+
+    async fn list_prompts_by_category(&self, category: &str) -> Result<Vec<Prompt>> {
+        let rows = sqlx::query(&format!(
+            "SELECT id, document FROM prompts WHERE category = ? AND namespace = ? {}",
+            ORDER_BY_NAME
+        ))
+        .bind(category)
+        .bind(&self.namespace)
+        .fetch_all(&*self.pool)
+        .await
+        .context("Failed to list prompts by category from database")?;
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn list_prompts_by_tag(&self, tag: &str) -> Result<Vec<Prompt>> {
+        let rows = sqlx::query(&format!(
+            "SELECT id, document FROM prompts WHERE namespace = ? AND EXISTS (SELECT 1 FROM json_each(prompts.document, '$.tags') WHERE value = ?) {}",
+            ORDER_BY_NAME
+        ))
+        .bind(&self.namespace)
+        .bind(tag.trim().to_lowercase())
+        .fetch_all(&*self.pool)
+        .await
+        .context("Failed to list prompts by tag from database")?;
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn list_prompts_paginated(&self, offset: usize, limit: usize) -> Result<Vec<Prompt>> {
+        let bound = |n: usize| i64::try_from(n).unwrap_or(i64::MAX);
+        let rows = sqlx::query(&format!(
+            "SELECT id, document FROM prompts WHERE namespace = ? {} LIMIT ? OFFSET ?",
+            ORDER_BY_NAME
+        ))
+        .bind(&self.namespace)
+        .bind(bound(limit))
+        .bind(bound(offset))
+        .fetch_all(&*self.pool)
+        .await
+        .context("Failed to fetch page of prompts from database")?;
+        Ok(decode_prompt_rows(&rows))
+    }
+
+    async fn get_prompt(&self, id: &Uuid) -> Result<Option<Prompt>> {
+        let row = sqlx::query("SELECT id, document FROM prompts WHERE id = ? AND namespace = ?")
+            .bind(id.to_string())
+            .bind(&self.namespace)
+            .fetch_optional(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to fetch prompt with id '{}' from database", id))?;
+        match row {
+            Some(row) => {
+                let document: String = row.try_get("document")?;
+                serde_json::from_str(&document)
+                    .map(Some)
+                    .with_context(|| format!("Failed to deserialize prompt with id '{}'", id))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn save_prompt(&self, prompt: &Prompt) -> Result<()> {
+        // created_at is taken from the stored document in the upsert below
+        let mut prompt = prompt.clone();
+        prompt.stamp_saved(None);
+        let prompt = &prompt;
+        let variables_json = prompt
+            .variables
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .context("Failed to serialize prompt variables to JSON")?;
+        let document = serde_json::to_string(prompt)
+            .with_context(|| format!("Failed to serialize prompt ID {}", prompt.id))?;
+
+        let description = prompt.category.clone();
+
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+        // Keep the stored revision when an edit replaces it with another version
+        sqlx::query(
+            r#"
+            INSERT INTO prompt_versions (id, version, namespace, document)
+            SELECT id, version, namespace, document FROM prompts WHERE id = ? AND namespace = ? AND version <> ?
+            ON CONFLICT (id, version) DO UPDATE SET document = excluded.document, archived_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(prompt.id.to_string())
+        .bind(&self.namespace)
+        .bind(i64::from(prompt.version))
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to archive previous version of prompt with id '{}'", prompt.id))?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO prompts (id, name, content, category, variables, description, version, namespace, document, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (id) DO UPDATE SET
+                name = excluded.name,
+                content = excluded.content,
+                category = excluded.category,
+                variables = excluded.variables,
+                description = excluded.description,
+                version = excluded.version,
+                document = json_set(
+                    excluded.document,
+                    '$.created_at',
+                    COALESCE(json_extract(prompts.document, '$.created_at'), json_extract(excluded.document, '$.created_at'))
+                ),
+                updated_at = excluded.updated_at
+            WHERE prompts.namespace = excluded.namespace
+            "#,
+        )
+        .bind(prompt.id.to_string())
+        .bind(&prompt.name)
+        .bind(&prompt.content)
+        .bind(&prompt.category)
+        .bind(&variables_json)
+        .bind(&description)
+        .bind(i64::from(prompt.version))
+        .bind(&self.namespace)
+        .bind(&document)
+        .bind(prompt.created_at.to_rfc3339())
+        .bind(prompt.updated_at.to_rfc3339())
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to save prompt with id '{}' to database", prompt.id))?;
+        if result.rows_affected() == 0 {
+            bail!("Prompt id '{}' is already used in another namespace", prompt.id);
+        }
+        tx.commit().await.context("Failed to commit prompt save")?;
+        Ok(())
+    }
+
+    async fn delete_prompt(&self, id: &Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+        let result = sqlx::query("DELETE FROM prompts WHERE id = ? AND namespace = ?")
+            .bind(id.to_string())
+            .bind(&self.namespace)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to delete prompt with id '{}' from database", id))?;
+        let deleted = result.rows_affected() > 0;
+        if deleted {
+            record_deletion(&mut tx, id, &self.namespace).await?;
+        }
+        tx.commit().await.context("Failed to commit prompt deletion")?;
+        Ok(deleted)
+    }
+
+    async fn delete_prompt_if_version(&self, id: &Uuid, expected_version: u32) -> Result<DeleteOutcome> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+        let result = sqlx::query("DELETE FROM prompts WHERE id = ? AND version = ? AND namespace = ?")
+            .bind(id.to_string())
+            .bind(i64::from(expected_version))
+            .bind(&self.namespace)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to delete prompt with id '{}' from database", id))?;
+
+        let outcome = if result.rows_affected() > 0 {
+            record_deletion(&mut tx, id, &self.namespace).await?;
+            DeleteOutcome::Deleted
+        } else {
+            let current: Option<i64> = sqlx::query_scalar("SELECT version FROM prompts WHERE id = ? AND namespace = ?")
+                .bind(id.to_string())
+                .bind(&self.namespace)
+                .fetch_optional(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to fetch version of prompt with id '{}'", id))?;
+            match current {
+                Some(version) => DeleteOutcome::VersionMismatch { current: version.clamp(1, u32::MAX as i64) as u32 },
+                None => DeleteOutcome::NotFound,
+            }
+        };
+        tx.commit().await.context("Failed to commit prompt deletion")?;
+        Ok(outcome)
+    }
+
+    async fn is_deleted(&self, id: &Uuid) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM deleted_prompts WHERE id = ? AND namespace = ?")
+            .bind(id.to_string())
+            .bind(&self.namespace)
+            .fetch_optional(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to check deletion state of prompt with id '{}'", id))?;
+        Ok(row.is_some())
+    }
+
+    async fn purge_prompt(&self, id: &Uuid) -> Result<bool> {
+        let removed = self.delete_prompt(id).await?;
+        sqlx::query("DELETE FROM deleted_prompts WHERE id = ? AND namespace = ?")
+            .bind(id.to_string())
+            .bind(&self.namespace)
+            .execute(&*self.pool)
+            .await
+            .with_context(|| format!("Failed to remove tombstone of prompt with id '{}'", id))?;
+        Ok(removed)
+    }
+
+    async fn list_prompt_versions(&self, id: &Uuid) -> Result<Vec<u32>> {
+        let versions: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT version FROM prompts WHERE id = ?1 AND namespace = ?2
+            UNION
+            SELECT version FROM prompt_versions WHERE id = ?1 AND namespace = ?2
+                AND EXISTS (SELECT 1 FROM prompts WHERE id = ?1 AND namespace = ?2)
+            ORDER BY version
+            "#,
+        )
+        .bind(id.to_string())
+        .bind(&self.namespace)
+        .fetch_all(&*self.pool)
+        .await
+        .with_context(|| format!("Failed to list versions of prompt with id '{}'", id))?;
+        Ok(versions.into_iter().map(|v| v.clamp(1, u32::MAX as i64) as u32).collect())
+    }
+
+    async fn get_prompt_version(&self, id: &Uuid, version: u32) -> Result<Option<Prompt>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, document FROM prompts WHERE id = ?1 AND namespace = ?2 AND version = ?3
+            UNION ALL
+            SELECT id, document FROM prompt_versions WHERE id = ?1 AND namespace = ?2 AND version = ?3
+                AND EXISTS (SELECT 1 FROM prompts WHERE id = ?1 AND namespace = ?2)
+            LIMIT 1
+            "#,
+        )
+        .bind(id.to_string())
+        .bind(&self.namespace)
+        .bind(i64::from(version))
+        .fetch_optional(&*self.pool)
+        .await
+        .with_context(|| format!("Failed to fetch version {} of prompt with id '{}'", version, id))?;
+        match row {
+            Some(row) => {
+                let document: String = row.try_get("document")?;
+                serde_json::from_str(&document)
+                    .map(Some)
+                    .with_context(|| format!("Failed to deserialize version {} of prompt with id '{}'", version, id))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn run_maintenance(&self) -> Result<Vec<String>> {
+        sqlx::query("VACUUM")
+            .execute(&*self.pool)
+            .await
+            .context("Failed to vacuum database")?;
+        Ok(vec!["Vacuumed database".to_string()])
+    }
+}
+
+/// Records a tombstone for a deleted prompt so reads can answer `410 Gone`, and
+/// drops its version history.
+async fn record_deletion(tx: &mut Transaction<'_, Sqlite>, id: &Uuid, namespace: &str) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO deleted_prompts (id, namespace) VALUES (?, ?) ON CONFLICT (id) DO UPDATE SET deleted_at = CURRENT_TIMESTAMP, namespace = excluded.namespace",
+    )
+    .bind(id.to_string())
+    .bind(namespace)
+    .execute(&mut **tx)
+    .await
+    .with_context(|| format!("Failed to record deletion of prompt with id '{}'", id))?;
+    sqlx::query("DELETE FROM prompt_versions WHERE id = ? AND namespace = ?")
+        .bind(id.to_string())
+        .bind(namespace)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to delete version history of prompt with id '{}'", id))?;
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use anyhow::{Context, Result};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{self, Tracer};
+use opentelemetry_sdk::{runtime, Resource};
+
+/// `service.name` reported with every exported span.
+pub const SERVICE_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Builds a tracer that exports spans in batches to the OTLP/gRPC collector at
+/// `endpoint`, e.g. `http://localhost:4317`. Must be called inside a Tokio runtime.
+pub fn otlp_tracer(endpoint: &str) -> Result<Tracer> {
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+        .with_trace_config(trace::config().with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            SERVICE_NAME,
+        )])))
+        .install_batch(runtime::Tokio)
+        .with_context(|| format!("Failed to set up OTLP trace export to {}", endpoint))
+}
+
+/// Flushes spans that haven't been exported yet and stops the exporter.
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
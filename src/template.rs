@@ -0,0 +1,749 @@
+use crate::models::prompt::{Delimiters, Prompt};
+use crate::references::PROMPTS_KEY;
+use crate::snippets;
+use crate::storage::PromptStorage;
+use crate::variables;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tera::{Context, Tera};
+use tokio::sync::Semaphore;
+
+/// Template engines that prompt content can be written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateEngine {
+    Tera,
+    Handlebars,
+    /// No templating: content is returned as written, whatever the arguments.
+    None,
+}
+
+impl TemplateEngine {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TemplateEngine::Tera => "tera",
+            TemplateEngine::Handlebars => "handlebars",
+            TemplateEngine::None => "none",
+        }
+    }
+}
+
+/// How the server picks the template engine for rendering a prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineMode {
+    /// Render every prompt with Tera.
+    #[default]
+    Tera,
+    /// Render every prompt with Handlebars.
+    Handlebars,
+    /// Pick the engine per prompt with [`detect_engine`], using Tera when unsure.
+    Auto,
+}
+
+impl EngineMode {
+    /// The engine to render `prompt` with: its own `template_engine` if set, otherwise
+    /// the one this mode picks. Prompts that rely on Tera-only features (inheritance,
+    /// custom delimiters) use Tera unless they set an engine.
+    pub fn engine_for(&self, prompt: &Prompt) -> TemplateEngine {
+        if let Some(engine) = prompt.template_engine {
+            return engine;
+        }
+        if prompt.extends.is_some() || prompt.delimiters.is_some() {
+            return TemplateEngine::Tera;
+        }
+        match self {
+            EngineMode::Tera => TemplateEngine::Tera,
+            EngineMode::Handlebars => TemplateEngine::Handlebars,
+            EngineMode::Auto => detect_engine(&prompt.content).unwrap_or(TemplateEngine::Tera),
+        }
+    }
+}
+
+impl FromStr for EngineMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tera" => Ok(Self::Tera),
+            "handlebars" => Ok(Self::Handlebars),
+            "auto" => Ok(Self::Auto),
+            other => Err(format!("Unknown template engine '{}'; expected tera, handlebars or auto", other)),
+        }
+    }
+}
+
+impl fmt::Display for EngineMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Tera => "tera",
+            Self::Handlebars => "handlebars",
+            Self::Auto => "auto",
+        })
+    }
+}
+
+/// Guesses the engine a template was written for from syntax only one engine uses:
+/// `{% %}` statements or `{# #}` comments for Tera, `{{#block}}`, `{{/block}}`,
+/// `{{> partial}}`, `{{! comment}}`, `{{else}}` or `{{~` for Handlebars.
+///
+/// Returns `None` when the content has markers of both engines or of neither.
+pub fn detect_engine(content: &str) -> Option<TemplateEngine> {
+    let handlebars = ["{{#", "{{/", "{{>", "{{!", "{{else", "{{~"]
+        .iter()
+        .any(|marker| content.contains(marker));
+    // `{{#` also contains `{#`, so only a `{#` not preceded by `{` is a Tera comment
+    let tera = content.contains("{%")
+        || content.match_indices("{#").any(|(i, _)| !content[..i].ends_with('{'));
+    match (tera, handlebars) {
+        (true, false) => Some(TemplateEngine::Tera),
+        (false, true) => Some(TemplateEngine::Handlebars),
+        _ => None,
+    }
+}
+
+/// Filters built into Tera, available to every prompt.
+pub const TERA_BUILTIN_FILTERS: &[&str] = &[
+    "lower", "upper", "wordcount", "capitalize", "replace", "addslashes", "slugify", "title",
+    "trim", "trim_start", "trim_end", "trim_start_matches", "trim_end_matches", "truncate",
+    "linebreaksbr", "spaceless", "indent", "striptags", "first", "last", "nth", "join", "length",
+    "reverse", "sort", "unique", "slice", "group_by", "filter", "map", "concat", "urlencode",
+    "urlencode_strict", "abs", "pluralize", "round", "filesizeformat", "date", "escape",
+    "escape_xml", "safe", "get", "split", "int", "float", "json_encode", "as_str", "default",
+];
+
+/// Describes the templating the server applies, so clients can validate templates the same way.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TemplateCapabilities {
+    /// Engine used to render prompts.
+    pub engine: TemplateEngine,
+    /// Engines that `check-engine` can validate against.
+    pub supported_engines: Vec<TemplateEngine>,
+    /// Filters built into the engine.
+    pub builtin_filters: Vec<&'static str>,
+    /// Filters and functions registered by this server on top of the built-ins.
+    pub custom_filters: Vec<&'static str>,
+    /// Server-side template features (inheritance, sections, partial rendering, ...).
+    pub features: Vec<&'static str>,
+}
+
+impl TemplateCapabilities {
+    pub fn for_engine(engine: TemplateEngine) -> Self {
+        Self {
+            engine,
+            supported_engines: vec![TemplateEngine::Tera, TemplateEngine::Handlebars],
+            builtin_filters: match engine {
+                TemplateEngine::Tera => TERA_BUILTIN_FILTERS.to_vec(),
+                TemplateEngine::Handlebars | TemplateEngine::None => Vec::new(),
+            },
+            custom_filters: match engine {
+                TemplateEngine::Tera => vec![PROMPT_FUNCTION],
+                TemplateEngine::Handlebars | TemplateEngine::None => Vec::new(),
+            },
+            features: vec!["extends", "sections", "partial", "globals", "snippets"],
+        }
+    }
+}
+
+/// Checks that `content` compiles as a template for `engine`, without rendering it.
+///
+/// Returns the engine's syntax error message on failure.
+pub fn check_syntax(engine: TemplateEngine, content: &str) -> Result<(), String> {
+    match engine {
+        TemplateEngine::Tera => {
+            let mut tera = Tera::default();
+            tera.add_raw_template("__check__", content)
+                .map(|_| ())
+                .map_err(|e| describe_tera_error(&e))
+        }
+        TemplateEngine::Handlebars => handlebars::Template::compile(content)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        TemplateEngine::None => Ok(()),
+    }
+}
+
+/// Parses the content of `prompt` with the engine `mode` picks for it, and every
+/// section as a Tera template, after translating custom delimiters. Nothing is
+/// rendered and no related prompts are loaded. Returns a message describing the
+/// first syntax error.
+pub fn validate_prompt(prompt: &Prompt, mode: EngineMode) -> Result<(), String> {
+    prompt.check_content()?;
+    if prompt.template_engine == Some(TemplateEngine::Handlebars) && (prompt.extends.is_some() || prompt.delimiters.is_some()) {
+        return Err("template_engine handlebars can't be combined with extends or delimiters, which need Tera".to_string());
+    }
+    match mode.engine_for(prompt) {
+        _ if prompt.is_encoded() => {}
+        TemplateEngine::Tera => parse_template("content", &prompt_source(prompt).map_err(|e| e.to_string())?)?,
+        TemplateEngine::Handlebars => check_syntax(TemplateEngine::Handlebars, &prompt.content)
+            .map_err(|e| format!("Invalid template in content: {}", e))?,
+        TemplateEngine::None => {}
+    }
+    for (name, section) in prompt.sections.iter().flatten() {
+        let source = match &prompt.delimiters {
+            Some(delimiters) => to_tera_syntax(section, delimiters).map_err(|e| e.to_string())?,
+            None => section.clone(),
+        };
+        parse_template(&format!("section '{}'", name), &source)?;
+    }
+    Ok(())
+}
+
+fn parse_template(label: &str, source: &str) -> Result<(), String> {
+    tera::Template::new(label, None, source)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid template in {}: {}", label, describe_tera_error(&e)))
+}
+
+/// Error returned when rendering a prompt.
+#[derive(Debug)]
+pub enum RenderError {
+    /// The template, or its inheritance chain, is invalid or failed to render.
+    Template(String),
+    /// Loading a related prompt from storage failed.
+    Storage(anyhow::Error),
+    /// Rendering took too long or produced too much output.
+    LimitExceeded(String),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Template(msg) => write!(f, "{}", msg),
+            RenderError::Storage(e) => write!(f, "storage error: {:#}", e),
+            RenderError::LimitExceeded(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<tera::Error> for RenderError {
+    fn from(err: tera::Error) -> Self {
+        RenderError::Template(describe_tera_error(&err))
+    }
+}
+
+/// Flattens a Tera error and its sources into a single message.
+/// Tera's top-level message alone is usually just "Failed to render '...'".
+pub fn describe_tera_error(err: &tera::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(inner) = source {
+        message.push_str(": ");
+        message.push_str(&inner.to_string());
+        source = inner.source();
+    }
+    message
+}
+
+/// Name of the template function that inlines another prompt by name, e.g.
+/// `{{ prompt(name="preamble") }}`.
+pub const PROMPT_FUNCTION: &str = "prompt";
+
+/// The `prompt()` function for a render with `arguments`. It returns the prompts
+/// rendered ahead of time by [`crate::references::with_references`], since Tera
+/// functions can't load from storage themselves.
+fn prompt_function(arguments: &Map<String, Value>) -> impl tera::Function {
+    let included = match arguments.get(PROMPTS_KEY) {
+        Some(Value::Object(included)) => included.clone(),
+        _ => Map::new(),
+    };
+    move |args: &HashMap<String, Value>| -> tera::Result<Value> {
+        let name = args
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| tera::Error::msg("prompt() needs a string `name` argument"))?;
+        included.get(name).cloned().ok_or_else(|| {
+            tera::Error::msg(format!("Prompt '{}' was not included; prompt() only accepts a literal name", name))
+        })
+    }
+}
+
+/// A Tera instance with the functions and snippets prompts can use.
+fn tera_for(arguments: &Map<String, Value>) -> Tera {
+    let mut tera = snippets::base_tera();
+    tera.register_function(PROMPT_FUNCTION, prompt_function(arguments));
+    tera
+}
+
+/// Most templates kept compiled by [`compiled_tera`]. Once full, the least
+/// recently used template is dropped to make room for a new one.
+pub const TEMPLATE_CACHE_CAPACITY: usize = 4096;
+
+/// Compiled templates by source, evicting the least recently used once full.
+pub struct TemplateCache {
+    capacity: usize,
+    entries: HashMap<String, (Tera, u64)>,
+    clock: u64,
+}
+
+impl TemplateCache {
+    /// An empty cache holding at most `capacity` templates.
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, entries: HashMap::new(), clock: 0 }
+    }
+
+    /// The template compiled from `source`, marking it as just used.
+    pub fn get(&mut self, source: &str) -> Option<Tera> {
+        self.clock += 1;
+        let (tera, last_used) = self.entries.get_mut(source)?;
+        *last_used = self.clock;
+        Some(tera.clone())
+    }
+
+    /// Caches `tera` as compiled from `source`, dropping the least recently used
+    /// template if the cache is full.
+    pub fn insert(&mut self, source: &str, tera: Tera) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(source) {
+            let oldest = self.entries.iter().min_by_key(|(_, (_, last_used))| *last_used).map(|(s, _)| s.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(source.to_string(), (tera, self.clock));
+    }
+
+    /// Number of cached templates.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// True if no templates are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops every cached template.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Compiled standalone templates by source, each in a Tera instance with the snippets.
+fn template_cache() -> &'static Mutex<TemplateCache> {
+    static CACHE: OnceLock<Mutex<TemplateCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(TemplateCache::new(TEMPLATE_CACHE_CAPACITY)))
+}
+
+/// A Tera instance with the snippets and `source` compiled as `__prompt__`.
+fn compile_tera(source: &str) -> Result<Tera, RenderError> {
+    let mut tera = snippets::base_tera();
+    tera.add_raw_template("__prompt__", source)?;
+    Ok(tera)
+}
+
+/// [`compile_tera`], from the cache when this source was compiled before.
+fn compiled_tera(source: &str) -> Result<Tera, RenderError> {
+    if let Some(tera) = template_cache().lock().unwrap_or_else(|e| e.into_inner()).get(source) {
+        return Ok(tera);
+    }
+    // Compile without holding the lock so other renders aren't held up
+    let tera = compile_tera(source)?;
+    template_cache().lock().unwrap_or_else(|e| e.into_inner()).insert(source, tera.clone());
+    Ok(tera)
+}
+
+/// Drops every compiled template, e.g. after the snippets they were compiled with change.
+pub fn clear_template_cache() {
+    template_cache().lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Compiles the Tera templates of a prompt's content and sections into the cache
+/// renders use, so its first render doesn't pay for compiling them. Returns the
+/// number of templates compiled; content rendered with Handlebars, encoded content
+/// and content that extends another prompt are not cached.
+pub fn warm_template_cache(prompt: &Prompt, mode: EngineMode) -> Result<usize, RenderError> {
+    let mut sources = Vec::new();
+    if mode.engine_for(prompt) == TemplateEngine::Tera && !prompt.is_encoded() && prompt.extends.is_none() {
+        sources.push(prompt_source(prompt)?);
+    }
+    for section in prompt.sections.iter().flat_map(|s| s.values()) {
+        sources.push(match &prompt.delimiters {
+            Some(delimiters) => to_tera_syntax(section, delimiters)?,
+            None => section.clone(),
+        });
+    }
+    for source in &sources {
+        compiled_tera(source)?;
+    }
+    Ok(sources.len())
+}
+
+/// Renders a standalone Tera template with the functions and snippets prompts can use.
+fn render_tera(source: &str, arguments: &Map<String, Value>) -> Result<String, RenderError> {
+    render_compiled(compiled_tera(source)?, arguments)
+}
+
+/// Renders the `__prompt__` template of `tera` with the `prompt()` function for `arguments`.
+fn render_compiled(mut tera: Tera, arguments: &Map<String, Value>) -> Result<String, RenderError> {
+    tera.register_function(PROMPT_FUNCTION, prompt_function(arguments));
+    render_bounded(|out| Ok(tera.render_to("__prompt__", &build_context(arguments), out)?))
+}
+
+/// Builds a Tera context from a JSON object of arguments.
+pub fn build_context(arguments: &Map<String, Value>) -> Context {
+    let mut context = Context::new();
+    for (key, value) in arguments {
+        context.insert(key.as_str(), value);
+    }
+    context
+}
+
+/// Default for how deeply templates may nest through references, `prompt()`
+/// includes and `extends`, counting one level per step.
+pub const DEFAULT_MAX_TEMPLATE_DEPTH: usize = 8;
+
+/// Error for a template nested more than `max_depth` levels deep.
+pub fn depth_exceeded(max_depth: usize) -> RenderError {
+    RenderError::Template(format!(
+        "Templates are nested more than {} levels deep through references, includes and extends",
+        max_depth
+    ))
+}
+
+/// Loads the ancestors of `prompt` by following `extends`, nearest parent first.
+///
+/// Fails if a parent is missing, the chain contains a cycle or it has more than
+/// `max_depth` ancestors.
+pub async fn resolve_parents(
+    storage: &dyn PromptStorage,
+    prompt: &Prompt,
+    max_depth: usize,
+) -> Result<Vec<Prompt>, RenderError> {
+    let mut parents = Vec::new();
+    let mut seen = HashSet::from([prompt.id]);
+    let mut next = prompt.extends;
+
+    while let Some(parent_id) = next {
+        if !seen.insert(parent_id) {
+            return Err(RenderError::Template(format!(
+                "Inheritance cycle detected: prompt {} is extended more than once in the chain",
+                parent_id
+            )));
+        }
+        if parents.len() >= max_depth {
+            return Err(depth_exceeded(max_depth));
+        }
+        let parent = storage
+            .get_prompt(&parent_id)
+            .await
+            .map_err(RenderError::Storage)?
+            .ok_or_else(|| {
+                RenderError::Template(format!("Parent prompt {} not found", parent_id))
+            })?;
+        next = parent.extends;
+        parents.push(parent);
+    }
+    Ok(parents)
+}
+
+/// Returns the Tera source for a prompt's content, translated from its custom
+/// delimiters if it has any.
+fn prompt_source(prompt: &Prompt) -> Result<String, RenderError> {
+    match &prompt.delimiters {
+        Some(delimiters) => to_tera_syntax(&prompt.content, delimiters),
+        None => Ok(prompt.content.clone()),
+    }
+}
+
+/// Returns the template source for a prompt, prefixed with an `{% extends %}` tag
+/// pointing at its parent when it has one and the author didn't write the tag.
+fn template_source(prompt: &Prompt) -> Result<String, RenderError> {
+    let source = prompt_source(prompt)?;
+    Ok(match prompt.extends {
+        Some(parent_id) if !source.trim_start().starts_with("{% extends") => {
+            format!("{{% extends \"{}\" %}}\n{}", parent_id, source)
+        }
+        _ => source,
+    })
+}
+
+/// Renders a prompt's content with the given arguments, using the engine `mode`
+/// picks for it.
+///
+/// Prompts with `extends` set are rendered through Tera template inheritance:
+/// every ancestor is registered as a template named by its id, so the child
+/// only needs to override `{% block %}`s defined by its parents, of which there
+/// may be at most `max_depth`.
+pub async fn render_prompt(
+    storage: &dyn PromptStorage,
+    prompt: &Prompt,
+    arguments: &Map<String, Value>,
+    mode: EngineMode,
+    max_depth: usize,
+) -> Result<String, RenderError> {
+    if prompt.is_encoded() {
+        return Ok(prompt.content.clone());
+    }
+    match mode.engine_for(prompt) {
+        TemplateEngine::Tera => {}
+        TemplateEngine::Handlebars => return render_handlebars(&prompt.content, arguments),
+        TemplateEngine::None => return Ok(prompt.content.clone()),
+    }
+    if prompt.extends.is_none() {
+        return render_tera(&prompt_source(prompt)?, arguments);
+    }
+
+    let parents = resolve_parents(storage, prompt, max_depth).await?;
+    let context = build_context(arguments);
+    let mut tera = tera_for(arguments);
+    let templates = std::iter::once(prompt)
+        .chain(parents.iter())
+        .map(|p| Ok((p.id.to_string(), template_source(p)?)))
+        .collect::<Result<Vec<(String, String)>, RenderError>>()?;
+    tera.add_raw_templates(templates)?;
+    render_bounded(|out| Ok(tera.render_to(&prompt.id.to_string(), &context, out)?))
+}
+
+/// Renders `content` as a Handlebars template. Like Tera renders, output is not HTML-escaped.
+fn render_handlebars(content: &str, arguments: &Map<String, Value>) -> Result<String, RenderError> {
+    let mut handlebars = handlebars::Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    render_bounded(|out| {
+        handlebars
+            .render_template_to_write(content, &Value::Object(arguments.clone()), out)
+            .map_err(|e| RenderError::Template(e.to_string()))
+    })
+}
+
+/// Translates content written with custom expression delimiters into Tera syntax.
+///
+/// Text between `delimiters.start` and `delimiters.end` becomes a `{{ }}` expression.
+/// Everything else is literal, so any `{{`, `{%` or `{#` in it is escaped and
+/// rendered verbatim.
+pub fn to_tera_syntax(content: &str, delimiters: &Delimiters) -> Result<String, RenderError> {
+    if delimiters.start.is_empty() || delimiters.end.is_empty() {
+        return Err(RenderError::Template("Template delimiters must not be empty".to_string()));
+    }
+
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find(&delimiters.start) {
+        escape_literal(&rest[..start], &mut output);
+        let after = &rest[start + delimiters.start.len()..];
+        let end = after.find(&delimiters.end).ok_or_else(|| {
+            RenderError::Template(format!("Unclosed '{}' tag", delimiters.start))
+        })?;
+        output.push_str("{{");
+        output.push_str(&after[..end]);
+        output.push_str("}}");
+        rest = &after[end + delimiters.end.len()..];
+    }
+    escape_literal(rest, &mut output);
+    Ok(output)
+}
+
+/// Appends literal text so that Tera outputs it unchanged. A `{` that could start
+/// a Tera tag (including one at the end, right before a generated `{{`) is
+/// emitted as an expression producing `{`.
+fn escape_literal(text: &str, output: &mut String) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '{' && matches!(chars.peek(), None | Some('{') | Some('%') | Some('#')) {
+            output.push_str("{{ \"{\" }}");
+        } else {
+            output.push(c);
+        }
+    }
+}
+
+/// Renders only the `{{ }}` expressions that use variables, all of which are in
+/// `arguments`.
+///
+/// Every other tag, including all `{% %}` statements and expressions such as
+/// `{{ loop.index }}` or `{{ super() }}` that only make sense in the full
+/// template, is left exactly as written, so the output is itself a template that
+/// can be rendered again once the remaining variables are known.
+pub fn render_partial(content: &str, arguments: &Map<String, Value>) -> Result<String, RenderError> {
+    let mut output = String::with_capacity(content.len());
+    let mut last = 0;
+
+    for tag in variables::template_tags(content) {
+        let tag_text = &content[tag.clone()];
+        let used = variables::tag_variables(tag_text);
+        let resolvable = tag_text.starts_with("{{")
+            && !used.is_empty()
+            && used.iter().all(|name| arguments.contains_key(*name));
+
+        output.push_str(&content[last..tag.start]);
+        if resolvable {
+            // Not cached: tags would crowd whole templates out of the cache
+            output.push_str(&render_compiled(compile_tera(tag_text)?, arguments)?);
+        } else {
+            output.push_str(tag_text);
+        }
+        last = tag.end;
+    }
+    output.push_str(&content[last..]);
+    Ok(output)
+}
+
+/// Renders the named sections of a prompt in the given order, joined by blank lines.
+/// Each section is rendered through Tera on its own with the same arguments.
+pub fn render_sections(
+    prompt: &Prompt,
+    names: &[String],
+    arguments: &Map<String, Value>,
+) -> Result<String, RenderError> {
+    let sections = prompt
+        .sections
+        .as_ref()
+        .ok_or_else(|| RenderError::Template(format!("Prompt {} has no sections", prompt.id)))?;
+
+    let rendered = names
+        .iter()
+        .map(|name| {
+            let section = sections
+                .get(name)
+                .ok_or_else(|| RenderError::Template(format!("Section '{}' not found", name)))?;
+            let source = match &prompt.delimiters {
+                Some(delimiters) => to_tera_syntax(section, delimiters)?,
+                None => section.clone(),
+            };
+            render_tera(&source, arguments)
+        })
+        .collect::<Result<Vec<String>, RenderError>>()?;
+    Ok(rendered.join("\n\n"))
+}
+
+/// Resource limits applied when rendering untrusted or expensive templates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderLimits {
+    /// Maximum time a render may take.
+    pub timeout: Duration,
+    /// Maximum size of the rendered output in bytes.
+    pub max_output_bytes: usize,
+}
+
+tokio::task_local! {
+    /// Output limit of the render [`run_with_limits`] is driving.
+    static MAX_OUTPUT: usize;
+}
+
+/// Collects render output, failing writes that would take it past `max` bytes so
+/// the engine stops rendering instead of building ever larger output.
+pub(crate) struct BoundedWriter {
+    buffer: Vec<u8>,
+    max: usize,
+    exceeded: bool,
+}
+
+impl BoundedWriter {
+    pub(crate) fn new(max: usize) -> Self {
+        Self { buffer: Vec::new(), max, exceeded: false }
+    }
+
+    /// The output of a render that returned `result`, or the error for output over the limit.
+    pub(crate) fn finish(self, result: Result<(), RenderError>) -> Result<String, RenderError> {
+        if self.exceeded {
+            return Err(RenderError::LimitExceeded(format!(
+                "Rendered output exceeds the limit of {} bytes",
+                self.max
+            )));
+        }
+        result?;
+        String::from_utf8(self.buffer).map_err(|e| RenderError::Template(e.to_string()))
+    }
+}
+
+impl Write for BoundedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() + buf.len() > self.max {
+            self.exceeded = true;
+            return Err(io::Error::other("render output limit exceeded"));
+        }
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `render` into a [`BoundedWriter`] limited to the output size of the
+/// surrounding [`run_with_limits`], or unlimited outside of one.
+fn render_bounded(
+    render: impl FnOnce(&mut BoundedWriter) -> Result<(), RenderError>,
+) -> Result<String, RenderError> {
+    let mut out = BoundedWriter::new(MAX_OUTPUT.try_with(|max| *max).unwrap_or(usize::MAX));
+    let result = render(&mut out);
+    out.finish(result)
+}
+
+/// Most renders that may run on blocking threads at once, counting renders that
+/// timed out but haven't finished yet.
+pub const MAX_BLOCKING_RENDERS: usize = 64;
+
+/// Slots for renders on blocking threads, so runaway templates can't take over
+/// tokio's blocking thread pool.
+fn render_slots() -> &'static Arc<Semaphore> {
+    static SLOTS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+    SLOTS.get_or_init(|| Arc::new(Semaphore::new(MAX_BLOCKING_RENDERS)))
+}
+
+/// Drives `render` to completion on a blocking thread, so a slow template can't
+/// stall the async workers, and fails if it exceeds `limits`.
+///
+/// A render that times out keeps running on its blocking thread until the engine
+/// finishes, but its result is discarded and the caller gets an error immediately.
+/// It keeps its slot until then, so at most [`MAX_BLOCKING_RENDERS`] renders run
+/// at once; waiting for a slot counts against the time limit. Each template
+/// rendered within `render` stops as soon as its output grows past
+/// `limits.max_output_bytes`, and the combined result is checked against it too.
+pub async fn run_with_limits<F>(limits: RenderLimits, render: F) -> Result<String, RenderError>
+where
+    F: Future<Output = Result<String, RenderError>> + Send + 'static,
+{
+    let deadline = tokio::time::Instant::now() + limits.timeout;
+    let timed_out = || {
+        RenderError::LimitExceeded(format!(
+            "Render exceeded the time limit of {} ms",
+            limits.timeout.as_millis()
+        ))
+    };
+    let slot = match tokio::time::timeout_at(deadline, render_slots().clone().acquire_owned()).await {
+        Ok(slot) => slot.expect("the render semaphore is never closed"),
+        Err(_) => return Err(timed_out()),
+    };
+    let handle = tokio::runtime::Handle::current();
+    let task = tokio::task::spawn_blocking(move || {
+        let rendered = handle.block_on(MAX_OUTPUT.scope(limits.max_output_bytes, render));
+        drop(slot);
+        rendered
+    });
+
+    let rendered = match tokio::time::timeout_at(deadline, task).await {
+        Ok(Ok(result)) => result?,
+        Ok(Err(join_error)) => {
+            return Err(RenderError::Template(format!("Render task failed: {}", join_error)))
+        }
+        Err(_) => return Err(timed_out()),
+    };
+    check_output_size(rendered, limits.max_output_bytes)
+}
+
+/// Fails if the rendered output is larger than `max_output_bytes`.
+pub fn check_output_size(rendered: String, max_output_bytes: usize) -> Result<String, RenderError> {
+    if rendered.len() > max_output_bytes {
+        return Err(RenderError::LimitExceeded(format!(
+            "Rendered output of {} bytes exceeds the limit of {} bytes",
+            rendered.len(),
+            max_output_bytes
+        )));
+    }
+    Ok(rendered)
+}
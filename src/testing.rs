@@ -0,0 +1,87 @@
+use crate::models::prompt::{self, Prompt, PromptTest};
+use crate::template::RenderError;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Outcome of one stored test case of a prompt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestCaseResult {
+    /// Position of the case in the prompt's `tests`, starting at 0.
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub passed: bool,
+    /// What the prompt rendered, when rendering succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_output: Option<String>,
+    /// Unified diff from the expected to the actual output of a failed case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+    /// Why rendering failed, when it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TestCaseResult {
+    /// Compares the result of rendering `test`'s arguments with its expected output.
+    /// A failed render fails the case.
+    pub fn check(index: usize, test: &PromptTest, rendered: Result<String, RenderError>) -> Self {
+        let result = TestCaseResult {
+            index,
+            name: test.name.clone(),
+            passed: false,
+            actual_output: None,
+            diff: None,
+            error: None,
+        };
+        match rendered {
+            Ok(actual) if actual == test.expected_output => TestCaseResult {
+                passed: true,
+                actual_output: Some(actual),
+                ..result
+            },
+            Ok(actual) => TestCaseResult {
+                diff: Some(prompt::unified_diff(&test.expected_output, &actual)),
+                actual_output: Some(actual),
+                ..result
+            },
+            Err(e) => TestCaseResult {
+                error: Some(e.to_string()),
+                ..result
+            },
+        }
+    }
+
+    /// The case's name, or `#<index>` for unnamed cases.
+    pub fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| format!("#{}", self.index))
+    }
+}
+
+/// Results of running all stored test cases of a prompt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestReport {
+    pub id: Uuid,
+    pub name: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<TestCaseResult>,
+}
+
+impl TestReport {
+    pub fn new(prompt: &Prompt, results: Vec<TestCaseResult>) -> Self {
+        let passed = results.iter().filter(|r| r.passed).count();
+        TestReport {
+            id: prompt.id,
+            name: prompt.name.clone(),
+            passed,
+            failed: results.len() - passed,
+            results,
+        }
+    }
+
+    /// True if no case failed. A prompt without test cases succeeds.
+    pub fn succeeded(&self) -> bool {
+        self.failed == 0
+    }
+}
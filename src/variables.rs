@@ -0,0 +1,264 @@
+use crate::context::GLOBALS_KEY;
+use crate::models::prompt::Prompt;
+use crate::references::{PROMPTS_KEY, REFS_KEY};
+use crate::template::{self, RenderError};
+use regex::Regex;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// Words inside tags that are syntax, literals or built-ins rather than variables.
+const RESERVED_WORDS: &[&str] = &[
+    "and", "or", "not", "in", "is", "if", "elif", "else", "endif", "for", "endfor", "set",
+    "set_global", "endset", "block", "endblock", "extends", "include", "import", "macro",
+    "endmacro", "filter", "endfilter", "raw", "endraw", "as", "true", "false", "True", "False",
+    "none", "None", "ignore", "missing", "break", "continue", "loop", "self", "super",
+];
+
+/// Words after which the next identifier names something other than a variable.
+const NAMING_WORDS: &[&str] = &["block", "endblock", "macro", "as", "filter", "is"];
+
+/// Matches Tera comments (`{# #}`), `{% raw %}` blocks, expression (`{{ }}`) and
+/// statement (`{% %}`) tags, in the order Tera's parser gives them precedence.
+fn tag_regex() -> &'static Regex {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    TAG.get_or_init(|| {
+        Regex::new(r"(?s)\{#.*?#\}|\{%-?\s*raw\s*-?%\}.*?\{%-?\s*endraw\s*-?%\}|\{\{.*?\}\}|\{%.*?%\}")
+            .expect("valid tag regex")
+    })
+}
+
+/// Returns the byte ranges of all `{{ }}` and `{% %}` tags in `content`.
+///
+/// Comments and `{% raw %}` blocks, including their own tags, are literal text
+/// to Tera and are skipped, so nothing inside them counts as a tag.
+pub fn template_tags(content: &str) -> Vec<Range<usize>> {
+    tag_regex()
+        .find_iter(content)
+        .filter(|m| !m.as_str().starts_with("{#") && !is_raw_block(m.as_str()))
+        .map(|m| m.range())
+        .collect()
+}
+
+/// True for a match of [`tag_regex`] that is a whole `{% raw %}` block.
+fn is_raw_block(tag: &str) -> bool {
+    tag.strip_prefix("{%")
+        .map(|rest| rest.trim_start_matches('-').trim_start())
+        .is_some_and(|rest| rest.starts_with("raw") && rest[3..].trim_start().trim_start_matches('-').starts_with("%}"))
+}
+
+/// Returns true if `name` is a valid template variable identifier.
+pub fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Finds the variables referenced inside a single tag, as byte ranges into `tag`.
+///
+/// Only root identifiers count: attribute names (`x.attr`), filters (`x | upper`),
+/// tests (`x is defined`), function and macro names, keyword arguments, block names,
+/// `for` loop bindings, reserved words and anything inside string literals are skipped.
+pub fn variable_refs(tag: &str) -> Vec<Range<usize>> {
+    let chars: Vec<(usize, char)> = tag.char_indices().collect();
+    let mut refs = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut prev_symbol: Option<char> = None;
+    let mut prev_word: Option<&str> = None;
+    let mut in_for_binding = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (_, c) = chars[i];
+
+        if let Some(q) = quote {
+            if c == '\\' {
+                i += 2;
+                continue;
+            }
+            if c == q {
+                quote = None;
+                prev_symbol = Some(c);
+                prev_word = None;
+            }
+            i += 1;
+            continue;
+        }
+
+        if c == '"' || c == '\'' || c == '`' {
+            quote = Some(c);
+            i += 1;
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = chars[i].0;
+            while i < chars.len() && (chars[i].1.is_alphanumeric() || chars[i].1 == '_') {
+                i += 1;
+            }
+            let end = chars.get(i).map_or(tag.len(), |(pos, _)| *pos);
+            let word = &tag[start..end];
+
+            let next = tag[end..].trim_start();
+            let is_call = next.starts_with('(');
+            let is_kwarg = next.starts_with('=') && !next.starts_with("==");
+            let follows_symbol = matches!(prev_symbol, Some('.') | Some('|') | Some(':'));
+            let is_named = prev_word.is_some_and(|w| NAMING_WORDS.contains(&w))
+                || (prev_word == Some("not") && follows_is_not(tag, start));
+
+            if word == "for" {
+                in_for_binding = true;
+            } else if word == "in" {
+                in_for_binding = false;
+            } else if !(RESERVED_WORDS.contains(&word)
+                || follows_symbol
+                || is_call
+                || is_kwarg
+                || is_named
+                || in_for_binding)
+            {
+                refs.push(start..end);
+            }
+
+            prev_symbol = None;
+            prev_word = Some(word);
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            // Skip numeric literals, including things like `1e5`
+            while i < chars.len() && (chars[i].1.is_alphanumeric() || chars[i].1 == '.') {
+                i += 1;
+            }
+            prev_symbol = None;
+            prev_word = None;
+            continue;
+        }
+
+        if !c.is_whitespace() {
+            prev_symbol = Some(c);
+            prev_word = None;
+        }
+        i += 1;
+    }
+    refs
+}
+
+/// True when the `not` just before `pos` is part of an `is not <test>` expression.
+fn follows_is_not(tag: &str, pos: usize) -> bool {
+    tag[..pos]
+        .trim_end()
+        .strip_suffix("not")
+        .is_some_and(|before| before.trim_end().ends_with(" is"))
+}
+
+/// Returns the names of the variables referenced inside a single tag.
+pub fn tag_variables(tag: &str) -> Vec<&str> {
+    variable_refs(tag).into_iter().map(|r| &tag[r]).collect()
+}
+
+fn binding_regex() -> &'static Regex {
+    static BINDING: OnceLock<Regex> = OnceLock::new();
+    BINDING.get_or_init(|| {
+        Regex::new(
+            r"^\{%-?\s*(?:for\s+([A-Za-z_]\w*)(?:\s*,\s*([A-Za-z_]\w*))?\s+in\b|set(?:_global)?\s+([A-Za-z_]\w*)|macro\s+\w+\s*\(([^)]*)\))",
+        )
+        .expect("valid binding regex")
+    })
+}
+
+/// Names bound inside the template itself by `for`, `set` and macro parameters.
+pub fn template_bindings(source: &str) -> HashSet<String> {
+    let mut bound = HashSet::new();
+    for tag in template_tags(source) {
+        let Some(captures) = binding_regex().captures(&source[tag]) else {
+            continue;
+        };
+        for name in [captures.get(1), captures.get(2), captures.get(3)].into_iter().flatten() {
+            bound.insert(name.as_str().to_string());
+        }
+        if let Some(params) = captures.get(4) {
+            for param in params.as_str().split(',') {
+                let name = param.split('=').next().unwrap_or_default().trim();
+                if is_valid_identifier(name) {
+                    bound.insert(name.to_string());
+                }
+            }
+        }
+    }
+    bound
+}
+
+/// Returns the variables a template actually uses, in order of first use and
+/// without duplicates, whatever the prompt declares. Names bound by the template
+/// itself (see [`template_bindings`]) are left out.
+pub fn template_variables(content: &str) -> Vec<String> {
+    let bound = template_bindings(content);
+    let mut used: Vec<String> = Vec::new();
+    for tag in template_tags(content) {
+        for name in tag_variables(&content[tag]) {
+            if !bound.contains(name) && !used.iter().any(|u| u == name) {
+                used.push(name.to_string());
+            }
+        }
+    }
+    used
+}
+
+/// Returns the variables a prompt's content and sections use, in order of first
+/// use. Sections render with the same arguments, so their variables count too.
+/// Fails if the prompt's custom delimiters are invalid.
+pub fn prompt_variables(prompt: &Prompt) -> Result<Vec<String>, RenderError> {
+    let mut used: Vec<String> = Vec::new();
+    let content = std::iter::once(&prompt.content).filter(|_| !prompt.is_encoded());
+    for text in content.chain(prompt.sections.iter().flat_map(|s| s.values())) {
+        let source = match &prompt.delimiters {
+            Some(delimiters) => template::to_tera_syntax(text, delimiters)?,
+            None => text.clone(),
+        };
+        for name in template_variables(&source) {
+            if !used.contains(&name) {
+                used.push(name);
+            }
+        }
+    }
+    Ok(used)
+}
+
+/// Sets the prompt's declared `variables` to the arguments its templates use
+/// (see [`prompt_variables`]), leaving out the `globals`, `refs` and `prompts`
+/// render context keys, which aren't arguments. `variable_metadata` and
+/// `default_arguments` are kept as they are.
+pub fn declare_used_variables(prompt: &mut Prompt) -> Result<(), RenderError> {
+    let context_keys = [GLOBALS_KEY, REFS_KEY, PROMPTS_KEY];
+    let mut used = prompt_variables(prompt)?;
+    used.retain(|name| !context_keys.contains(&name.as_str()));
+    prompt.variables = Some(used);
+    Ok(())
+}
+
+/// Renames the template variable `old` to `new` everywhere it is used in `content`.
+///
+/// Only variable references inside `{{ }}` and `{% %}` tags are replaced (see
+/// [`variable_refs`]), so text outside tags, string literals, attribute accesses,
+/// filter names and identifiers that merely contain `old` are left untouched.
+/// Returns the new content and the number of replacements made.
+pub fn rename_variable(content: &str, old: &str, new: &str) -> (String, usize) {
+    let mut output = String::with_capacity(content.len());
+    let mut count = 0;
+    let mut last = 0;
+
+    for tag in template_tags(content) {
+        let tag_text = &content[tag.clone()];
+        for r in variable_refs(tag_text) {
+            if tag_text[r.clone()] == *old {
+                output.push_str(&content[last..tag.start + r.start]);
+                output.push_str(new);
+                last = tag.start + r.end;
+                count += 1;
+            }
+        }
+    }
+    output.push_str(&content[last..]);
+    (output, count)
+}
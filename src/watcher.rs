@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Capacity of the change event channel. Slow subscribers miss old events
+/// rather than blocking the watcher thread.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Kind of change observed on a prompt file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+/// A change to a single prompt file in the watched directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PromptChangeEvent {
+    pub id: Uuid,
+    pub kind: PromptChangeKind,
+}
+
+/// Watches a prompt directory and broadcasts change events for `{uuid}.json` files.
+///
+/// The underlying OS watcher stops when this value is dropped, so keep it alive
+/// for as long as events are needed.
+pub struct PromptDirWatcher {
+    _watcher: RecommendedWatcher,
+    events: broadcast::Sender<PromptChangeEvent>,
+    dir: PathBuf,
+}
+
+impl PromptDirWatcher {
+    /// Starts watching `dir` (non-recursively) for prompt file changes.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let sender = events.clone();
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            match res {
+                Ok(event) => {
+                    let kind = match event.kind {
+                        EventKind::Create(_) => PromptChangeKind::Created,
+                        EventKind::Modify(_) => PromptChangeKind::Modified,
+                        EventKind::Remove(_) => PromptChangeKind::Removed,
+                        _ => return,
+                    };
+                    for path in &event.paths {
+                        if let Some(id) = prompt_id_from_path(path) {
+                            debug!(prompt_id = %id, ?kind, "Prompt file changed on disk");
+                            // An error here only means there are no subscribers right now.
+                            let _ = sender.send(PromptChangeEvent { id, kind });
+                        }
+                    }
+                }
+                Err(e) => warn!(error = %e, "Prompt directory watcher error"),
+            }
+        })
+        .context("Failed to create prompt directory watcher")?;
+
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch prompt directory '{}'", dir.display()))?;
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            dir,
+        })
+    }
+
+    /// Returns a receiver for change events emitted after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<PromptChangeEvent> {
+        self.events.subscribe()
+    }
+
+    /// The directory being watched.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// Extracts the prompt id from a `{uuid}.json` path, ignoring any other files.
+fn prompt_id_from_path(path: &Path) -> Option<Uuid> {
+    if path.extension().is_none_or(|ext| ext != "json") {
+        return None;
+    }
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(|stem| Uuid::parse_str(stem).ok())
+}
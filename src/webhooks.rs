@@ -0,0 +1,133 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+/// Maximum number of undelivered events buffered before new ones are dropped.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Delivery settings for outbound webhooks.
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// Endpoints that receive every event.
+    pub urls: Vec<String>,
+    /// Retries after the first failed attempt before an event is dead-lettered.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubles on each further retry.
+    pub initial_backoff: Duration,
+    /// Timeout for a single delivery attempt.
+    pub timeout: Duration,
+    /// File that events are appended to (as JSON lines) once retries are exhausted.
+    pub dead_letter_path: Option<PathBuf>,
+}
+
+/// Kind of change a webhook event reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum WebhookEventKind {
+    #[serde(rename = "prompt.created")]
+    Created,
+    #[serde(rename = "prompt.updated")]
+    Updated,
+    #[serde(rename = "prompt.deleted")]
+    Deleted,
+}
+
+/// Payload POSTed to each webhook URL.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEvent {
+    pub event: WebhookEventKind,
+    pub prompt_id: Uuid,
+}
+
+/// Queues webhook events and delivers them from a background task, so request
+/// latency doesn't depend on webhook endpoints.
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    sender: Option<mpsc::Sender<WebhookEvent>>,
+}
+
+impl WebhookDispatcher {
+    /// A dispatcher that drops every event.
+    pub fn disabled() -> Self {
+        Self { sender: None }
+    }
+
+    /// Starts the background delivery task. Must be called inside a Tokio runtime.
+    pub fn start(config: WebhookConfig) -> anyhow::Result<Self> {
+        if config.urls.is_empty() {
+            return Ok(Self::disabled());
+        }
+
+        let client = reqwest::Client::builder().timeout(config.timeout).build()?;
+        let config = Arc::new(config);
+        let (sender, mut receiver) = mpsc::channel::<WebhookEvent>(QUEUE_CAPACITY);
+
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                for url in &config.urls {
+                    // One task per delivery so a slow endpoint doesn't hold up the others
+                    tokio::spawn(deliver(client.clone(), Arc::clone(&config), url.clone(), event.clone()));
+                }
+            }
+        });
+
+        Ok(Self { sender: Some(sender) })
+    }
+
+    /// Queues an event for delivery. Never blocks; drops the event if the queue is full.
+    pub fn notify(&self, event: WebhookEventKind, prompt_id: Uuid) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        if let Err(e) = sender.try_send(WebhookEvent { event, prompt_id }) {
+            warn!(prompt_id = %prompt_id, error = %e, "Dropping webhook event");
+        }
+    }
+}
+
+/// Delivers one event to one URL with exponential backoff, dead-lettering it on failure.
+async fn deliver(client: reqwest::Client, config: Arc<WebhookConfig>, url: String, event: WebhookEvent) {
+    let mut backoff = config.initial_backoff;
+
+    for attempt in 0..=config.max_retries {
+        if attempt > 0 {
+            tokio::time::sleep(backoff).await;
+            backoff = backoff.saturating_mul(2);
+        }
+
+        let outcome = client
+            .post(&url)
+            .json(&event)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        match outcome {
+            Ok(_) => {
+                debug!(url = %url, prompt_id = %event.prompt_id, attempt, "Webhook delivered");
+                return;
+            }
+            Err(e) => warn!(url = %url, prompt_id = %event.prompt_id, attempt, error = %e, "Webhook delivery failed"),
+        }
+    }
+
+    error!(url = %url, prompt_id = %event.prompt_id, event = ?event.event, "Webhook retries exhausted; dead-lettering event");
+    if let Some(path) = &config.dead_letter_path {
+        let line = serde_json::json!({ "url": url, "payload": event }).to_string() + "\n";
+        let written = async {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(line.as_bytes()).await
+        }
+        .await;
+        if let Err(e) = written {
+            error!(path = %path.display(), error = %e, "Failed to write webhook dead letter");
+        }
+    }
+}
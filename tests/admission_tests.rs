@@ -0,0 +1,25 @@
+use mcp_prompts_rs::admission::{is_exempt, AdmissionControl, AdmissionLimits, RequestClass, DEFAULT_EXEMPT_PATHS};
+
+#[test]
+fn requests_beyond_the_limits_are_turned_away() {
+    let admission = AdmissionControl::new(AdmissionLimits { total: Some(2), reads: None, writes: Some(1) });
+
+    let write = admission.try_admit(RequestClass::Write).unwrap();
+    assert!(admission.try_admit(RequestClass::Write).is_none());
+    let read = admission.try_admit(RequestClass::Read).unwrap();
+    assert!(admission.try_admit(RequestClass::Read).is_none());
+
+    drop(write);
+    assert!(admission.try_admit(RequestClass::Read).is_some());
+    drop(read);
+}
+
+#[test]
+fn default_exempt_paths_cover_probes() {
+    let exempt: Vec<String> = DEFAULT_EXEMPT_PATHS.iter().map(|p| p.to_string()).collect();
+    assert!(is_exempt("/health", &exempt));
+    assert!(is_exempt("/health/live/", &exempt));
+    assert!(is_exempt("/metrics", &exempt));
+    assert!(!is_exempt("/prompts", &exempt));
+    assert!(!is_exempt("/healthz", &exempt));
+}
@@ -0,0 +1,56 @@
+use actix_web::http::{header, StatusCode};
+use actix_web::{test, web, App, HttpResponse};
+use mcp_prompts_rs::auth::ApiKeyAuth;
+
+async fn status_of(auth: ApiKeyAuth, path: &str, authorization: Option<&str>) -> StatusCode {
+    let app = test::init_service(
+        App::new()
+            .wrap(auth)
+            .route("/health", web::get().to(|| async { HttpResponse::Ok().body("OK") }))
+            .route("/metrics", web::get().to(|| async { HttpResponse::Ok().body("") }))
+            .route("/prompts", web::get().to(|| async { HttpResponse::Ok().body("[]") })),
+    )
+    .await;
+    let mut req = test::TestRequest::get().uri(path);
+    if let Some(value) = authorization {
+        req = req.insert_header((header::AUTHORIZATION, value));
+    }
+    test::call_service(&app, req.to_request()).await.status()
+}
+
+#[actix_web::test]
+async fn api_key_is_required_only_when_configured() {
+    let auth = || ApiKeyAuth::new(Some("s3cret".to_string())).also_accept(Some("admin".to_string()));
+    assert!(auth().is_enabled());
+
+    // Authorized: the API key or the admin token
+    assert_eq!(status_of(auth(), "/prompts", Some("Bearer s3cret")).await, StatusCode::OK);
+    assert_eq!(status_of(auth(), "/prompts", Some("Bearer admin")).await, StatusCode::OK);
+
+    // Unauthorized: missing, wrong or malformed credentials
+    assert_eq!(status_of(auth(), "/prompts", None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status_of(auth(), "/prompts", Some("Bearer wrong")).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status_of(auth(), "/prompts", Some("s3cret")).await, StatusCode::UNAUTHORIZED);
+
+    // Health probes stay open
+    assert_eq!(status_of(auth(), "/health", None).await, StatusCode::OK);
+
+    // Disabled: no key (or a blank one) lets everything through, even with an admin token set
+    let disabled = ApiKeyAuth::new(None).also_accept(Some("admin".to_string()));
+    assert!(!disabled.is_enabled());
+    assert_eq!(status_of(disabled, "/prompts", None).await, StatusCode::OK);
+    assert_eq!(status_of(ApiKeyAuth::new(Some("  ".to_string())), "/prompts", None).await, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn configured_open_paths_skip_the_api_key() {
+    let auth = || ApiKeyAuth::new(Some("s3cret".to_string())).open_paths(vec!["/metrics".to_string()]);
+
+    assert_eq!(status_of(auth(), "/metrics", None).await, StatusCode::OK);
+    assert_eq!(status_of(auth(), "/health", None).await, StatusCode::OK);
+    assert_eq!(status_of(auth(), "/prompts", None).await, StatusCode::UNAUTHORIZED);
+
+    // Without the path configured, metrics need the key like anything else
+    let strict = ApiKeyAuth::new(Some("s3cret".to_string()));
+    assert_eq!(status_of(strict, "/metrics", None).await, StatusCode::UNAUTHORIZED);
+}
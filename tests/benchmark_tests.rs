@@ -0,0 +1,34 @@
+use mcp_prompts_rs::benchmark::{run_benchmark, LatencyPercentiles};
+use mcp_prompts_rs::storage::memory::InMemoryStorage;
+use mcp_prompts_rs::storage::PromptStorage;
+use std::time::Duration;
+
+#[test]
+fn percentiles_use_the_nearest_rank() {
+    let mut samples: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+    let percentiles = LatencyPercentiles::from_samples(&mut samples);
+    assert_eq!(percentiles, LatencyPercentiles { p50_ms: 50.0, p95_ms: 95.0, p99_ms: 99.0 });
+
+    // With few samples the high percentiles are the slowest one
+    let mut samples = vec![Duration::from_millis(3), Duration::from_millis(1)];
+    let percentiles = LatencyPercentiles::from_samples(&mut samples);
+    assert_eq!(percentiles, LatencyPercentiles { p50_ms: 1.0, p95_ms: 3.0, p99_ms: 3.0 });
+}
+
+#[tokio::test]
+async fn benchmark_reports_every_operation_and_cleans_up() {
+    let storage = InMemoryStorage::new();
+    let report = run_benchmark(&storage, 20).await.unwrap();
+
+    assert_eq!(report.storage, storage.backend_name());
+    assert_eq!(report.ops, 20);
+    for percentiles in [report.write, report.read, report.delete, report.cycle] {
+        assert!(percentiles.p50_ms <= percentiles.p95_ms && percentiles.p95_ms <= percentiles.p99_ms);
+    }
+    assert!(storage.list_prompts().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn benchmark_needs_at_least_one_operation() {
+    assert!(run_benchmark(&InMemoryStorage::new(), 0).await.is_err());
+}
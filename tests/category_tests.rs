@@ -0,0 +1,39 @@
+use mcp_prompts_rs::categories::in_subtree;
+use mcp_prompts_rs::models::prompt::Prompt;
+use mcp_prompts_rs::storage::memory::InMemoryStorage;
+use mcp_prompts_rs::storage::PromptStorage;
+
+#[test]
+fn subtrees_match_whole_segments() {
+    assert!(in_subtree("writing", "writing"));
+    assert!(in_subtree("writing/email", "writing"));
+    assert!(in_subtree("writing/email", "writing/"));
+    assert!(in_subtree("writing/email/cold", "writing/email"));
+    assert!(!in_subtree("writingtools", "writing"));
+    assert!(!in_subtree("writing", "writing/email"));
+    assert!(!in_subtree("code/writing", "writing"));
+    assert!(in_subtree("anything", ""));
+}
+
+#[tokio::test]
+async fn category_prefix_listing_stays_inside_the_subtree() {
+    let storage = InMemoryStorage::new();
+    for category in ["writing", "writing/email", "writingtools", "code"] {
+        let prompt = Prompt::new(category.to_string(), "content".to_string(), Some(category.to_string()), None);
+        storage.save_prompt(&prompt).await.unwrap();
+    }
+    storage
+        .save_prompt(&Prompt::new("uncategorized".to_string(), "content".to_string(), None, None))
+        .await
+        .unwrap();
+
+    let mut names: Vec<String> = storage
+        .list_prompts_by_category_prefix("writing/")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["writing", "writing/email"]);
+}
@@ -0,0 +1,87 @@
+use mcp_prompts_rs::context::{resolve_aliases, with_defaults, with_globals, GlobalContext, GLOBALS_KEY};
+use mcp_prompts_rs::models::prompt::VariableMetadata;
+use std::collections::BTreeMap;
+use serde_json::{json, Map, Value};
+
+fn object(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map,
+        _ => panic!("expected a JSON object"),
+    }
+}
+
+#[test]
+fn globals_are_added_under_reserved_key() {
+    let globals = object(json!({ "company": "Acme", "support": "help@acme.test" }));
+    let args = object(json!({ "name": "Ada" }));
+
+    let merged = with_globals(&globals, &args);
+    assert_eq!(merged["name"], json!("Ada"));
+    assert_eq!(merged[GLOBALS_KEY], json!({ "company": "Acme", "support": "help@acme.test" }));
+}
+
+#[test]
+fn user_arguments_take_precedence_over_globals() {
+    let globals = object(json!({ "company": "Acme", "support": "help@acme.test" }));
+    let args = object(json!({ "globals": { "company": "Initech" } }));
+
+    let merged = with_globals(&globals, &args);
+    assert_eq!(merged[GLOBALS_KEY], json!({ "company": "Initech", "support": "help@acme.test" }));
+}
+
+#[test]
+fn default_argument_precedence_is_request_then_prompt_then_server() {
+    let server = object(json!({ "tone": "professional", "language": "en", "length": "short" }));
+    let prompt = object(json!({ "tone": "friendly", "language": "de" }));
+    let request = object(json!({ "tone": "terse" }));
+
+    let merged = with_defaults(&server, &prompt, &request);
+    assert_eq!(merged["tone"], json!("terse"));
+    assert_eq!(merged["language"], json!("de"));
+    assert_eq!(merged["length"], json!("short"));
+}
+
+#[test]
+fn default_arguments_replace_whole_values() {
+    let server = object(json!({ "style": { "tone": "professional", "emoji": false } }));
+    let request = object(json!({ "style": { "tone": "casual" } }));
+
+    let merged = with_defaults(&server, &Map::new(), &request);
+    assert_eq!(merged["style"], json!({ "tone": "casual" }));
+}
+
+fn username_aliases() -> BTreeMap<String, VariableMetadata> {
+    BTreeMap::from([(
+        "username".to_string(),
+        VariableMetadata { aliases: vec!["user".to_string(), "login".to_string()] },
+    )])
+}
+
+#[test]
+fn aliases_are_mapped_to_canonical_name() {
+    let args = object(json!({ "user": "ada", "topic": "math" }));
+
+    let resolved = resolve_aliases(&username_aliases(), &args);
+    assert_eq!(resolved, object(json!({ "username": "ada", "topic": "math" })));
+}
+
+#[test]
+fn canonical_name_wins_over_aliases_then_first_alias() {
+    let args = object(json!({ "username": "ada", "user": "grace" }));
+    assert_eq!(resolve_aliases(&username_aliases(), &args), object(json!({ "username": "ada" })));
+
+    let args = object(json!({ "login": "alan", "user": "grace" }));
+    assert_eq!(resolve_aliases(&username_aliases(), &args), object(json!({ "username": "grace" })));
+}
+
+#[tokio::test]
+async fn context_file_errors_name_the_context_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("context.json");
+    std::fs::write(&path, "[1, 2]").unwrap();
+
+    let err = GlobalContext::from_file(&path).await.err().unwrap();
+    assert_eq!(err.to_string(), format!("Context file '{}' must contain a JSON object", path.display()));
+    let err = GlobalContext::from_file(dir.path().join("missing.json")).await.err().unwrap();
+    assert!(err.to_string().starts_with("Failed to read context file"));
+}
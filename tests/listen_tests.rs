@@ -0,0 +1,26 @@
+use mcp_prompts_rs::listen::{listen_addrs, DEFAULT_HOST};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+#[test]
+fn listen_addresses_accept_ipv4_ipv6_and_host_names() {
+    assert_eq!(
+        listen_addrs(DEFAULT_HOST, 8080).unwrap(),
+        vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080)]
+    );
+    assert_eq!(
+        listen_addrs("0.0.0.0", 3000).unwrap(),
+        vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 3000)]
+    );
+    let loopback_v6 = vec![SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 9000)];
+    assert_eq!(listen_addrs("::1", 9000).unwrap(), loopback_v6);
+    assert_eq!(listen_addrs("[::1]", 9000).unwrap(), loopback_v6);
+
+    let resolved = listen_addrs("localhost", 8080).unwrap();
+    assert!(!resolved.is_empty());
+    assert!(resolved.iter().all(|addr| addr.port() == 8080 && addr.ip().is_loopback()));
+
+    for bad in ["", "not a host", "300.1.1.1/8", "[localhost"] {
+        let err = listen_addrs(bad, 8080).unwrap_err();
+        assert!(err.to_string().contains("Invalid --host"), "{}: {}", bad, err);
+    }
+}
@@ -0,0 +1,35 @@
+use mcp_prompts_rs::metrics::{InstrumentedStorage, Metrics, PromptOperation};
+use mcp_prompts_rs::models::prompt::Prompt;
+use mcp_prompts_rs::storage::memory::InMemoryStorage;
+use mcp_prompts_rs::storage::PromptStorage;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn scraped_metrics_count_operations_and_time_storage_calls() {
+    let metrics = Metrics::new().unwrap();
+    let storage = InstrumentedStorage::new(Arc::new(InMemoryStorage::new()), metrics.clone());
+    assert_eq!(storage.backend_name(), "memory");
+
+    let prompt = Prompt::new("metered".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+    metrics.record(PromptOperation::Create);
+    for _ in 0..2 {
+        assert!(storage.get_prompt(&prompt.id).await.unwrap().is_some());
+        metrics.record(PromptOperation::Get);
+    }
+    assert!(storage.delete_prompt(&prompt.id).await.unwrap());
+    metrics.record(PromptOperation::Delete);
+
+    let scraped = metrics.render().unwrap();
+    for line in [
+        r#"mcp_prompts_prompt_operations_total{operation="create"} 1"#,
+        r#"mcp_prompts_prompt_operations_total{operation="get"} 2"#,
+        r#"mcp_prompts_prompt_operations_total{operation="delete"} 1"#,
+        r#"mcp_prompts_storage_operation_duration_seconds_count{operation="save_prompt"} 1"#,
+        r#"mcp_prompts_storage_operation_duration_seconds_count{operation="get_prompt"} 2"#,
+        r#"mcp_prompts_storage_operation_duration_seconds_count{operation="delete_prompt"} 1"#,
+    ] {
+        assert!(scraped.lines().any(|l| l == line), "missing `{}` in:\n{}", line, scraped);
+    }
+    assert!(!scraped.contains(r#"operation="update""#));
+}
@@ -0,0 +1,255 @@
+use mcp_prompts_rs::catalog::{catalog_entries, map_entry, CATALOG_ID_KEY};
+use mcp_prompts_rs::models::prompt::{
+    merge_content, normalize_text, parse_prompt_json, unified_diff, Prompt, PromptParseError,
+};
+
+#[test]
+fn normalize_content_converts_crlf_and_trims_trailing_whitespace() {
+    let mut prompt = Prompt::new(
+        "normalize".to_string(),
+        "Hello {{ name }}  \r\n\tIndented line\t\r\nLast line   ".to_string(),
+        None,
+        None,
+    );
+    prompt.normalize_content();
+    assert_eq!(prompt.content, "Hello {{ name }}\n\tIndented line\nLast line");
+}
+
+#[test]
+fn normalize_content_is_idempotent() {
+    let raw = "a \r\nb\t\r\n\r\n  c  \n";
+    let once = normalize_text(raw);
+    let twice = normalize_text(&once);
+    assert_eq!(once, twice);
+}
+
+#[test]
+fn strict_parse_rejects_unknown_fields() {
+    let body = br#"{"name": "n", "content": "c", "category": null, "variables": null, "titel": "typo"}"#;
+    match parse_prompt_json(body, true) {
+        Err(PromptParseError::UnknownFields(fields)) => assert_eq!(fields, vec!["titel".to_string()]),
+        other => panic!("expected unknown field error, got {:?}", other),
+    }
+}
+
+#[test]
+fn lenient_parse_ignores_unknown_fields() {
+    let body = br#"{"name": "n", "content": "c", "category": null, "variables": null, "titel": "typo"}"#;
+    let prompt = parse_prompt_json(body, false).expect("lenient parse should succeed");
+    assert_eq!(prompt.name, "n");
+    assert_eq!(prompt.content, "c");
+}
+
+#[test]
+fn unset_optional_fields_are_omitted_when_serializing() {
+    let prompt = Prompt::new("n".to_string(), "c".to_string(), None, None);
+    let json = serde_json::to_value(&prompt).unwrap();
+    let object = json.as_object().unwrap();
+    for field in ["category", "variables", "extends", "render_quota_per_hour", "sections"] {
+        assert!(!object.contains_key(field), "{} should be omitted", field);
+    }
+}
+
+#[test]
+fn null_and_absent_optional_fields_deserialize_the_same() {
+    let with_nulls = parse_prompt_json(br#"{"name": "n", "content": "c", "category": null, "variables": null}"#, true)
+        .expect("explicit nulls should parse");
+    let absent = parse_prompt_json(br#"{"name": "n", "content": "c"}"#, true).expect("absent fields should parse");
+    assert_eq!(with_nulls.category, absent.category);
+    assert_eq!(with_nulls.variables, absent.variables);
+}
+
+#[test]
+fn projection_keeps_only_requested_fields() {
+    use mcp_prompts_rs::projection::{parse_fields, project};
+
+    let prompt = Prompt::new("n".to_string(), "c".to_string(), Some("writing".to_string()), None);
+    let fields = parse_fields("id, name,category").unwrap();
+    let projected = project(&vec![prompt.clone()], &fields).unwrap();
+    assert_eq!(
+        projected,
+        serde_json::json!([{ "id": prompt.id, "name": "n", "category": "writing" }])
+    );
+}
+
+#[test]
+fn projection_rejects_unknown_fields() {
+    let err = mcp_prompts_rs::projection::parse_fields("id,titel").unwrap_err();
+    assert!(err.contains("titel"));
+}
+
+#[test]
+fn catalog_entry_maps_messages_arguments_and_unmapped_fields() {
+    let document = serde_json::json!({
+        "prompts": [{
+            "id": "code-review",
+            "name": "Code Review",
+            "description": "Review a diff",
+            "tags": ["code"],
+            "arguments": [{ "name": "diff", "description": "The diff", "required": true }],
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "Review this:" } },
+                { "role": "user", "content": "{{ diff }}" }
+            ],
+            "isTemplate": true
+        }]
+    });
+    let entries = catalog_entries(document).unwrap();
+    let import = map_entry(&entries[0]).unwrap();
+
+    assert_eq!(import.prompt.name, "Code Review");
+    assert_eq!(import.prompt.content, "Review this:\n\n{{ diff }}");
+    assert_eq!(import.prompt.variables, Some(vec!["diff".to_string()]));
+    assert_eq!(import.catalog_id.as_deref(), Some("code-review"));
+    assert!(import.prompt.metadata_matches(CATALOG_ID_KEY, "code-review"));
+    assert_eq!(import.prompt.metadata["description"], serde_json::json!("Review a diff"));
+    assert_eq!(import.prompt.tags, vec!["code".to_string()]);
+    assert_eq!(import.unmapped, vec!["isTemplate".to_string()]);
+}
+
+#[test]
+fn catalog_entry_without_content_is_rejected() {
+    let entries = catalog_entries(serde_json::json!([{ "name": "empty" }])).unwrap();
+    assert!(map_entry(&entries[0]).is_err());
+}
+
+#[test]
+fn prompt_schema_lists_model_fields() {
+    let schema = serde_json::to_value(mcp_prompts_rs::models::prompt::prompt_schema()).unwrap();
+    let properties = schema["properties"].as_object().unwrap();
+    for field in mcp_prompts_rs::projection::PROMPT_FIELDS {
+        assert!(properties.contains_key(*field), "schema is missing {}", field);
+    }
+    let required: Vec<&str> = schema["required"].as_array().unwrap().iter().filter_map(|v| v.as_str()).collect();
+    assert_eq!(required, vec!["content", "name"]);
+}
+
+#[test]
+fn merge_content_combines_edits_to_different_lines() {
+    let base = "Intro\nBody\nOutro\n";
+    let current = "Intro, revised\nBody\nOutro\n";
+    let edited = "Intro\nBody\nOutro, revised\n";
+    assert_eq!(
+        merge_content(base, current, edited).unwrap(),
+        "Intro, revised\nBody\nOutro, revised\n"
+    );
+}
+
+#[test]
+fn merge_content_marks_overlapping_edits() {
+    let conflicted = merge_content("Hello\n", "Hi\n", "Hey\n").unwrap_err();
+    assert!(conflicted.contains("<<<<<<<"));
+    assert!(conflicted.contains("Hi\n") && conflicted.contains("Hey\n"));
+}
+
+#[test]
+fn unified_diff_shows_changed_lines_and_is_empty_when_equal() {
+    let diff = unified_diff("Hello Ada\nBye\n", "Hi Ada\nBye\n");
+    assert!(diff.contains("-Hello Ada\n"));
+    assert!(diff.contains("+Hi Ada\n"));
+    assert!(diff.contains(" Bye\n"));
+    assert_eq!(unified_diff("same\n", "same\n"), "");
+}
+
+#[test]
+fn prompt_tests_report_passes_diffs_and_render_errors() {
+    use mcp_prompts_rs::models::prompt::PromptTest;
+    use mcp_prompts_rs::template::RenderError;
+    use mcp_prompts_rs::testing::{TestCaseResult, TestReport};
+
+    let json = r#"{"name": "greet", "content": "Hello {{ name }}!", "tests": [
+        {"name": "ada", "arguments": {"name": "Ada"}, "expected_output": "Hello Ada!"},
+        {"arguments": {"name": "Bob"}, "expected_output": "Hello Bob."}
+    ]}"#;
+    let prompt = parse_prompt_json(json.as_bytes(), true).unwrap();
+    assert_eq!(prompt.tests.len(), 2);
+    assert_eq!(prompt.tests[1], PromptTest {
+        name: None,
+        arguments: serde_json::json!({ "name": "Bob" }).as_object().unwrap().clone(),
+        expected_output: "Hello Bob.".to_string(),
+    });
+
+    let pass = TestCaseResult::check(0, &prompt.tests[0], Ok("Hello Ada!".to_string()));
+    assert!(pass.passed && pass.diff.is_none());
+    let mismatch = TestCaseResult::check(1, &prompt.tests[1], Ok("Hello Bob!".to_string()));
+    assert!(!mismatch.passed);
+    assert_eq!(mismatch.label(), "#1");
+    assert!(mismatch.diff.as_deref().unwrap().contains("+Hello Bob!"));
+    let error = TestCaseResult::check(1, &prompt.tests[1], Err(RenderError::Template("boom".to_string())));
+    assert_eq!(error.error.as_deref(), Some("boom"));
+
+    let report = TestReport::new(&prompt, vec![pass, mismatch, error]);
+    assert_eq!((report.passed, report.failed), (1, 2));
+    assert!(!report.succeeded());
+}
+
+#[test]
+fn base64_content_decodes_and_is_checked() {
+    let json = r#"{"name": "pixel", "content": "iVBO\nRw0K", "content_encoding": "base64", "content_type": "image/png"}"#;
+    let mut prompt: Prompt = serde_json::from_str(json).unwrap();
+    assert!(prompt.is_encoded());
+    assert_eq!(prompt.decoded_content().unwrap(), b"\x89PNG\r\n");
+    assert!(prompt.check_content().is_ok());
+
+    prompt.normalize_content();
+    assert_eq!(prompt.content, "iVBO\nRw0K");
+
+    prompt.content_type = Some("not a type".to_string());
+    assert!(prompt.check_content().is_err());
+    prompt.content_type = None;
+    prompt.content = "not base64!".to_string();
+    assert!(prompt.check_content().is_err());
+    prompt.content_encoding = Some("gzip".to_string());
+    assert!(prompt.decoded_content().unwrap_err().contains("Unsupported content_encoding"));
+}
+
+#[test]
+fn validate_variables_reports_undeclared_names() {
+    let variables = Some(vec!["user_name".to_string(), "tone".to_string()]);
+    let mut prompt = Prompt::new("greet".to_string(), "Hi {{ usr_name }}, {{ day }}.".to_string(), None, variables);
+    let err = prompt.validate_variables().unwrap_err();
+    assert!(err.contains("usr_name, day"), "{}", err);
+
+    prompt.content = "Hi {{ user_name | upper }}.".to_string();
+    assert_eq!(prompt.validate_variables().unwrap(), vec!["tone".to_string()]);
+
+    // Prompts that don't declare their variables aren't checked
+    prompt.variables = None;
+    prompt.content = "Hi {{ anyone }}.".to_string();
+    assert!(prompt.validate_variables().unwrap().is_empty());
+}
+
+#[test]
+fn validate_variables_allows_loop_bindings_and_nested_attributes() {
+    let content = "{% for item in items %}{{ loop.index }}. {{ item.title }}{% set last = item %}{% endfor %}\n\
+                   {{ last }} by {{ author.name }} {{ globals.company }}";
+    let variables = Some(vec!["items".to_string(), "author".to_string()]);
+    let mut prompt = Prompt::new("list".to_string(), content.to_string(), None, variables);
+    assert_eq!(prompt.validate_variables(), Ok(Vec::new()));
+
+    prompt.content = "{% for item in items %}{{ itme.title }}{% endfor %}{{ author }}".to_string();
+    assert_eq!(prompt.validate_variables(), Err("Template uses undeclared variables: itme".to_string()));
+}
+
+#[test]
+fn etags_match_until_the_prompt_changes() {
+    let prompt = Prompt::new("etag".to_string(), "v1".to_string(), None, None);
+    let etag = prompt.etag();
+    assert!(etag.starts_with("W/\"1-"));
+    assert!(prompt.matches_if_match(&etag));
+    assert!(prompt.matches_if_match(etag.trim_start_matches("W/")));
+
+    let mut changed = prompt.clone();
+    changed.content = "v2".to_string();
+    changed.version += 1;
+    assert!(!changed.matches_if_match(&etag));
+    assert!(changed.matches_if_match(&format!("{}, {}", etag, changed.etag())));
+    assert!(changed.matches_if_match("*"));
+    assert!(changed.matches_if_match("\"2\""));
+
+    // Changes that keep the version, such as an owner transfer, still change the tag
+    let mut transferred = prompt.clone();
+    transferred.owner = Some("alice".to_string());
+    assert_ne!(transferred.etag(), etag);
+    assert!(!transferred.matches_if_match(&etag));
+}
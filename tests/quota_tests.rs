@@ -0,0 +1,42 @@
+use mcp_prompts_rs::quota::{RenderQuota, QUOTA_WINDOW};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+#[test]
+fn renders_beyond_the_limit_wait_for_the_oldest_to_leave_the_window() {
+    let quota = RenderQuota::new();
+    let id = Uuid::new_v4();
+    let start = Instant::now();
+
+    quota.try_acquire_at(&id, 2, start).unwrap();
+    quota.try_acquire_at(&id, 2, start + Duration::from_secs(10)).unwrap();
+    let retry_after = quota.try_acquire_at(&id, 2, start + Duration::from_secs(20)).unwrap_err();
+    assert_eq!(retry_after, QUOTA_WINDOW - Duration::from_secs(20));
+
+    // Once the first render is an hour old its slot is free again
+    assert!(quota.try_acquire_at(&id, 2, start + QUOTA_WINDOW).is_ok());
+    assert!(quota.try_acquire_at(&id, 2, start + QUOTA_WINDOW).is_err());
+}
+
+#[test]
+fn quotas_are_tracked_per_prompt() {
+    let quota = RenderQuota::new();
+    let now = Instant::now();
+    let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+    quota.try_acquire_at(&first, 1, now).unwrap();
+    assert!(quota.try_acquire_at(&first, 1, now).is_err());
+    assert!(quota.try_acquire_at(&second, 1, now).is_ok());
+}
+
+#[test]
+fn refunded_renders_do_not_count() {
+    let quota = RenderQuota::new();
+    let id = Uuid::new_v4();
+    let now = Instant::now();
+
+    let slot = quota.try_acquire_at(&id, 1, now).unwrap();
+    assert!(quota.try_acquire_at(&id, 1, now).is_err());
+    quota.refund(&id, slot);
+    assert!(quota.try_acquire_at(&id, 1, now).is_ok());
+}
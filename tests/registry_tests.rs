@@ -0,0 +1,82 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use mcp_prompts_rs::models::prompt::Prompt;
+use mcp_prompts_rs::storage::memory::InMemoryStorage;
+use mcp_prompts_rs::storage::registry::registered_storage_names;
+use mcp_prompts_rs::storage::{
+    create_storage, register_storage, PromptStorage, StorageFactory, StorageOptions, StorageSetupError,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn options() -> StorageOptions {
+    StorageOptions {
+        prompt_dir: PathBuf::from("./prompts"),
+        connection: None,
+        fsync: false,
+        namespace: None,
+    }
+}
+
+/// Hands out the same storage every time, so a test can see what the server would write to.
+struct SharedFactory(Arc<InMemoryStorage>);
+
+#[async_trait]
+impl StorageFactory for SharedFactory {
+    async fn create(&self, _options: &StorageOptions) -> Result<Arc<dyn PromptStorage>> {
+        Ok(self.0.clone())
+    }
+}
+
+#[tokio::test]
+async fn registered_backends_are_created_by_name() {
+    let shared = Arc::new(InMemoryStorage::new());
+    register_storage("registry-test-shared", SharedFactory(shared.clone()));
+    assert!(registered_storage_names().contains(&"registry-test-shared".to_string()));
+
+    let storage = create_storage("registry-test-shared", &options()).await.unwrap();
+    let prompt = Prompt::new("custom".to_string(), "Hello".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+    // Saving stamps `updated_at`, so compare what the caller wrote
+    let stored = shared.get_prompt(&prompt.id).await.unwrap().unwrap();
+    assert_eq!((stored.name, stored.content), (prompt.name, prompt.content));
+}
+
+#[tokio::test]
+async fn built_in_backends_are_registered_by_default() {
+    let names = registered_storage_names();
+    for name in ["filesystem", "memory", "postgres", "redis", "sqlite"] {
+        assert!(names.contains(&name.to_string()), "{} is not registered", name);
+    }
+    assert!(create_storage("memory", &options()).await.is_ok());
+}
+
+#[tokio::test]
+async fn unknown_backends_list_the_registered_names() {
+    let err = create_storage("registry-test-missing", &options()).await.err().unwrap();
+    match err.downcast_ref::<StorageSetupError>() {
+        Some(StorageSetupError::Unsupported { name, available }) => {
+            assert_eq!(name, "registry-test-missing");
+            assert!(available.contains(&"memory".to_string()));
+        }
+        other => panic!("expected an unsupported backend error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn networked_backends_require_a_connection() {
+    let err = create_storage("postgres", &options()).await.err().unwrap();
+    assert_eq!(
+        err.downcast_ref::<StorageSetupError>(),
+        Some(&StorageSetupError::MissingConnection { backend: "postgres".to_string() })
+    );
+}
+
+#[tokio::test]
+async fn invalid_namespaces_are_rejected_before_the_factory_runs() {
+    let options = StorageOptions {
+        namespace: Some("../escape".to_string()),
+        ..options()
+    };
+    assert!(create_storage("memory", &options).await.is_err());
+}
@@ -0,0 +1,55 @@
+use mcp_prompts_rs::models::prompt::Prompt;
+use mcp_prompts_rs::storage::memory::InMemoryStorage;
+use mcp_prompts_rs::template::{
+    render_prompt, run_with_limits, EngineMode, RenderError, RenderLimits, DEFAULT_MAX_TEMPLATE_DEPTH,
+    MAX_BLOCKING_RENDERS,
+};
+use serde_json::Map;
+use std::time::Duration;
+
+fn limits(timeout_ms: u64) -> RenderLimits {
+    RenderLimits { timeout: Duration::from_millis(timeout_ms), max_output_bytes: 1024 }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn timed_out_renders_keep_their_slot_until_they_finish() {
+    let slow = (0..MAX_BLOCKING_RENDERS).map(|_| {
+        run_with_limits(limits(20), async {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok("slow".to_string())
+        })
+    });
+    for result in futures_util::future::join_all(slow).await {
+        assert!(matches!(result, Err(RenderError::LimitExceeded(_))));
+    }
+
+    // Every slot is still taken by a render that timed out
+    let quick = run_with_limits(limits(50), async { Ok("quick".to_string()) }).await;
+    assert!(matches!(quick, Err(RenderError::LimitExceeded(_))));
+
+    let quick = run_with_limits(limits(5000), async { Ok("quick".to_string()) }).await;
+    assert_eq!(quick.unwrap(), "quick");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn huge_output_stops_the_render_at_the_limit() {
+    // Would produce 800 MB and take far longer than the time limit if rendered in full
+    let content = "{% for i in range(end=10000) %}{% for j in range(end=10000) %}xxxxxxxx{% endfor %}{% endfor %}";
+    for engine in [EngineMode::Tera, EngineMode::Handlebars] {
+        let content = match engine {
+            EngineMode::Handlebars => "{{#each rows}}{{#each ../rows}}xxxxxxxx{{/each}}{{/each}}".to_string(),
+            _ => content.to_string(),
+        };
+        let prompt = Prompt::new("huge".to_string(), content, None, None);
+        let mut arguments = Map::new();
+        arguments.insert("rows".to_string(), (0..10_000).collect::<Vec<u32>>().into());
+        let rendered = run_with_limits(limits(30_000), async move {
+            render_prompt(&InMemoryStorage::new(), &prompt, &arguments, engine, DEFAULT_MAX_TEMPLATE_DEPTH).await
+        })
+        .await;
+        match rendered {
+            Err(RenderError::LimitExceeded(message)) => assert!(message.contains("1024 bytes"), "{}", message),
+            other => panic!("{:?}: expected the output limit, got {:?}", engine, other.map(|s| s.len())),
+        }
+    }
+}
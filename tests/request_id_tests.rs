@@ -0,0 +1,32 @@
+use actix_web::{test, web, App, HttpMessage, HttpRequest, HttpResponse};
+use mcp_prompts_rs::request_id::{accept, RequestId, RequestIds, MAX_REQUEST_ID_LEN, REQUEST_ID_HEADER};
+
+async fn echo_id(req: HttpRequest) -> HttpResponse {
+    let id = req.extensions().get::<RequestId>().map(|id| id.0.clone()).unwrap_or_default();
+    HttpResponse::Ok().body(id)
+}
+
+#[actix_web::test]
+async fn responses_carry_the_request_id() {
+    let app = test::init_service(App::new().wrap(RequestIds).route("/", web::get().to(echo_id))).await;
+
+    // A supplied ID is echoed back and visible to handlers
+    let req = test::TestRequest::get().uri("/").insert_header((REQUEST_ID_HEADER, "client-42")).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "client-42");
+    assert_eq!(test::read_body(res).await, "client-42");
+
+    // Without one, or with an unusable one, a fresh ID is generated
+    let too_long = "x".repeat(MAX_REQUEST_ID_LEN + 1);
+    for supplied in [None, Some("has spaces"), Some(too_long.as_str())] {
+        let mut req = test::TestRequest::get().uri("/");
+        if let Some(supplied) = supplied {
+            req = req.insert_header((REQUEST_ID_HEADER, supplied));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        let id = res.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+        assert!(uuid::Uuid::parse_str(&id).is_ok(), "{}", id);
+        assert_eq!(test::read_body(res).await, id);
+    }
+    assert_eq!(accept(""), None);
+}
@@ -0,0 +1,43 @@
+use mcp_prompts_rs::models::prompt::Prompt;
+use mcp_prompts_rs::seed::{seed_if_empty, SeedOutcome};
+use mcp_prompts_rs::storage::memory::InMemoryStorage;
+use mcp_prompts_rs::storage::PromptStorage;
+
+fn write_seed(dir: &tempfile::TempDir, prompts: &[Prompt]) -> std::path::PathBuf {
+    let path = dir.path().join("seed.json");
+    std::fs::write(&path, serde_json::to_vec(prompts).unwrap()).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn seeds_an_empty_store_through_prepare() {
+    let dir = tempfile::tempdir().unwrap();
+    let prompt = Prompt::new("greeting".to_string(), "Hello  ".to_string(), None, None);
+    let path = write_seed(&dir, std::slice::from_ref(&prompt));
+    let storage = InMemoryStorage::new();
+
+    let outcome = seed_if_empty(&storage, &path, |p| p.content = p.content.trim_end().to_string()).await.unwrap();
+    assert_eq!(outcome, SeedOutcome::Seeded(1));
+    assert_eq!(storage.get_prompt(&prompt.id).await.unwrap().unwrap().content, "Hello");
+}
+
+#[tokio::test]
+async fn skips_a_store_that_already_has_prompts() {
+    let dir = tempfile::tempdir().unwrap();
+    let seeded = Prompt::new("seeded".to_string(), "content".to_string(), None, None);
+    let path = write_seed(&dir, std::slice::from_ref(&seeded));
+    let storage = InMemoryStorage::new();
+    storage.save_prompt(&Prompt::new("existing".to_string(), "content".to_string(), None, None)).await.unwrap();
+
+    assert_eq!(seed_if_empty(&storage, &path, |_| {}).await.unwrap(), SeedOutcome::Skipped);
+    assert!(storage.get_prompt(&seeded.id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn an_empty_seed_file_is_not_reported_as_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_seed(&dir, &[]);
+    let storage = InMemoryStorage::new();
+
+    assert_eq!(seed_if_empty(&storage, &path, |_| {}).await.unwrap(), SeedOutcome::Seeded(0));
+}
@@ -0,0 +1,889 @@
+use mcp_prompts_rs::models::prompt::Prompt;
+use mcp_prompts_rs::storage::filesystem::FileSystemStorage;
+use mcp_prompts_rs::storage::PromptStorage;
+
+#[tokio::test]
+async fn filesystem_reads_prompt_file_with_utf8_bom() {
+    let dir = tempfile::tempdir().unwrap();
+    let prompt = Prompt::new("bom".to_string(), "Héllo {{ name }}".to_string(), None, None);
+    let json = serde_json::to_string(&prompt).unwrap();
+    let mut bytes = "\u{feff}".as_bytes().to_vec();
+    bytes.extend_from_slice(json.as_bytes());
+    std::fs::write(dir.path().join(format!("{}.json", prompt.id)), bytes).unwrap();
+
+    let storage = FileSystemStorage::new(dir.path());
+    let loaded = storage.get_prompt(&prompt.id).await.unwrap();
+    assert_eq!(loaded, Some(prompt));
+}
+
+#[tokio::test]
+async fn filesystem_writes_never_emit_bom() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let prompt = Prompt::new("no-bom".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+
+    let bytes = std::fs::read(dir.path().join(format!("{}.json", prompt.id))).unwrap();
+    assert!(!bytes.starts_with("\u{feff}".as_bytes()));
+}
+
+#[tokio::test]
+async fn filesystem_index_tracks_saves_deletes_and_external_edits() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let first = Prompt::new("first".to_string(), "one".to_string(), Some("writing/email".to_string()), None);
+    let second = Prompt::new("second".to_string(), "two".to_string(), None, None);
+    storage.save_prompt(&first).await.unwrap();
+    storage.save_prompt(&second).await.unwrap();
+
+    let summaries = storage.list_prompt_summaries().await.unwrap();
+    assert_eq!(summaries.len(), 2);
+    assert!(dir.path().join("index.json").exists());
+
+    storage.delete_prompt(&second.id).await.unwrap();
+    let summaries = storage.list_prompt_summaries().await.unwrap();
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].category.as_deref(), Some("writing/email"));
+
+    // A prompt file added behind the server's back is picked up, even by a fresh instance
+    let external = Prompt::new("external".to_string(), "three".to_string(), None, None);
+    std::fs::write(
+        dir.path().join(format!("{}.json", external.id)),
+        serde_json::to_string(&external).unwrap(),
+    )
+    .unwrap();
+    let reopened = FileSystemStorage::new(dir.path());
+    let mut names: Vec<String> = reopened
+        .list_prompt_summaries()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["external".to_string(), "first".to_string()]);
+}
+
+#[tokio::test]
+async fn filesystem_rebuilds_corrupt_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let prompt = Prompt::new("indexed".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+    std::fs::write(dir.path().join("index.json"), b"not json").unwrap();
+
+    let reopened = FileSystemStorage::new(dir.path());
+    let summaries = reopened.list_prompt_summaries().await.unwrap();
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].id, prompt.id);
+}
+
+#[tokio::test]
+async fn filesystem_lists_prompts_by_name_then_id() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    for name in ["beta", "alpha", "Gamma", "alpha"] {
+        storage
+            .save_prompt(&Prompt::new(name.to_string(), "content".to_string(), None, None))
+            .await
+            .unwrap();
+    }
+
+    let prompts = storage.list_prompts().await.unwrap();
+    let names: Vec<&str> = prompts.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["Gamma", "alpha", "alpha", "beta"]);
+    assert!(prompts[1].id < prompts[2].id);
+
+    let summaries = storage.list_prompt_summaries().await.unwrap();
+    let summary_ids: Vec<_> = summaries.iter().map(|s| s.id).collect();
+    let prompt_ids: Vec<_> = prompts.iter().map(|p| p.id).collect();
+    assert_eq!(summary_ids, prompt_ids);
+}
+
+#[tokio::test]
+async fn filesystem_pages_match_the_full_listing() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    for name in ["delta", "beta", "alpha", "echo", "charlie"] {
+        storage
+            .save_prompt(&Prompt::new(name.to_string(), "content".to_string(), None, None))
+            .await
+            .unwrap();
+    }
+    let names = |prompts: Vec<Prompt>| prompts.into_iter().map(|p| p.name).collect::<Vec<_>>();
+
+    assert_eq!(names(storage.list_prompts_paginated(0, 2).await.unwrap()), vec!["alpha", "beta"]);
+    assert_eq!(names(storage.list_prompts_paginated(2, 2).await.unwrap()), vec!["charlie", "delta"]);
+    // The last page is partial
+    assert_eq!(names(storage.list_prompts_paginated(4, 2).await.unwrap()), vec!["echo"]);
+    assert!(storage.list_prompts_paginated(5, 2).await.unwrap().is_empty());
+    assert!(storage.list_prompts_paginated(100, 2).await.unwrap().is_empty());
+    assert!(storage.list_prompts_paginated(0, 0).await.unwrap().is_empty());
+    assert_eq!(
+        names(storage.list_prompts_paginated(0, usize::MAX).await.unwrap()),
+        names(storage.list_prompts().await.unwrap())
+    );
+}
+
+#[tokio::test]
+async fn filesystem_lists_only_newest_file_claiming_an_id() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let mut prompt = Prompt::new("original".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+
+    // A copy under another file name that still claims the original id, edited later
+    prompt.name = "copy".to_string();
+    let copy_path = dir.path().join(format!("{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(&copy_path, serde_json::to_string(&prompt).unwrap()).unwrap();
+    let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+    std::fs::File::options().write(true).open(&copy_path).unwrap().set_modified(later).unwrap();
+
+    let prompts = storage.list_prompts().await.unwrap();
+    assert_eq!(prompts.len(), 1);
+    assert_eq!(prompts[0].id, prompt.id);
+    assert_eq!(prompts[0].name, "copy");
+}
+
+#[tokio::test]
+async fn filesystem_maintenance_removes_temp_files_and_rebuilds_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let prompt = Prompt::new("kept".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+    std::fs::write(dir.path().join("index.json.tmp"), b"partial").unwrap();
+    std::fs::write(dir.path().join("index.json"), b"not json").unwrap();
+
+    let actions = storage.run_maintenance().await.unwrap();
+    assert_eq!(actions.len(), 2);
+    assert!(!dir.path().join("index.json.tmp").exists());
+    assert!(dir.path().join(format!("{}.json", prompt.id)).exists());
+
+    let summaries = FileSystemStorage::new(dir.path()).list_prompt_summaries().await.unwrap();
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].id, prompt.id);
+}
+
+#[tokio::test]
+async fn filesystem_lists_prompts_by_content_length() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    for (name, content) in [("short", "hi"), ("medium", "héllo"), ("long", "a much longer prompt")] {
+        storage
+            .save_prompt(&Prompt::new(name.to_string(), content.to_string(), None, None))
+            .await
+            .unwrap();
+    }
+
+    let names = |prompts: Vec<Prompt>| prompts.into_iter().map(|p| p.name).collect::<Vec<_>>();
+    assert_eq!(names(storage.list_prompts_by_length(Some(3), Some(5)).await.unwrap()), vec!["medium"]);
+    assert_eq!(names(storage.list_prompts_by_length(None, Some(5)).await.unwrap()), vec!["medium", "short"]);
+    assert_eq!(names(storage.list_prompts_by_length(Some(6), None).await.unwrap()), vec!["long"]);
+}
+
+#[tokio::test]
+async fn filesystem_stores_attachments_until_prompt_is_deleted() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let prompt = Prompt::new("bundle".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+
+    storage.save_attachment(&prompt.id, "example.txt", b"hello").await.unwrap();
+    storage.save_attachment(&prompt.id, "diagram.png", &[0x89, 0x50]).await.unwrap();
+    assert_eq!(
+        storage.get_attachment(&prompt.id, "example.txt").await.unwrap(),
+        Some(b"hello".to_vec())
+    );
+    let names: Vec<(String, u64)> = storage
+        .list_attachments(&prompt.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|a| (a.name, a.size))
+        .collect();
+    assert_eq!(names, vec![("diagram.png".to_string(), 2), ("example.txt".to_string(), 5)]);
+    // Attachment directories are not mistaken for prompts
+    assert_eq!(storage.list_prompts().await.unwrap().len(), 1);
+
+    storage.delete_prompt(&prompt.id).await.unwrap();
+    assert!(storage.list_attachments(&prompt.id).await.unwrap().is_empty());
+    assert_eq!(storage.get_attachment(&prompt.id, "example.txt").await.unwrap(), None);
+}
+
+#[test]
+fn attachment_names_must_be_plain_file_names() {
+    use mcp_prompts_rs::attachments::is_valid_name;
+    assert!(is_valid_name("example-1.v2_final.png"));
+    for name in ["", ".hidden", "../prompt.json", "dir/file", "with space.txt"] {
+        assert!(!is_valid_name(name), "{:?} should be rejected", name);
+    }
+}
+
+#[tokio::test]
+async fn filesystem_category_records_round_trip() {
+    use mcp_prompts_rs::models::Category;
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    assert!(storage.list_categories().await.unwrap().is_empty());
+
+    let mut email = Category::new("writing/email");
+    email.color = Some("#3b82f6".to_string());
+    storage.save_category(&email).await.unwrap();
+    storage.save_category(&Category::new("code")).await.unwrap();
+
+    let names: Vec<String> = storage.list_categories().await.unwrap().into_iter().map(|c| c.name).collect();
+    assert_eq!(names, vec!["code".to_string(), "writing/email".to_string()]);
+    assert_eq!(storage.get_category("writing/email").await.unwrap(), Some(email));
+    // The category file is not mistaken for a prompt
+    assert!(storage.list_prompts().await.unwrap().is_empty());
+
+    assert!(storage.delete_category("code").await.unwrap());
+    assert!(!storage.delete_category("code").await.unwrap());
+    assert_eq!(FileSystemStorage::new(dir.path()).list_categories().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn filesystem_collections_round_trip() {
+    use mcp_prompts_rs::models::Collection;
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let (first, second) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+    let mut onboarding = Collection::new("onboarding", vec![second, first]);
+    storage.save_collection(&onboarding).await.unwrap();
+    storage.save_collection(&Collection::new("archive", Vec::new())).await.unwrap();
+
+    let names: Vec<String> = storage.list_collections().await.unwrap().into_iter().map(|c| c.name).collect();
+    assert_eq!(names, vec!["archive".to_string(), "onboarding".to_string()]);
+    assert_eq!(storage.get_collection(&onboarding.id).await.unwrap(), Some(onboarding.clone()));
+    assert!(storage.list_prompts().await.unwrap().is_empty());
+
+    onboarding.prompt_ids.push(first);
+    storage.save_collection(&onboarding).await.unwrap();
+    let reloaded = FileSystemStorage::new(dir.path()).get_collection(&onboarding.id).await.unwrap().unwrap();
+    assert_eq!(reloaded.prompt_ids, vec![second, first, first]);
+
+    assert!(storage.delete_collection(&onboarding.id).await.unwrap());
+    assert!(!storage.delete_collection(&onboarding.id).await.unwrap());
+}
+
+#[tokio::test]
+async fn filesystem_fsync_writes_round_trip() {
+    use mcp_prompts_rs::models::Collection;
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path()).with_fsync(true);
+    let prompt = Prompt::new("synced".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+    storage.save_attachment(&prompt.id, "notes.txt", b"notes").await.unwrap();
+    let collection = Collection::new("synced", vec![prompt.id]);
+    storage.save_collection(&collection).await.unwrap();
+
+    let reopened = FileSystemStorage::new(dir.path());
+    let loaded = reopened.get_prompt(&prompt.id).await.unwrap().unwrap();
+    assert_eq!(Prompt { updated_at: prompt.updated_at, ..loaded }, prompt);
+    assert_eq!(reopened.get_attachment(&prompt.id, "notes.txt").await.unwrap(), Some(b"notes".to_vec()));
+    assert_eq!(reopened.get_collection(&collection.id).await.unwrap(), Some(collection));
+    assert!(!dir.path().join("collections.json.tmp").exists());
+
+    assert!(storage.delete_prompt(&prompt.id).await.unwrap());
+    assert!(reopened.is_deleted(&prompt.id).await.unwrap());
+}
+
+#[tokio::test]
+async fn resaving_keeps_created_at_and_advances_updated_at() {
+    use mcp_prompts_rs::storage::sqlite::SqliteStorage;
+    use mcp_prompts_rs::storage::StorageConfig;
+
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite://{}", dir.path().join("prompts.db").display());
+    let sqlite = SqliteStorage::new(&StorageConfig::new(url.as_str())).await.unwrap();
+    sqlite.init_schema().await.unwrap();
+    let backends: Vec<Box<dyn PromptStorage>> = vec![Box::new(FileSystemStorage::new(dir.path())), Box::new(sqlite)];
+
+    for storage in backends {
+        let prompt = Prompt::new("stamped".to_string(), "v1".to_string(), None, None);
+        storage.save_prompt(&prompt).await.unwrap();
+        let first = storage.get_prompt(&prompt.id).await.unwrap().unwrap();
+        assert_eq!(first.created_at, prompt.created_at);
+
+        // An update built from a request body carries a fresh created_at, which is ignored
+        let mut update = Prompt::new("stamped".to_string(), "v2".to_string(), None, None);
+        update.id = prompt.id;
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        storage.save_prompt(&update).await.unwrap();
+        let second = storage.get_prompt(&prompt.id).await.unwrap().unwrap();
+        assert_eq!(second.content, "v2");
+        assert_eq!(second.created_at, first.created_at, "{}", storage.backend_name());
+        assert!(second.updated_at > first.updated_at, "{}", storage.backend_name());
+    }
+}
+
+#[tokio::test]
+async fn edits_keep_earlier_versions_until_the_prompt_is_deleted() {
+    use mcp_prompts_rs::storage::memory::InMemoryStorage;
+    use mcp_prompts_rs::storage::sqlite::SqliteStorage;
+    use mcp_prompts_rs::storage::StorageConfig;
+
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite://{}", dir.path().join("prompts.db").display());
+    let sqlite = SqliteStorage::new(&StorageConfig::new(url.as_str())).await.unwrap();
+    sqlite.init_schema().await.unwrap();
+    let backends: Vec<Box<dyn PromptStorage>> =
+        vec![Box::new(FileSystemStorage::new(dir.path())), Box::new(InMemoryStorage::new()), Box::new(sqlite)];
+
+    for storage in backends {
+        let name = storage.backend_name();
+        let mut prompt = Prompt::new("audited".to_string(), "first".to_string(), None, None);
+        storage.save_prompt(&prompt).await.unwrap();
+        for content in ["second", "third"] {
+            prompt.content = content.to_string();
+            prompt.version += 1;
+            storage.save_prompt(&prompt).await.unwrap();
+        }
+        // Saving without a new version, as locking does, replaces the current version in place
+        prompt.locked = true;
+        storage.save_prompt(&prompt).await.unwrap();
+
+        assert_eq!(storage.list_prompt_versions(&prompt.id).await.unwrap(), vec![1, 2, 3], "{}", name);
+        let first = storage.get_prompt_version(&prompt.id, 1).await.unwrap().unwrap();
+        assert_eq!((first.version, first.content.as_str()), (1, "first"), "{}", name);
+        let second = storage.get_prompt_version(&prompt.id, 2).await.unwrap().unwrap();
+        assert_eq!((second.version, second.content.as_str()), (2, "second"), "{}", name);
+        let current = storage.get_prompt_version(&prompt.id, 3).await.unwrap().unwrap();
+        assert!(current.locked, "{}", name);
+        assert_eq!(storage.get_prompt_version(&prompt.id, 4).await.unwrap(), None, "{}", name);
+
+        assert!(storage.delete_prompt(&prompt.id).await.unwrap());
+        assert!(storage.list_prompt_versions(&prompt.id).await.unwrap().is_empty(), "{}", name);
+        assert_eq!(storage.get_prompt_version(&prompt.id, 1).await.unwrap(), None, "{}", name);
+    }
+}
+
+#[tokio::test]
+async fn filesystem_namespaces_are_isolated_subdirectories() {
+    use mcp_prompts_rs::storage::{create_storage, StorageOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let options = |namespace: Option<&str>| StorageOptions {
+        prompt_dir: dir.path().to_path_buf(),
+        connection: None,
+        fsync: false,
+        namespace: namespace.map(str::to_string),
+    };
+    assert_eq!(options(Some("staging")).storage_dir(), dir.path().join("staging"));
+    assert_eq!(options(None).storage_dir(), dir.path());
+    for invalid in ["", "../prod", "a/b", "white space"] {
+        assert!(create_storage("filesystem", &options(Some(invalid))).await.is_err(), "{:?}", invalid);
+    }
+
+    let dev = create_storage("filesystem", &options(Some("dev"))).await.unwrap();
+    let prod = create_storage("filesystem", &options(Some("prod"))).await.unwrap();
+    let prompt = Prompt::new("scoped".to_string(), "content".to_string(), None, None);
+    dev.save_prompt(&prompt).await.unwrap();
+
+    let loaded = dev.get_prompt(&prompt.id).await.unwrap().unwrap();
+    assert_eq!(Prompt { updated_at: prompt.updated_at, ..loaded }, prompt);
+    assert_eq!(prod.get_prompt(&prompt.id).await.unwrap(), None);
+    assert!(prod.list_prompts().await.unwrap().is_empty());
+    assert!(dir.path().join("dev").join(format!("{}.json", prompt.id)).exists());
+}
+
+#[tokio::test]
+async fn ranked_search_orders_by_matches_and_highlights() {
+    use mcp_prompts_rs::search::SearchOptions;
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let once = Prompt::new("a-once".to_string(), "Review the code.".to_string(), None, None);
+    let twice = Prompt::new("b-twice".to_string(), format!("{}Code review: check the CODE.", "x".repeat(60)), None, None);
+    storage.save_prompt(&once).await.unwrap();
+    storage.save_prompt(&twice).await.unwrap();
+
+    let hits = storage.search_prompts_ranked("code", &SearchOptions::default(), true).await.unwrap();
+    let names: Vec<&str> = hits.iter().map(|h| h.prompt.name.as_str()).collect();
+    assert_eq!(names, vec!["b-twice", "a-once"]);
+    assert_eq!(hits[0].matches, 2);
+    assert_eq!(hits[1].snippet.as_deref(), Some("Review the <mark>code</mark>."));
+    let snippet = hits[0].snippet.as_deref().unwrap();
+    assert!(snippet.starts_with('…') && snippet.contains("<mark>Code</mark> review"));
+}
+
+#[tokio::test]
+async fn ranked_search_puts_name_matches_above_content_matches() {
+    use mcp_prompts_rs::search::SearchOptions;
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let content_only = Prompt::new("a-reviewer".to_string(), "Summarize, then summarize again.".to_string(), None, None);
+    let by_name = Prompt::new("b-summarize".to_string(), "Keep it short.".to_string(), None, None);
+    let unrelated = Prompt::new("c-other".to_string(), "Translate the text.".to_string(), None, None);
+    for prompt in [&content_only, &by_name, &unrelated] {
+        storage.save_prompt(prompt).await.unwrap();
+    }
+
+    let hits = storage.search_prompts_ranked("SUMMARIZE", &SearchOptions::default(), false).await.unwrap();
+    let names: Vec<&str> = hits.iter().map(|h| h.prompt.name.as_str()).collect();
+    assert_eq!(names, vec!["b-summarize", "a-reviewer"]);
+    assert_eq!(hits[1].matches, 2);
+}
+
+#[tokio::test]
+async fn ranked_search_highlights_accent_folded_matches() {
+    use mcp_prompts_rs::search::SearchOptions;
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    storage.save_prompt(&Prompt::new("menu".to_string(), "Order a Café au lait".to_string(), None, None)).await.unwrap();
+
+    let hits = storage.search_prompts_ranked("cafe", &SearchOptions { fold: true }, true).await.unwrap();
+    assert_eq!(hits[0].snippet.as_deref(), Some("Order a <mark>Café</mark> au lait"));
+}
+
+/// Runs against the database in `TEST_DATABASE_URL`; skipped when it isn't set.
+#[tokio::test]
+async fn postgres_listing_survives_malformed_jsonb() {
+    use mcp_prompts_rs::storage::postgres::PostgresStorage;
+    use mcp_prompts_rs::storage::StorageConfig;
+
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set; skipping PostgreSQL test");
+        return;
+    };
+    let storage = PostgresStorage::new(&StorageConfig::new(url.as_str())).await.unwrap();
+    storage.init_schema().await.unwrap();
+    let good = Prompt::new("jsonb-good".to_string(), "fine".to_string(), None, Some(vec!["x".to_string()]));
+    let bad = Prompt::new("jsonb-bad".to_string(), "broken".to_string(), None, Some(vec!["y".to_string()]));
+    storage.save_prompt(&good).await.unwrap();
+    storage.save_prompt(&bad).await.unwrap();
+
+    // Values an older, incompatible version could have written
+    let pool = sqlx::PgPool::connect(&url).await.unwrap();
+    sqlx::query(r#"UPDATE prompts SET variables = '"y"'::jsonb, sections = '[1, 2]'::jsonb, "references" = '"none"'::jsonb WHERE id = $1"#)
+        .bind(bad.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let listed = storage.list_prompts().await.unwrap();
+    let loaded_bad = listed.iter().find(|p| p.id == bad.id).unwrap();
+    assert_eq!(loaded_bad.variables, None);
+    assert_eq!(loaded_bad.sections, None);
+    assert!(loaded_bad.references.is_empty());
+    assert_eq!(listed.iter().find(|p| p.id == good.id).unwrap().variables, good.variables);
+
+    storage.delete_prompt(&good.id).await.unwrap();
+    storage.delete_prompt(&bad.id).await.unwrap();
+}
+
+#[tokio::test]
+async fn postgres_namespaces_are_isolated() {
+    use mcp_prompts_rs::storage::postgres::PostgresStorage;
+    use mcp_prompts_rs::storage::StorageConfig;
+
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set; skipping PostgreSQL test");
+        return;
+    };
+    let config = StorageConfig::new(url.as_str());
+    let dev = PostgresStorage::new(&config).await.unwrap().with_namespace("test-dev");
+    let prod = PostgresStorage::new(&config).await.unwrap().with_namespace("test-prod");
+    dev.init_schema().await.unwrap();
+    let prompt = Prompt::new("namespaced".to_string(), "content".to_string(), None, None);
+    dev.save_prompt(&prompt).await.unwrap();
+
+    assert_eq!(dev.get_prompt(&prompt.id).await.unwrap().map(|p| p.id), Some(prompt.id));
+    assert_eq!(prod.get_prompt(&prompt.id).await.unwrap(), None);
+    assert!(prod.list_prompts().await.unwrap().iter().all(|p| p.id != prompt.id));
+    // The id belongs to the other namespace, so it can be neither overwritten nor deleted here
+    assert!(prod.save_prompt(&prompt).await.is_err());
+    assert!(!prod.delete_prompt(&prompt.id).await.unwrap());
+
+    assert!(dev.delete_prompt(&prompt.id).await.unwrap());
+    assert!(dev.is_deleted(&prompt.id).await.unwrap());
+    assert!(!prod.is_deleted(&prompt.id).await.unwrap());
+}
+
+#[tokio::test]
+async fn postgres_delete_reports_whether_a_row_was_removed() {
+    use mcp_prompts_rs::storage::postgres::PostgresStorage;
+    use mcp_prompts_rs::storage::StorageConfig;
+
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set; skipping PostgreSQL test");
+        return;
+    };
+    let storage = PostgresStorage::new(&StorageConfig::new(url.as_str())).await.unwrap();
+    storage.init_schema().await.unwrap();
+    let prompt = Prompt::new("delete-me".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+
+    assert!(storage.delete_prompt(&prompt.id).await.unwrap());
+    assert!(!storage.delete_prompt(&prompt.id).await.unwrap());
+    assert!(!storage.delete_prompt(&uuid::Uuid::new_v4()).await.unwrap());
+}
+
+/// Runs against the server in `TEST_REDIS_URL`; skipped when it isn't set.
+#[tokio::test]
+async fn redis_keeps_prompts_versions_and_deletions() {
+    use mcp_prompts_rs::storage::redis::RedisStorage;
+    use mcp_prompts_rs::storage::StorageConfig;
+
+    let Ok(url) = std::env::var("TEST_REDIS_URL") else {
+        eprintln!("TEST_REDIS_URL not set; skipping Redis test");
+        return;
+    };
+    // A namespace of its own keeps the test's keys apart from other data on the server
+    let namespace = format!("test-{}", uuid::Uuid::new_v4().simple());
+    let storage = RedisStorage::new(&StorageConfig::new(url.as_str())).await.unwrap().with_namespace(namespace.as_str());
+    let other = RedisStorage::new(&StorageConfig::new(url.as_str())).await.unwrap().with_namespace(format!("{}-other", namespace));
+
+    // Create
+    let mut beta = Prompt::new("beta".to_string(), "v1".to_string(), Some("writing".to_string()), None);
+    let alpha = Prompt::new("alpha".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&beta).await.unwrap();
+    storage.save_prompt(&alpha).await.unwrap();
+    let loaded = storage.get_prompt(&beta.id).await.unwrap().unwrap();
+    assert_eq!(Prompt { updated_at: beta.updated_at, ..loaded.clone() }, beta);
+    let names: Vec<String> = storage.list_prompts().await.unwrap().into_iter().map(|p| p.name).collect();
+    assert_eq!(names, vec!["alpha", "beta"]);
+    assert_eq!(storage.count_prompts().await.unwrap(), 2);
+    assert!(other.list_prompts().await.unwrap().is_empty());
+
+    // Update, keeping the first version and the creation time
+    beta.content = "v2".to_string();
+    beta.version = 2;
+    storage.save_prompt(&beta).await.unwrap();
+    let updated = storage.get_prompt(&beta.id).await.unwrap().unwrap();
+    assert_eq!((updated.content.as_str(), updated.created_at), ("v2", loaded.created_at));
+    assert_eq!(storage.list_prompt_versions(&beta.id).await.unwrap(), vec![1, 2]);
+    assert_eq!(storage.get_prompt_version(&beta.id, 1).await.unwrap().unwrap().content, "v1");
+
+    // Delete
+    for prompt in [&alpha, &beta] {
+        assert!(storage.delete_prompt(&prompt.id).await.unwrap());
+        assert!(!storage.delete_prompt(&prompt.id).await.unwrap());
+        assert_eq!(storage.get_prompt(&prompt.id).await.unwrap(), None);
+        assert!(storage.is_deleted(&prompt.id).await.unwrap());
+    }
+    assert!(storage.list_prompt_versions(&beta.id).await.unwrap().is_empty());
+    assert_eq!(storage.count_prompts().await.unwrap(), 0);
+
+    let client = redis::Client::open(url.as_str()).unwrap();
+    let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+    redis::cmd("DEL").arg(format!("{}:deleted_prompts", namespace)).query_async::<_, ()>(&mut connection).await.unwrap();
+}
+
+#[tokio::test]
+async fn sqlite_round_trips_prompts_through_a_database_file() {
+    use mcp_prompts_rs::storage::sqlite::SqliteStorage;
+    use mcp_prompts_rs::storage::{DeleteOutcome, StorageConfig};
+
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite://{}", dir.path().join("prompts.db").display());
+    let storage = SqliteStorage::new(&StorageConfig::new(url.as_str())).await.unwrap();
+    storage.init_schema().await.unwrap();
+
+    let mut beta = Prompt::new("beta".to_string(), "Hi {{ name }}".to_string(), Some("writing".to_string()), Some(vec!["name".to_string()]));
+    beta.metadata.insert("team".to_string(), serde_json::json!("growth"));
+    let alpha = Prompt::new("alpha".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&beta).await.unwrap();
+    storage.save_prompt(&alpha).await.unwrap();
+    let loaded = storage.get_prompt(&beta.id).await.unwrap().unwrap();
+    assert_eq!(Prompt { updated_at: beta.updated_at, ..loaded }, beta);
+
+    beta.content = "Hello {{ name }}".to_string();
+    beta.version = 2;
+    storage.save_prompt(&beta).await.unwrap();
+    let names: Vec<String> = storage.list_prompts().await.unwrap().into_iter().map(|p| p.name).collect();
+    assert_eq!(names, vec!["alpha", "beta"]);
+    assert_eq!(storage.get_prompt(&beta.id).await.unwrap().unwrap().content, "Hello {{ name }}");
+    assert_eq!(storage.count_prompts().await.unwrap(), 2);
+
+    assert_eq!(
+        storage.delete_prompt_if_version(&beta.id, 1).await.unwrap(),
+        DeleteOutcome::VersionMismatch { current: 2 }
+    );
+    assert!(storage.delete_prompt(&beta.id).await.unwrap());
+    assert!(!storage.delete_prompt(&beta.id).await.unwrap());
+    assert_eq!(storage.get_prompt(&beta.id).await.unwrap(), None);
+    assert!(storage.is_deleted(&beta.id).await.unwrap());
+    assert_eq!(storage.list_prompts().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn built_in_backends_implement_the_uuid_keyed_trait() {
+    use mcp_prompts_rs::storage::memory::InMemoryStorage;
+    use mcp_prompts_rs::storage::postgres::PostgresStorage;
+    use mcp_prompts_rs::storage::redis::RedisStorage;
+    use mcp_prompts_rs::storage::registry::registered_storage_names;
+    use mcp_prompts_rs::storage::sqlite::SqliteStorage;
+    use mcp_prompts_rs::storage::{create_storage, StorageOptions};
+
+    // Fails to compile if a backend drifts from the single PromptStorage trait
+    fn assert_backend<T: PromptStorage>() {}
+    assert_backend::<FileSystemStorage>();
+    assert_backend::<InMemoryStorage>();
+    assert_backend::<PostgresStorage>();
+    assert_backend::<RedisStorage>();
+    assert_backend::<SqliteStorage>();
+    for name in ["filesystem", "memory", "postgres", "redis", "sqlite"] {
+        assert!(registered_storage_names().contains(&name.to_string()));
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let options = StorageOptions {
+        prompt_dir: dir.path().to_path_buf(),
+        connection: None,
+        fsync: false,
+        namespace: None,
+    };
+    let storage = create_storage("filesystem", &options).await.unwrap();
+    let prompt = Prompt::new("round-trip".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+    let loaded = storage.get_prompt(&prompt.id).await.unwrap().unwrap();
+    assert_eq!((loaded.id, loaded.name.as_str()), (prompt.id, "round-trip"));
+}
+
+#[tokio::test]
+async fn memory_storage_keeps_concurrent_writes() {
+    use mcp_prompts_rs::storage::memory::InMemoryStorage;
+    use std::sync::Arc;
+
+    let storage = Arc::new(InMemoryStorage::new());
+    let tasks: Vec<_> = (0..32)
+        .map(|i| {
+            let storage = Arc::clone(&storage);
+            tokio::spawn(async move {
+                let prompt = Prompt::new(format!("prompt-{:02}", i), "content".to_string(), None, None);
+                storage.save_prompt(&prompt).await.unwrap();
+                prompt.id
+            })
+        })
+        .collect();
+    let mut ids = Vec::new();
+    for task in tasks {
+        ids.push(task.await.unwrap());
+    }
+
+    assert_eq!(storage.count_prompts().await.unwrap(), 32);
+    let listed = storage.list_prompts().await.unwrap();
+    assert_eq!(listed.first().map(|p| p.name.as_str()), Some("prompt-00"));
+    for id in &ids {
+        assert!(storage.get_prompt(id).await.unwrap().is_some());
+    }
+}
+
+#[tokio::test]
+async fn memory_storage_delete_reports_missing_ids() {
+    use mcp_prompts_rs::storage::memory::InMemoryStorage;
+
+    let storage = InMemoryStorage::new();
+    assert!(!storage.delete_prompt(&uuid::Uuid::new_v4()).await.unwrap());
+
+    let prompt = Prompt::new("short-lived".to_string(), "content".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+    assert!(storage.delete_prompt(&prompt.id).await.unwrap());
+    assert!(!storage.delete_prompt(&prompt.id).await.unwrap());
+    assert!(storage.is_deleted(&prompt.id).await.unwrap());
+    assert_eq!(storage.get_prompt(&prompt.id).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn listing_by_category_matches_exactly_and_skips_uncategorized() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    for (name, category) in [
+        ("review", Some("development")),
+        ("refactor", Some("development")),
+        ("essay", Some("writing")),
+        ("tests", Some("development/testing")),
+        ("loose", None),
+    ] {
+        storage
+            .save_prompt(&Prompt::new(name.to_string(), "content".to_string(), category.map(str::to_string), None))
+            .await
+            .unwrap();
+    }
+
+    let development = storage.list_prompts_by_category("development").await.unwrap();
+    let names: Vec<&str> = development.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["refactor", "review"]);
+    assert!(storage.list_prompts_by_category("marketing").await.unwrap().is_empty());
+    assert!(storage.list_prompts_by_category("").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn prompts_match_any_one_of_their_normalized_tags() {
+    use mcp_prompts_rs::storage::memory::InMemoryStorage;
+    use mcp_prompts_rs::storage::sqlite::SqliteStorage;
+    use mcp_prompts_rs::storage::StorageConfig;
+
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite://{}", dir.path().join("prompts.db").display());
+    let sqlite = SqliteStorage::new(&StorageConfig::new(url.as_str())).await.unwrap();
+    sqlite.init_schema().await.unwrap();
+    let backends: Vec<Box<dyn PromptStorage>> =
+        vec![Box::new(FileSystemStorage::new(dir.path())), Box::new(InMemoryStorage::new()), Box::new(sqlite)];
+
+    for storage in backends {
+        let name = storage.backend_name();
+        let mut tagged = Prompt::new("tagged".to_string(), "content".to_string(), None, None);
+        tagged.tags = vec!["Code".to_string(), " review ".to_string(), "code".to_string(), "".to_string()];
+        let mut other = Prompt::new("other".to_string(), "content".to_string(), None, None);
+        other.tags = vec!["writing".to_string()];
+        let untagged = Prompt::new("untagged".to_string(), "content".to_string(), None, None);
+        for prompt in [&tagged, &other, &untagged] {
+            storage.save_prompt(prompt).await.unwrap();
+        }
+
+        let stored = storage.get_prompt(&tagged.id).await.unwrap().unwrap();
+        assert_eq!(stored.tags, vec!["code".to_string(), "review".to_string()], "{}", name);
+        for tag in ["review", "CODE"] {
+            let found = storage.list_prompts_by_tag(tag).await.unwrap();
+            let ids: Vec<_> = found.iter().map(|p| p.id).collect();
+            assert_eq!(ids, vec![tagged.id], "{} {}", name, tag);
+        }
+        assert!(storage.list_prompts_by_tag("missing").await.unwrap().is_empty(), "{}", name);
+    }
+}
+
+#[tokio::test]
+async fn storage_setup_mistakes_are_typed_errors() {
+    use mcp_prompts_rs::storage::{create_storage, StorageOptions, StorageSetupError};
+
+    let dir = tempfile::tempdir().unwrap();
+    let options = StorageOptions {
+        prompt_dir: dir.path().to_path_buf(),
+        connection: None,
+        fsync: false,
+        namespace: None,
+    };
+
+    let err = create_storage("mongodb", &options).await.err().unwrap();
+    match err.downcast_ref::<StorageSetupError>() {
+        Some(StorageSetupError::Unsupported { name, available }) => {
+            assert_eq!(name, "mongodb");
+            assert!(available.contains(&"filesystem".to_string()) && available.contains(&"sqlite".to_string()));
+        }
+        other => panic!("expected an unsupported storage error, got {:?}", other),
+    }
+    assert!(err.to_string().contains("expected one of: filesystem, memory"), "{}", err);
+
+    for backend in ["postgres", "redis", "sqlite"] {
+        let err = create_storage(backend, &options).await.err().unwrap();
+        assert_eq!(
+            err.downcast_ref::<StorageSetupError>(),
+            Some(&StorageSetupError::MissingConnection { backend: backend.to_string() }),
+            "{:#}",
+            err
+        );
+    }
+}
+
+#[tokio::test]
+async fn etags_are_unchanged_by_a_save_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let mut prompt = Prompt::new("etag".to_string(), "v1".to_string(), None, None);
+    storage.save_prompt(&prompt).await.unwrap();
+    let loaded = storage.get_prompt(&prompt.id).await.unwrap().unwrap();
+    assert_eq!(loaded.etag(), prompt.etag());
+
+    // The backend sets a new updated_at, but what readers see next is the tag of what was saved
+    prompt.content = "v2".to_string();
+    prompt.version += 1;
+    storage.save_prompt(&prompt).await.unwrap();
+    let stored = storage.get_prompt(&prompt.id).await.unwrap().unwrap();
+    assert_eq!(stored.etag(), prompt.etag());
+}
+
+#[tokio::test]
+async fn self_test_leaves_neither_prompts_nor_tombstones_behind() {
+    use mcp_prompts_rs::selftest::run_self_test;
+    use mcp_prompts_rs::storage::memory::InMemoryStorage;
+    use mcp_prompts_rs::storage::sqlite::SqliteStorage;
+    use mcp_prompts_rs::storage::StorageConfig;
+
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite://{}", dir.path().join("prompts.db").display());
+    let sqlite = SqliteStorage::new(&StorageConfig::new(url.as_str())).await.unwrap();
+    sqlite.init_schema().await.unwrap();
+    let backends: Vec<Box<dyn PromptStorage>> =
+        vec![Box::new(FileSystemStorage::new(dir.path())), Box::new(InMemoryStorage::new()), Box::new(sqlite)];
+
+    for storage in backends {
+        let name = storage.backend_name();
+        run_self_test(storage.as_ref()).await.unwrap();
+        assert!(storage.list_prompts().await.unwrap().is_empty(), "{}", name);
+
+        // Purging a real prompt makes it read as never having existed
+        let prompt = Prompt::new("scratch".to_string(), "content".to_string(), None, None);
+        storage.save_prompt(&prompt).await.unwrap();
+        assert!(storage.purge_prompt(&prompt.id).await.unwrap(), "{}", name);
+        assert!(!storage.is_deleted(&prompt.id).await.unwrap(), "{}", name);
+        assert!(!storage.purge_prompt(&prompt.id).await.unwrap(), "{}", name);
+    }
+    assert!(std::fs::read_dir(dir.path()).unwrap().all(|entry| {
+        entry.unwrap().path().extension().is_none_or(|ext| ext != "deleted")
+    }));
+}
+
+#[tokio::test]
+async fn conditional_delete_only_removes_the_expected_version() {
+    use mcp_prompts_rs::storage::memory::InMemoryStorage;
+    use mcp_prompts_rs::storage::sqlite::SqliteStorage;
+    use mcp_prompts_rs::storage::{DeleteOutcome, StorageConfig};
+
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite://{}", dir.path().join("prompts.db").display());
+    let sqlite = SqliteStorage::new(&StorageConfig::new(url.as_str())).await.unwrap();
+    sqlite.init_schema().await.unwrap();
+    let backends: Vec<Box<dyn PromptStorage>> =
+        vec![Box::new(FileSystemStorage::new(dir.path())), Box::new(InMemoryStorage::new()), Box::new(sqlite)];
+
+    for storage in backends {
+        let name = storage.backend_name();
+        let mut prompt = Prompt::new("guarded".to_string(), "first".to_string(), None, None);
+        storage.save_prompt(&prompt).await.unwrap();
+        prompt.version += 1;
+        storage.save_prompt(&prompt).await.unwrap();
+
+        let outcome = storage.delete_prompt_if_version(&prompt.id, 1).await.unwrap();
+        assert_eq!(outcome, DeleteOutcome::VersionMismatch { current: 2 }, "{}", name);
+        assert!(storage.get_prompt(&prompt.id).await.unwrap().is_some(), "{}", name);
+
+        let outcome = storage.delete_prompt_if_version(&prompt.id, 2).await.unwrap();
+        assert_eq!(outcome, DeleteOutcome::Deleted, "{}", name);
+        assert_eq!(storage.get_prompt(&prompt.id).await.unwrap(), None, "{}", name);
+
+        let outcome = storage.delete_prompt_if_version(&prompt.id, 2).await.unwrap();
+        assert_eq!(outcome, DeleteOutcome::NotFound, "{}", name);
+    }
+}
+
+#[tokio::test]
+async fn filesystem_category_listings_read_only_matching_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let wanted = Prompt::new("a-wanted".to_string(), "content".to_string(), Some("writing/email".to_string()), None);
+    let other = Prompt::new("b-other".to_string(), "content".to_string(), Some("code".to_string()), None);
+    storage.save_prompt(&wanted).await.unwrap();
+    storage.save_prompt(&other).await.unwrap();
+    assert_eq!(storage.list_prompt_summaries().await.unwrap().len(), 2);
+
+    // Corrupt the other file without changing its modification time, so the index still trusts it
+    let path = dir.path().join(format!("{}.json", other.id));
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    std::fs::write(&path, b"not json").unwrap();
+    std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+
+    let by_category = storage.list_prompts_by_category("writing/email").await.unwrap();
+    assert_eq!(by_category.iter().map(|p| p.id).collect::<Vec<_>>(), vec![wanted.id]);
+    let by_prefix = storage.list_prompts_by_category_prefix("writing").await.unwrap();
+    assert_eq!(by_prefix.iter().map(|p| p.id).collect::<Vec<_>>(), vec![wanted.id]);
+    let page = storage.list_prompts_paginated(0, 1).await.unwrap();
+    assert_eq!(page.iter().map(|p| p.id).collect::<Vec<_>>(), vec![wanted.id]);
+    assert_eq!(storage.count_prompts().await.unwrap(), 2);
+}
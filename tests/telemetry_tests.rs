@@ -0,0 +1,19 @@
+use mcp_prompts_rs::telemetry::{self, SERVICE_NAME};
+
+#[test]
+fn service_name_is_the_crate_name() {
+    assert_eq!(SERVICE_NAME, "mcp-prompts-rs");
+}
+
+// The batch exporter connects lazily, so no collector needs to be listening
+#[tokio::test(flavor = "multi_thread")]
+async fn builds_a_tracer_for_a_collector_url_and_shuts_down() {
+    assert!(telemetry::otlp_tracer("http://127.0.0.1:4317").is_ok());
+    telemetry::shutdown();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejects_an_endpoint_that_is_not_a_url() {
+    let err = telemetry::otlp_tracer("not a url").unwrap_err();
+    assert!(format!("{:#}", err).contains("not a url"));
+}
@@ -0,0 +1,548 @@
+use mcp_prompts_rs::lint::{lint_prompt, LintKind};
+use mcp_prompts_rs::variables::template_variables;
+use mcp_prompts_rs::models::prompt::{Delimiters, Prompt};
+use mcp_prompts_rs::sandbox;
+use mcp_prompts_rs::storage::filesystem::FileSystemStorage;
+use mcp_prompts_rs::template::{
+    detect_engine, render_prompt, run_with_limits, validate_prompt, EngineMode, RenderError, RenderLimits, TemplateEngine,
+    DEFAULT_MAX_TEMPLATE_DEPTH,
+};
+use serde_json::{json, Map, Value};
+
+fn arguments(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map,
+        _ => panic!("expected a JSON object"),
+    }
+}
+
+#[tokio::test]
+async fn custom_delimiters_keep_literal_braces() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let mut prompt = Prompt::new(
+        "syntax-doc".to_string(),
+        "Write {{ name }} or {% if x %} in Tera; {<% subject %>} is <% subject | upper %>.".to_string(),
+        None,
+        None,
+    );
+    prompt.delimiters = Some(Delimiters {
+        start: "<%".to_string(),
+        end: "%>".to_string(),
+    });
+
+    let rendered = render_prompt(&storage, &prompt, &arguments(json!({ "subject": "rust" })), EngineMode::Tera, DEFAULT_MAX_TEMPLATE_DEPTH)
+        .await
+        .unwrap();
+    assert_eq!(rendered, "Write {{ name }} or {% if x %} in Tera; {rust} is RUST.");
+}
+
+#[tokio::test]
+async fn default_delimiters_are_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let prompt = Prompt::new("plain".to_string(), "Hello {{ name }}!".to_string(), None, None);
+
+    let rendered = render_prompt(&storage, &prompt, &arguments(json!({ "name": "Ada" })), EngineMode::Tera, DEFAULT_MAX_TEMPLATE_DEPTH)
+        .await
+        .unwrap();
+    assert_eq!(rendered, "Hello Ada!");
+}
+
+#[tokio::test]
+async fn render_errors_name_missing_variables_and_syntax_problems() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let mut prompt = Prompt::new("greet".to_string(), "Dear {{ title }} {{ name }},".to_string(), None, None);
+
+    let rendered = render_prompt(&storage, &prompt, &arguments(json!({ "title": "Dr", "name": "Ada" })), EngineMode::Tera, DEFAULT_MAX_TEMPLATE_DEPTH)
+        .await
+        .unwrap();
+    assert_eq!(rendered, "Dear Dr Ada,");
+
+    // A missing variable fails the render instead of rendering as empty text
+    let missing = render_prompt(&storage, &prompt, &arguments(json!({ "title": "Dr" })), EngineMode::Tera, DEFAULT_MAX_TEMPLATE_DEPTH).await;
+    match missing {
+        Err(RenderError::Template(msg)) => assert!(msg.contains("name"), "{}", msg),
+        other => panic!("expected a template error, got {:?}", other),
+    }
+
+    prompt.content = "Dear {{ name ,".to_string();
+    let invalid = render_prompt(&storage, &prompt, &arguments(json!({ "name": "Ada" })), EngineMode::Tera, DEFAULT_MAX_TEMPLATE_DEPTH).await;
+    assert!(matches!(invalid, Err(RenderError::Template(_))), "{:?}", invalid);
+}
+
+mod references {
+    use super::arguments;
+    use mcp_prompts_rs::models::prompt::Prompt;
+    use mcp_prompts_rs::references::{dependents_of, with_references, Dependency, MissingReferencePolicy};
+    use mcp_prompts_rs::storage::filesystem::FileSystemStorage;
+    use mcp_prompts_rs::storage::PromptStorage;
+    use mcp_prompts_rs::template::{render_prompt, EngineMode, DEFAULT_MAX_TEMPLATE_DEPTH};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    async fn render(storage: &FileSystemStorage, prompt: &Prompt, policy: MissingReferencePolicy) -> Result<String, String> {
+        render_to_depth(storage, prompt, policy, DEFAULT_MAX_TEMPLATE_DEPTH).await
+    }
+
+    async fn render_to_depth(
+        storage: &FileSystemStorage,
+        prompt: &Prompt,
+        policy: MissingReferencePolicy,
+        max_depth: usize,
+    ) -> Result<String, String> {
+        let args = with_references(storage, prompt, &arguments(json!({ "name": "Ada" })), policy, EngineMode::Tera, max_depth)
+            .await
+            .map_err(|e| e.to_string())?;
+        render_prompt(storage, prompt, &args, EngineMode::Tera, max_depth).await.map_err(|e| e.to_string())
+    }
+
+    #[tokio::test]
+    async fn referenced_prompts_are_rendered_inline() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileSystemStorage::new(dir.path());
+        let preamble = Prompt::new("preamble".to_string(), "Hi {{ name }}.".to_string(), None, None);
+        storage.save_prompt(&preamble).await.unwrap();
+        let mut prompt = Prompt::new("main".to_string(), "{{ refs.intro }} Bye.".to_string(), None, None);
+        prompt.references.insert("intro".to_string(), preamble.id);
+
+        assert_eq!(render(&storage, &prompt, MissingReferencePolicy::Error).await.unwrap(), "Hi Ada. Bye.");
+    }
+
+    #[tokio::test]
+    async fn missing_reference_policy_controls_the_outcome() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileSystemStorage::new(dir.path());
+        let missing = Uuid::new_v4();
+        let mut prompt = Prompt::new("main".to_string(), "[{{ refs.gone }}]".to_string(), None, None);
+        prompt.references.insert("gone".to_string(), missing);
+
+        assert!(render(&storage, &prompt, MissingReferencePolicy::Error).await.is_err());
+        assert_eq!(
+            render(&storage, &prompt, MissingReferencePolicy::Placeholder).await.unwrap(),
+            format!("[[missing: {}]]", missing)
+        );
+        assert_eq!(render(&storage, &prompt, MissingReferencePolicy::Skip).await.unwrap(), "[]");
+    }
+
+    #[tokio::test]
+    async fn prompt_function_inlines_prompts_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileSystemStorage::new(dir.path());
+        let preamble = Prompt::new("preamble".to_string(), "Hi {{ name }}.".to_string(), None, None);
+        storage.save_prompt(&preamble).await.unwrap();
+        let prompt = Prompt::new("main".to_string(), "{{ prompt(name='preamble') }} Bye.".to_string(), None, None);
+
+        assert_eq!(render(&storage, &prompt, MissingReferencePolicy::Error).await.unwrap(), "Hi Ada. Bye.");
+
+        let gone = Prompt::new("main".to_string(), "[{{ prompt(name=\"gone\") }}]".to_string(), None, None);
+        assert!(render(&storage, &gone, MissingReferencePolicy::Error).await.is_err());
+        assert_eq!(render(&storage, &gone, MissingReferencePolicy::Placeholder).await.unwrap(), "[[missing: gone]]");
+    }
+
+    #[tokio::test]
+    async fn prompt_function_detects_cycles() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileSystemStorage::new(dir.path());
+        let looping = Prompt::new("loop".to_string(), "{{ prompt(name=\"loop\") }}".to_string(), None, None);
+        storage.save_prompt(&looping).await.unwrap();
+
+        assert!(render(&storage, &looping, MissingReferencePolicy::Error).await.unwrap_err().contains("cycle"));
+    }
+
+    #[test]
+    fn dependents_lists_references_extends_and_includes() {
+        let target = Prompt::new("preamble".to_string(), "Hi.".to_string(), None, None);
+        let mut referencing = Prompt::new("a".to_string(), "{{ refs.intro }} {{ prompt(name='preamble') }}".to_string(), None, None);
+        referencing.references.insert("intro".to_string(), target.id);
+        let mut extending = Prompt::new("b".to_string(), "{% block body %}{% endblock %}".to_string(), None, None);
+        extending.extends = Some(target.id);
+        let unrelated = Prompt::new("c".to_string(), "{{ prompt(name='other') }}".to_string(), None, None);
+
+        let prompts = vec![target.clone(), referencing.clone(), unrelated, extending.clone()];
+        let dependents = dependents_of(&target, &prompts);
+        let ids: Vec<Uuid> = dependents.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![referencing.id, extending.id]);
+        assert_eq!(
+            dependents[0].via,
+            vec![Dependency::Reference { alias: "intro".to_string() }, Dependency::Include]
+        );
+        assert_eq!(dependents[1].via, vec![Dependency::Extends]);
+        assert_eq!(
+            serde_json::to_value(&dependents[0].via[0]).unwrap(),
+            json!({ "kind": "reference", "alias": "intro" })
+        );
+    }
+
+    #[tokio::test]
+    async fn depth_limit_counts_references_and_extends_together() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileSystemStorage::new(dir.path());
+        let base = Prompt::new("base".to_string(), "[{% block body %}{% endblock %}]".to_string(), None, None);
+        let mut child = Prompt::new("child".to_string(), "{% block body %}Hi {{ name }}{% endblock %}".to_string(), None, None);
+        child.extends = Some(base.id);
+        storage.save_prompt(&base).await.unwrap();
+        storage.save_prompt(&child).await.unwrap();
+        let mut prompt = Prompt::new("main".to_string(), "{{ refs.greeting }}!".to_string(), None, None);
+        prompt.references.insert("greeting".to_string(), child.id);
+
+        // main -> child (reference) -> base (extends) is two levels deep
+        assert_eq!(render_to_depth(&storage, &prompt, MissingReferencePolicy::Error, 2).await.unwrap(), "[Hi Ada]!");
+        let error = render_to_depth(&storage, &prompt, MissingReferencePolicy::Error, 1).await.unwrap_err();
+        assert!(error.contains("more than 1 levels deep"));
+        assert!(render_to_depth(&storage, &child, MissingReferencePolicy::Error, 0).await.is_err());
+    }
+}
+
+#[test]
+fn validate_prompt_reports_syntax_errors_in_content_and_sections() {
+    let mut prompt = Prompt::new("valid".to_string(), "Hi {{ name }}{% if x %}!{% endif %}".to_string(), None, None);
+    assert!(validate_prompt(&prompt, EngineMode::Tera).is_ok());
+
+    prompt.content = "Hi {{ name".to_string();
+    assert!(validate_prompt(&prompt, EngineMode::Tera).unwrap_err().contains("content"));
+
+    prompt.content = "Hi".to_string();
+    prompt.sections = Some([("intro".to_string(), "{% if x %}".to_string())].into_iter().collect());
+    assert!(validate_prompt(&prompt, EngineMode::Tera).unwrap_err().contains("intro"));
+}
+
+#[test]
+fn validate_prompt_accepts_literal_braces_with_custom_delimiters() {
+    let mut prompt = Prompt::new("doc".to_string(), "Write {{ x to insert <% name %>".to_string(), None, None);
+    prompt.delimiters = Some(Delimiters { start: "<%".to_string(), end: "%>".to_string() });
+    assert!(validate_prompt(&prompt, EngineMode::Tera).is_ok());
+}
+
+#[test]
+fn detect_engine_recognizes_tera_statements_and_comments() {
+    assert_eq!(detect_engine("{% if x %}yes{% endif %}"), Some(TemplateEngine::Tera));
+    assert_eq!(detect_engine("{# note #}Hello {{ name }}"), Some(TemplateEngine::Tera));
+}
+
+#[test]
+fn detect_engine_recognizes_handlebars_blocks() {
+    assert_eq!(detect_engine("{{#if x}}yes{{/if}}"), Some(TemplateEngine::Handlebars));
+    assert_eq!(detect_engine("{{#each items}}{{this}}{{/each}}"), Some(TemplateEngine::Handlebars));
+    assert_eq!(detect_engine("{{> header}} {{! comment }}"), Some(TemplateEngine::Handlebars));
+}
+
+#[test]
+fn detect_engine_is_unsure_about_plain_or_mixed_content() {
+    assert_eq!(detect_engine("Hello {{ name }}"), None);
+    assert_eq!(detect_engine("No tags at all"), None);
+    assert_eq!(detect_engine("{% if x %}{{#if y}}{{/if}}{% endif %}"), None);
+}
+
+#[tokio::test]
+async fn auto_mode_renders_each_prompt_with_its_detected_engine() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let args = arguments(json!({ "items": ["a", "b"] }));
+
+    let handlebars = Prompt::new("hbs".to_string(), "{{#each items}}[{{this}}]{{/each}}".to_string(), None, None);
+    let rendered = render_prompt(&storage, &handlebars, &args, EngineMode::Auto, DEFAULT_MAX_TEMPLATE_DEPTH).await.unwrap();
+    assert_eq!(rendered, "[a][b]");
+
+    let tera = Prompt::new("tera".to_string(), "{% for i in items %}({{ i }}){% endfor %}".to_string(), None, None);
+    let rendered = render_prompt(&storage, &tera, &args, EngineMode::Auto, DEFAULT_MAX_TEMPLATE_DEPTH).await.unwrap();
+    assert_eq!(rendered, "(a)(b)");
+
+    // Ambiguous content falls back to Tera, so Tera-only filters work
+    let plain = Prompt::new("plain".to_string(), "{{ items | join(sep=\",\") }}".to_string(), None, None);
+    let rendered = render_prompt(&storage, &plain, &args, EngineMode::Auto, DEFAULT_MAX_TEMPLATE_DEPTH).await.unwrap();
+    assert_eq!(rendered, "a,b");
+}
+
+#[tokio::test]
+async fn stored_prompt_renders_are_capped_by_limits() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = std::sync::Arc::new(FileSystemStorage::new(dir.path()));
+    let prompt = Prompt::new("big".to_string(), "{% for i in range(end=1000) %}xxxxxxxxxx{% endfor %}".to_string(), None, None);
+    let limits = RenderLimits { timeout: std::time::Duration::from_secs(5), max_output_bytes: 100 };
+
+    let render = async move { render_prompt(storage.as_ref(), &prompt, &Map::new(), EngineMode::Tera, DEFAULT_MAX_TEMPLATE_DEPTH).await };
+    let result = run_with_limits(limits, render).await;
+    assert!(matches!(result, Err(RenderError::LimitExceeded(_))));
+}
+
+fn sandbox_limits(max_output_bytes: usize) -> RenderLimits {
+    RenderLimits { timeout: std::time::Duration::from_secs(5), max_output_bytes }
+}
+
+#[tokio::test]
+async fn sandbox_rejects_runaway_loops_before_rendering() {
+    let huge_range = "{% for i in range(end=1000000000) %}xxxxxxxx{% endfor %}";
+    let result = sandbox::render(huge_range.to_string(), Map::new(), EngineMode::Tera, sandbox_limits(65536)).await;
+    assert!(matches!(result, Err(RenderError::LimitExceeded(_))));
+
+    let nested = "{% for a in items %}{% for b in items %}{% for c in items %}.{% endfor %}{% endfor %}{% endfor %}";
+    let args = arguments(json!({ "items": vec![0; 100] }));
+    let result = sandbox::render(nested.to_string(), args, EngineMode::Tera, sandbox_limits(65536)).await;
+    assert!(matches!(result, Err(RenderError::LimitExceeded(_))));
+
+    // range() with a computed end is capped while rendering
+    let computed = "{% for i in range(end=n) %}{% endfor %}";
+    let args = arguments(json!({ "n": 1000000000 }));
+    let result = sandbox::render(computed.to_string(), args, EngineMode::Tera, sandbox_limits(65536)).await;
+    assert!(matches!(result, Err(RenderError::Template(msg)) if msg.contains("more than the 10000 allowed")));
+
+    let recursive = "{% macro f() %}{{ self::f() }}{% endmacro f %}{{ self::f() }}";
+    let result = sandbox::render(recursive.to_string(), Map::new(), EngineMode::Tera, sandbox_limits(65536)).await;
+    assert!(matches!(result, Err(RenderError::Template(msg)) if msg.contains("Macros are not supported")));
+}
+
+#[tokio::test]
+async fn sandbox_stops_rendering_at_the_output_limit() {
+    let content = "{% for i in range(end=1000) %}xxxxxxxxxx{% endfor %}";
+    let result = sandbox::render(content.to_string(), Map::new(), EngineMode::Tera, sandbox_limits(100)).await;
+    assert!(matches!(result, Err(RenderError::LimitExceeded(_))));
+
+    let rendered = sandbox::render(content.to_string(), Map::new(), EngineMode::Tera, sandbox_limits(10000)).await;
+    assert_eq!(rendered.unwrap().len(), 10000);
+}
+
+#[tokio::test]
+async fn sandbox_renders_with_the_configured_engine() {
+    let args = arguments(json!({ "items": ["a", "b"] }));
+    let content = "{{#each items}}{{this}};{{/each}}";
+    let rendered = sandbox::render(content.to_string(), args.clone(), EngineMode::Handlebars, sandbox_limits(1024)).await;
+    assert_eq!(rendered.unwrap(), "a;b;");
+    let rendered = sandbox::render(content.to_string(), args, EngineMode::Auto, sandbox_limits(1024)).await;
+    assert_eq!(rendered.unwrap(), "a;b;");
+
+    let inline = "{{#*inline \"f\"}}{{> f}}{{/inline}}{{> f}}";
+    let result = sandbox::render(inline.to_string(), Map::new(), EngineMode::Handlebars, sandbox_limits(1024)).await;
+    assert!(matches!(result, Err(RenderError::Template(msg)) if msg.contains("not supported in the sandbox")));
+}
+
+#[test]
+fn lint_reports_unbalanced_delimiters_with_lines() {
+    let prompt = Prompt::new("lint".to_string(), "Hi {{ name }}\n{% if x }}\nBye }}".to_string(), None, None);
+    let warnings = lint_prompt(&prompt, EngineMode::Tera);
+    let lines: Vec<usize> = warnings.iter().map(|w| w.line).collect();
+    assert!(warnings.iter().all(|w| w.kind == LintKind::UnbalancedDelimiter));
+    assert_eq!(lines, vec![2, 3]);
+
+    let prompt = Prompt::new("ok".to_string(), "{# {{ #}{% raw %}{{{% endraw %}".to_string(), None, None);
+    assert!(lint_prompt(&prompt, EngineMode::Tera).is_empty());
+}
+
+#[test]
+fn lint_reports_unknown_filters() {
+    let mut prompt = Prompt::new("lint".to_string(), "{{ name | upper | shout }}{{ \"a|b\" }}".to_string(), None, None);
+    prompt.sections = Some([("intro".to_string(), "{% filter yell %}x{% endfilter %}".to_string())].into_iter().collect());
+    let warnings = lint_prompt(&prompt, EngineMode::Tera);
+    let messages: Vec<(&str, &str)> = warnings.iter().map(|w| (w.location.as_str(), w.message.as_str())).collect();
+    assert_eq!(
+        messages,
+        vec![("content", "Unknown filter 'shout'"), ("section 'intro'", "Unknown filter 'yell'")]
+    );
+}
+
+#[test]
+fn lint_reports_undeclared_variables_once() {
+    let content = "{{ name }} {{ topic }} {{ topic }}{% for item in items %}{{ item }}{% endfor %}{% set n = 1 %}{{ n }}";
+    let mut prompt = Prompt::new("lint".to_string(), content.to_string(), None, Some(vec!["name".to_string()]));
+    prompt.default_arguments.insert("items".to_string(), json!([]));
+    let warnings = lint_prompt(&prompt, EngineMode::Tera);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].kind, LintKind::UndeclaredVariable);
+    assert!(warnings[0].message.contains("'topic'"));
+
+    prompt.variables = None;
+    assert!(lint_prompt(&prompt, EngineMode::Tera).is_empty());
+}
+
+#[tokio::test]
+async fn render_messages_maps_role_sections_to_chat_messages() {
+    use mcp_prompts_rs::messages::{render_messages, ChatMessage, Role};
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let mut prompt = Prompt::new("chat".to_string(), "Explain {{ topic }}.".to_string(), None, None);
+    let args = arguments(json!({ "topic": "lifetimes" }));
+
+    let messages = render_messages(&storage, &prompt, &args, EngineMode::Tera, DEFAULT_MAX_TEMPLATE_DEPTH).await.unwrap();
+    assert_eq!(messages, vec![ChatMessage { role: Role::User, content: "Explain lifetimes.".to_string() }]);
+
+    prompt.sections = Some(
+        [("system", "You teach {{ topic }}."), ("assistant", "Sure:"), ("notes", "unused")]
+            .into_iter()
+            .map(|(name, text)| (name.to_string(), text.to_string()))
+            .collect(),
+    );
+    let messages = render_messages(&storage, &prompt, &args, EngineMode::Tera, DEFAULT_MAX_TEMPLATE_DEPTH).await.unwrap();
+    let roles: Vec<Role> = messages.iter().map(|m| m.role).collect();
+    assert_eq!(roles, vec![Role::System, Role::User, Role::Assistant]);
+    assert_eq!(messages[0].content, "You teach lifetimes.");
+    assert_eq!(serde_json::to_value(&messages[2]).unwrap(), json!({ "role": "assistant", "content": "Sure:" }));
+}
+
+#[test]
+fn template_variables_lists_used_variables_in_first_seen_order() {
+    let content = "{% if formal %}Dear{% endif %} {{ name | upper }}, {% for item in items %}{{ item.title }} {{ name }}{% endfor %}{% set n = count %}{{ n }}";
+    assert_eq!(template_variables(content), vec!["formal", "name", "items", "count"]);
+}
+
+#[test]
+fn declare_used_variables_replaces_declared_list_and_keeps_metadata() {
+    use mcp_prompts_rs::models::prompt::VariableMetadata;
+    use mcp_prompts_rs::variables::declare_used_variables;
+
+    let mut prompt = Prompt::new(
+        "auto".to_string(),
+        "{{ refs.intro }} Hi {{ user }}, {{ globals.company }} {{ topic }}".to_string(),
+        None,
+        Some(vec!["stale".to_string()]),
+    );
+    prompt.sections = Some([("notes".to_string(), "{{ extra }} {{ user }}".to_string())].into_iter().collect());
+    prompt.variable_metadata.insert(
+        "user".to_string(),
+        VariableMetadata { aliases: vec!["name".to_string()] },
+    );
+    prompt.default_arguments.insert("topic".to_string(), json!("rust"));
+
+    declare_used_variables(&mut prompt).unwrap();
+    assert_eq!(
+        prompt.variables,
+        Some(vec!["user".to_string(), "topic".to_string(), "extra".to_string()])
+    );
+    assert_eq!(prompt.variable_metadata["user"].aliases, vec!["name".to_string()]);
+    assert_eq!(prompt.default_arguments["topic"], json!("rust"));
+}
+
+#[tokio::test]
+async fn prompts_include_snippets_loaded_from_a_directory() {
+    use mcp_prompts_rs::snippets::{self, SnippetLibrary};
+    use mcp_prompts_rs::storage::PromptStorage;
+
+    let snippets_dir = tempfile::tempdir().unwrap();
+    std::fs::write(snippets_dir.path().join("greeting.txt"), "Hello {{ name }}").unwrap();
+    std::fs::write(snippets_dir.path().join("signoff.md"), "{% include \"snippet:greeting\" %}, bye!").unwrap();
+    std::fs::write(snippets_dir.path().join(".hidden"), "{{ unclosed").unwrap();
+    let library = SnippetLibrary::load_dir(snippets_dir.path()).await.unwrap();
+    assert_eq!(library.names(), ["greeting", "signoff"]);
+    snippets::install(library);
+
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let prompt = Prompt::new("uses-snippet".to_string(), "{% include \"snippet:signoff\" %}".to_string(), None, None);
+    let rendered = render_prompt(&storage, &prompt, &arguments(json!({ "name": "Ada" })), EngineMode::Tera, DEFAULT_MAX_TEMPLATE_DEPTH)
+        .await
+        .unwrap();
+    assert_eq!(rendered, "Hello Ada, bye!");
+    assert!(storage.list_prompts().await.unwrap().is_empty());
+
+    std::fs::write(snippets_dir.path().join("broken.txt"), "{% if %}").unwrap();
+    assert!(SnippetLibrary::load_dir(snippets_dir.path()).await.is_err());
+}
+
+#[test]
+fn warmup_compiles_content_and_sections_and_reports_failures() {
+    use mcp_prompts_rs::template::warm_template_cache;
+    use std::collections::BTreeMap;
+
+    let mut prompt = Prompt::new("warm".to_string(), "Hello {{ name }}".to_string(), None, None);
+    prompt.sections = Some(BTreeMap::from([("intro".to_string(), "{{ topic }}".to_string())]));
+    assert_eq!(warm_template_cache(&prompt, EngineMode::Tera).unwrap(), 2);
+    assert_eq!(warm_template_cache(&prompt, EngineMode::Handlebars).unwrap(), 1);
+
+    prompt.content = "{% if %}".to_string();
+    assert!(warm_template_cache(&prompt, EngineMode::Tera).is_err());
+}
+
+#[tokio::test]
+async fn prompts_pick_their_own_template_engine() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileSystemStorage::new(dir.path());
+    let args = arguments(json!({ "name": "Ada" }));
+    let render = |prompt: Prompt| {
+        let (storage, args) = (&storage, &args);
+        async move { render_prompt(storage, &prompt, args, EngineMode::Tera, DEFAULT_MAX_TEMPLATE_DEPTH).await.unwrap() }
+    };
+
+    // The same substitution through both engines, whatever the server-wide mode says
+    let tera = Prompt::new("tera".to_string(), "Hello {{ name }}!".to_string(), None, None);
+    assert_eq!(render(tera).await, "Hello Ada!");
+    let mut handlebars = Prompt::new("hbs".to_string(), "Hello {{name}}!{{#if name}} Welcome.{{/if}}".to_string(), None, None);
+    handlebars.template_engine = Some(TemplateEngine::Handlebars);
+    assert_eq!(render(handlebars).await, "Hello Ada! Welcome.");
+
+    // None skips rendering even with arguments, so template syntax comes back untouched
+    let mut raw = Prompt::new("raw".to_string(), "Hello {{ name }} {% if x %}".to_string(), None, None);
+    raw.template_engine = Some(TemplateEngine::None);
+    assert!(validate_prompt(&raw, EngineMode::Tera).is_ok());
+    assert_eq!(render(raw.clone()).await, raw.content);
+    let round_trip: Prompt = serde_json::from_value(serde_json::to_value(&raw).unwrap()).unwrap();
+    assert_eq!(round_trip.template_engine, Some(TemplateEngine::None));
+}
+
+#[test]
+fn rename_variable_leaves_string_literals_alone() {
+    use mcp_prompts_rs::variables::rename_variable;
+
+    let (renamed, count) = rename_variable(
+        r#"{{ user }} {{ "user" }} {{ 'user \' user' ~ user }} {% if user == "user" %}user{% endif %}"#,
+        "user",
+        "member",
+    );
+    assert_eq!(
+        renamed,
+        r#"{{ member }} {{ "user" }} {{ 'user \' user' ~ member }} {% if member == "user" %}user{% endif %}"#
+    );
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn rename_variable_skips_raw_blocks_and_comments() {
+    use mcp_prompts_rs::variables::rename_variable;
+
+    let content = "{{ user }} {% raw %}{{ user }}{% endraw %} {%- raw -%} {% if user %} {%- endraw %} {# {{ user }} #} {{ user }}";
+    let (renamed, count) = rename_variable(content, "user", "member");
+    assert_eq!(
+        renamed,
+        "{{ member }} {% raw %}{{ user }}{% endraw %} {%- raw -%} {% if user %} {%- endraw %} {# {{ user }} #} {{ member }}"
+    );
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn rename_variable_keeps_filters_attributes_and_longer_names() {
+    use mcp_prompts_rs::variables::rename_variable;
+
+    let content = "{{ user | user }} {{ user.user }} {{ users }} {{ username | default(value=user) }} {{ upper(user=user) }}";
+    let (renamed, count) = rename_variable(content, "user", "member");
+    assert_eq!(
+        renamed,
+        "{{ member | user }} {{ member.user }} {{ users }} {{ username | default(value=member) }} {{ upper(user=member) }}"
+    );
+    assert_eq!(count, 4);
+}
+
+#[test]
+fn partial_render_keeps_expressions_without_variables() {
+    use mcp_prompts_rs::template::render_partial;
+
+    let content = "Hi {{ name }}! {% for item in items %}#{{ loop.index }} {{ item }} {{ 1 + 1 }}{% endfor %}";
+    let rendered = render_partial(content, &arguments(json!({ "name": "Ada" }))).unwrap();
+    assert_eq!(rendered, "Hi Ada! {% for item in items %}#{{ loop.index }} {{ item }} {{ 1 + 1 }}{% endfor %}");
+}
+
+#[test]
+fn template_cache_evicts_the_least_recently_used_template() {
+    use mcp_prompts_rs::template::TemplateCache;
+
+    let mut cache = TemplateCache::new(2);
+    cache.insert("old", tera::Tera::default());
+    cache.insert("recent", tera::Tera::default());
+    assert!(cache.get("old").is_some());
+
+    // "recent" was used least recently now, so it makes room for the new template
+    cache.insert("new", tera::Tera::default());
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get("recent").is_none());
+    assert!(cache.get("old").is_some());
+    assert!(cache.get("new").is_some());
+}